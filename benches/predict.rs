@@ -0,0 +1,49 @@
+//! Times shot prediction, full and fast, on a soft shot into the rack, a
+//! shot across an open table and a break.
+//!
+//! Run with `cargo bench`. Each case reports the mean time per call.
+//! `cargo test --all-targets` builds this too, but without the `--bench`
+//! flag it exits straight away.
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use rust_pool_sim::{tick, Ball, CueStroke, GameState, Prediction, Table};
+
+/// Calls made per case.
+const CALLS: u32 = 200;
+
+fn time(name: &str, predict: impl Fn() -> Prediction) {
+    let start = Instant::now();
+    for _ in 0..CALLS {
+        black_box(predict());
+    }
+    let per_call = start.elapsed() / CALLS;
+    println!("{name:<24} {:>8.1} us/call", per_call.as_secs_f64() * 1e6);
+}
+
+fn main() {
+    if !std::env::args().any(|arg| arg == "--bench") {
+        return;
+    }
+    let state = GameState::eight_ball(&Table::pool(800.0, 400.0), 7, 0.0);
+    let soft = CueStroke::new(0.0, 250.0, 0.0, 0.0, 0.0);
+    let brk = CueStroke::new(0.0, 900.0, 0.0, 0.0, 0.0);
+
+    time("predict, soft shot", || state.predict(&soft, 20.0));
+    time("predict_fast, soft shot", || {
+        state.predict_fast(&soft, 20.0)
+    });
+    let mut spread = state.clone();
+    assert!(spread.strike(&brk));
+    while spread.balls().iter().any(Ball::is_moving) {
+        tick(&mut spread, 1.0 / 60.0);
+    }
+    let aim = CueStroke::new(2.0, 500.0, 0.0, 0.2, 0.0);
+    time("predict, open table", || spread.predict(&aim, 20.0));
+    time("predict_fast, open table", || {
+        spread.predict_fast(&aim, 20.0)
+    });
+    time("predict, break", || state.predict(&brk, 20.0));
+    time("predict_fast, break", || state.predict_fast(&brk, 20.0));
+}
@@ -0,0 +1,1058 @@
+//! Computer opponent.
+//!
+//! The [`Planner`] looks at the table the way a player would. It lists the
+//! shots on offer for the balls the rules allow it to hit: direct pots,
+//! banks off one or two cushions, two-ball combinations, safeties and, when
+//! a ball is hidden, kicks off the cushions. Each is ranked by
+//! how easy it looks, and the most promising strokes are played out, rules
+//! and all, on copies of the game. A stroke is worth the points it scores
+//! and whether it keeps the table, less any foul, plus how easy it leaves the
+//! next shot for whoever plays it. The best stroke is then spoiled by a
+//! little noise to match the planner's [`Difficulty`].
+//!
+//! The work is bounded by an iteration count, the number of strokes played
+//! out per decision, rather than by the clock, which WebAssembly lacks; the
+//! same seed and table always give the same decision.
+
+use wasm_bindgen::prelude::*;
+
+use crate::bank::{bank_paths, BankPath};
+use crate::math;
+use crate::pocket::{self, Pocket};
+use crate::rng::Rng;
+use crate::{tick, Ball, CueStroke, GameState, Vector2D, GRAVITY, UNITS_PER_METER};
+
+/// Simulation time, in seconds, advanced per step while playing a stroke out.
+const PLAYOUT_STEP: f32 = 0.25;
+
+/// Default cap, in seconds, on the time a stroke is played out for.
+const DEFAULT_MAX_TIME: f32 = 15.0;
+
+/// Fastest stroke the planner will play, in units per second.
+const MAX_SPEED: f32 = 9.0 * UNITS_PER_METER;
+
+/// Extra distance, in units, a potted ball is sent past the pocket.
+const POT_SLACK: f32 = 0.2 * UNITS_PER_METER;
+
+/// Largest cut the planner will attempt, as the cosine of the angle.
+const MIN_CUT_COSINE: f32 = 0.2;
+
+/// Thinnest cut a combination's second ball may take, as the cosine of
+/// the angle.
+const MIN_COMBINATION_CUT_COSINE: f32 = 0.5;
+
+/// How much easier a direct pot looks than a bank of the same length, for
+/// each cushion the bank uses.
+const BANK_EASE: f32 = 0.5;
+
+/// Most cushions the planner banks or kicks off.
+const BANK_RAILS: u32 = 2;
+
+/// How much easier a direct pot looks than a combination.
+const COMBINATION_EASE: f32 = 0.4;
+
+/// Ease given to safeties when ranking strokes to play out.
+const SAFETY_EASE: f32 = 0.15;
+
+/// Ease given to kicks when ranking strokes to play out.
+const KICK_EASE: f32 = 0.1;
+
+/// How much faster than needed to roll the length of its path a kick is
+/// played, to allow for what the cushions take.
+const KICK_SPEED: f32 = 1.5;
+
+/// Angle, in radians, of the thin hits tried as safeties.
+const SAFETY_CUT: f32 = 1.0;
+
+/// Value of winning the game, or the negative of losing it.
+const WIN_VALUE: f32 = 1000.0;
+
+/// Value of each point scored, net of the opponent's.
+const POINT_VALUE: f32 = 10.0;
+
+/// Value of staying at the table.
+const TURN_VALUE: f32 = 40.0;
+
+/// Cost of committing a foul.
+const FOUL_COST: f32 = 50.0;
+
+/// Value of the easiest shot left for the next player, at full weight.
+const POSITION_VALUE: f32 = 30.0;
+
+/// Stroke variants tried for a pot: speed factor, vertical tip offset and
+/// how highly the variant ranks.
+const POT_VARIANTS: [(f32, f32, f32); 6] = [
+    (1.0, 0.0, 1.0),
+    (0.75, 0.0, 0.8),
+    (1.4, 0.0, 0.8),
+    (1.0, 0.5, 0.7),
+    (1.0, -0.5, 0.7),
+    (2.0, 0.0, 0.5),
+];
+
+/// Stroke variants tried for a safety, from a touch to a full break.
+const SAFETY_VARIANTS: [(f32, f32, f32); 3] = [(1.0, 0.0, 1.0), (2.5, 0.0, 0.8), (20.0, 0.0, 0.6)];
+
+/// Stroke variants tried for a kick, whose path holds only at the speed it
+/// was found for.
+const KICK_VARIANTS: [(f32, f32, f32); 1] = [(1.0, 0.0, 1.0)];
+
+/// Distances, in ball radii, behind the ghost ball tried for ball in hand.
+const PLACEMENT_DISTANCES: [f32; 3] = [4.0, 8.0, 14.0];
+
+/// Angles, in radians, off the line of a pot tried for ball in hand.
+const PLACEMENT_ANGLES: [f32; 5] = [0.0, 0.35, -0.35, 0.7, -0.7];
+
+/// How well the computer plays.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Difficulty {
+    /// Looks only for pots, plays few strokes out and cues loosely.
+    Beginner = 0,
+    /// Thinks about position some of the time and cues fairly well.
+    Amateur = 1,
+    /// Plans position and safeties and cues almost perfectly.
+    Expert = 2,
+}
+
+impl Difficulty {
+    /// Standard deviation of the aim, in radians.
+    const fn aim_error(self) -> f32 {
+        match self {
+            Self::Beginner => 0.03,
+            Self::Amateur => 0.01,
+            Self::Expert => 0.003,
+        }
+    }
+
+    /// Standard deviation of the speed, as a fraction of the speed.
+    const fn speed_error(self) -> f32 {
+        match self {
+            Self::Beginner => 0.15,
+            Self::Amateur => 0.08,
+            Self::Expert => 0.03,
+        }
+    }
+
+    /// Standard deviation of the tip offset, as a fraction of the radius.
+    const fn tip_error(self) -> f32 {
+        match self {
+            Self::Beginner => 0.15,
+            Self::Amateur => 0.08,
+            Self::Expert => 0.02,
+        }
+    }
+
+    /// Weight given to the next shot, from 0 to 1.
+    const fn position_weight(self) -> f32 {
+        match self {
+            Self::Beginner => 0.0,
+            Self::Amateur => 0.5,
+            Self::Expert => 1.0,
+        }
+    }
+
+    /// Strokes played out per decision unless configured otherwise.
+    const fn iterations(self) -> u32 {
+        match self {
+            Self::Beginner => 12,
+            Self::Amateur => 40,
+            Self::Expert => 120,
+        }
+    }
+}
+
+/// The kind of shot the planner chose.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ShotKind {
+    /// An object ball straight into a pocket.
+    Pot = 0,
+    /// An object ball off one or more cushions into a pocket.
+    Bank = 1,
+    /// An object ball into a second ball, which drops.
+    Combination = 2,
+    /// A shot played to leave the opponent nothing.
+    Safety = 3,
+    /// The cue ball off one or more cushions onto an object ball it cannot
+    /// reach directly.
+    Kick = 4,
+}
+
+/// A shot chosen by the [`Planner`], ready to be played with
+/// [`GameState::play_shot`].
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug)]
+pub struct PlannedShot {
+    /// The stroke, execution noise included.
+    stroke: CueStroke,
+    /// The kind of shot.
+    kind: ShotKind,
+    /// The ball the cue ball is sent at.
+    object_ball: u32,
+    /// The ball meant to drop.
+    called_ball: Option<u32>,
+    /// The pocket it is meant to drop in.
+    pocket: Option<Pocket>,
+    /// Where to put the cue ball first, with ball in hand.
+    cue_position: Option<Vector2D>,
+    /// How the planner valued the shot.
+    value: f32,
+}
+
+#[wasm_bindgen]
+impl PlannedShot {
+    /// Returns the stroke to play, execution noise included.
+    #[must_use]
+    pub fn stroke(&self) -> CueStroke {
+        self.stroke
+    }
+
+    /// Returns the kind of shot.
+    #[must_use]
+    pub fn kind(&self) -> ShotKind {
+        self.kind
+    }
+
+    /// Returns the id of the ball the cue ball is sent at.
+    #[must_use]
+    pub fn object_ball(&self) -> u32 {
+        self.object_ball
+    }
+
+    /// Returns the id of the ball meant to drop, or `undefined` for a
+    /// safety or a kick.
+    #[must_use]
+    pub fn called_ball(&self) -> Option<u32> {
+        self.called_ball
+    }
+
+    /// Returns the pocket the called ball is meant to drop in.
+    #[must_use]
+    pub fn pocket(&self) -> Option<Pocket> {
+        self.pocket
+    }
+
+    /// Returns where to place the cue ball before the stroke, if the player
+    /// has ball in hand.
+    #[must_use]
+    pub fn cue_position(&self) -> Option<Vector2D> {
+        self.cue_position
+    }
+
+    /// Returns the value the planner gave the shot; higher is better.
+    #[must_use]
+    pub fn value(&self) -> f32 {
+        self.value
+    }
+}
+
+/// A shot on offer, before any stroke is played out.
+#[derive(Clone, Copy, Debug)]
+struct Candidate {
+    /// The kind of shot.
+    kind: ShotKind,
+    /// The ball the cue ball is sent at.
+    object_ball: u32,
+    /// The ball meant to drop.
+    called_ball: Option<u32>,
+    /// The pocket it is meant to drop in.
+    pocket: Option<Pocket>,
+    /// Where to put the cue ball first, with ball in hand.
+    cue_position: Option<Vector2D>,
+    /// Where the cue ball is played from.
+    from: Vector2D,
+    /// Where the cue ball's centre is sent.
+    aim: Vector2D,
+    /// Cue speed for the plainest stroke, in units per second.
+    speed: f32,
+    /// How easy the shot looks, from 0 to 1.
+    ease: f32,
+}
+
+impl Candidate {
+    /// Returns the stroke for `variant`.
+    fn stroke(&self, (factor, tip_y, _): (f32, f32, f32)) -> CueStroke {
+        let direction = self.aim - self.from;
+        CueStroke::new(
+            math::atan2(direction.y, direction.x),
+            (self.speed * factor).min(MAX_SPEED),
+            0.0,
+            tip_y,
+            0.0,
+        )
+    }
+
+    /// Returns the stroke variants worth trying.
+    const fn variants(&self) -> &'static [(f32, f32, f32)] {
+        match self.kind {
+            ShotKind::Safety => &SAFETY_VARIANTS,
+            ShotKind::Kick => &KICK_VARIANTS,
+            _ => &POT_VARIANTS,
+        }
+    }
+}
+
+/// Chooses shots for a computer player.
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct Planner {
+    /// How well it plays.
+    difficulty: Difficulty,
+    /// Strokes played out per decision.
+    iterations: u32,
+    /// Cap, in seconds, on the time each stroke is played out for.
+    max_time: f32,
+    /// Source of execution noise.
+    rng: Rng,
+}
+
+#[wasm_bindgen]
+impl Planner {
+    /// Creates a planner of `difficulty` whose noise is drawn from `seed`.
+    #[wasm_bindgen(constructor)]
+    #[must_use]
+    pub fn new(difficulty: Difficulty, seed: u32) -> Self {
+        Self {
+            difficulty,
+            iterations: difficulty.iterations(),
+            max_time: DEFAULT_MAX_TIME,
+            rng: Rng::new(u64::from(seed)),
+        }
+    }
+
+    /// Returns how well the planner plays.
+    #[must_use]
+    pub fn difficulty(&self) -> Difficulty {
+        self.difficulty
+    }
+
+    /// Returns the number of strokes played out per decision.
+    #[must_use]
+    pub fn iterations(&self) -> u32 {
+        self.iterations
+    }
+
+    /// Sets the number of strokes played out per decision, at least one.
+    /// Decision time grows in proportion.
+    pub fn set_iterations(&mut self, iterations: u32) {
+        self.iterations = iterations.max(1);
+    }
+
+    /// Returns the cap, in seconds, on the time each stroke is played out for.
+    #[must_use]
+    pub fn max_time(&self) -> f32 {
+        self.max_time
+    }
+
+    /// Sets the cap, in seconds, on the time each stroke is played out for.
+    /// A cap that is not a positive number is replaced by the default.
+    pub fn set_max_time(&mut self, max_time: f32) {
+        self.max_time = if max_time.is_finite() && max_time > 0.0 {
+            max_time
+        } else {
+            DEFAULT_MAX_TIME
+        };
+    }
+
+    /// Chooses a shot for the player to shoot in `state`, or returns
+    /// `undefined` if there is nothing to play. `state` is not changed.
+    pub fn plan(&mut self, state: &GameState) -> Option<PlannedShot> {
+        if state.winner().is_some() {
+            return None;
+        }
+        let candidates = candidates(state);
+        let mut strokes: Vec<(f32, Candidate, (f32, f32, f32))> = candidates
+            .iter()
+            .flat_map(|candidate| {
+                candidate
+                    .variants()
+                    .iter()
+                    .map(move |&variant| (candidate.ease * variant.2, *candidate, variant))
+            })
+            .collect();
+        strokes.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        let limit = usize::try_from(self.iterations).unwrap_or(usize::MAX);
+        let weight = self.difficulty.position_weight();
+        let (_, candidate, variant, value) = strokes
+            .into_iter()
+            .take(limit)
+            .filter_map(|(ease, candidate, variant)| {
+                let stroke = candidate.stroke(variant);
+                let after = self.play_out(state, &candidate, &stroke)?;
+                Some((ease, candidate, variant, value(state, &after, weight)))
+            })
+            .max_by(|a, b| a.3.total_cmp(&b.3).then(a.0.total_cmp(&b.0)))?;
+
+        let stroke = self.spoil(candidate.stroke(variant));
+        Some(PlannedShot {
+            stroke,
+            kind: candidate.kind,
+            object_ball: candidate.object_ball,
+            called_ball: candidate.called_ball,
+            pocket: candidate.pocket,
+            cue_position: candidate.cue_position,
+            value,
+        })
+    }
+
+    /// Chooses a shot for the player to shoot in `state` and plays it.
+    /// Returns the shot, or `undefined` if there was none or it could not
+    /// be played.
+    pub fn play(&mut self, state: &mut GameState) -> Option<PlannedShot> {
+        let shot = self.plan(state)?;
+        state.play_shot(&shot).then_some(shot)
+    }
+}
+
+impl Planner {
+    /// Plays `stroke` for `candidate` out on a copy of `state`, returning the
+    /// position it leaves, or `None` if it cannot be played.
+    fn play_out(
+        &self,
+        state: &GameState,
+        candidate: &Candidate,
+        stroke: &CueStroke,
+    ) -> Option<GameState> {
+        let mut trial = state.clone();
+        trial.events.clear();
+        if !prepare(
+            &mut trial,
+            candidate.cue_position,
+            candidate.called_ball,
+            candidate.pocket,
+        ) || !trial.strike(stroke)
+        {
+            return None;
+        }
+        let mut elapsed = 0.0;
+        while trial.balls.iter().any(Ball::is_moving) && elapsed < self.max_time {
+            tick(&mut trial, PLAYOUT_STEP);
+            elapsed += PLAYOUT_STEP;
+        }
+        Some(trial)
+    }
+
+    /// Adds execution noise to `stroke`.
+    fn spoil(&mut self, stroke: CueStroke) -> CueStroke {
+        let difficulty = self.difficulty;
+        let aim = stroke.aim_angle + self.rng.next_normal() * difficulty.aim_error();
+        let speed = stroke.speed * (self.rng.next_normal() * difficulty.speed_error() + 1.0);
+        let tip_x = self.rng.next_normal() * difficulty.tip_error() + stroke.tip_x;
+        let tip_y = self.rng.next_normal() * difficulty.tip_error() + stroke.tip_y;
+        CueStroke::new(
+            aim,
+            speed.clamp(0.0, MAX_SPEED),
+            tip_x,
+            tip_y,
+            stroke.elevation,
+        )
+    }
+}
+
+#[wasm_bindgen]
+impl GameState {
+    /// Plays `shot`: places the cue ball if the shot says where, calls the
+    /// ball and pocket where the rules want them called, and strikes.
+    /// Returns `false` if the placement or the stroke is refused.
+    pub fn play_shot(&mut self, shot: &PlannedShot) -> bool {
+        prepare(self, shot.cue_position, shot.called_ball, shot.pocket) && self.strike(&shot.stroke)
+    }
+}
+
+/// Places the cue ball at `cue_position`, if given, and calls `ball` into
+/// `pocket`. Returns `false` if the placement is refused.
+fn prepare(
+    state: &mut GameState,
+    cue_position: Option<Vector2D>,
+    ball: Option<u32>,
+    pocket: Option<Pocket>,
+) -> bool {
+    if let Some(position) = cue_position {
+        if !state.place_cue_ball(position.x, position.y) {
+            return false;
+        }
+    }
+    if let (Some(ball), Some(pocket)) = (ball, pocket) {
+        state.call_shot(ball, pocket);
+        state.call_pocket(pocket);
+    }
+    true
+}
+
+/// Values the position `after` left by a shot played from `before`, for the
+/// player who played it. `weight` scales the value of the next shot.
+fn value(before: &GameState, after: &GameState, weight: f32) -> f32 {
+    let player = before.current_player();
+    if let (Some(player), Some(winner)) = (player, after.winner()) {
+        return if winner == player {
+            WIN_VALUE
+        } else {
+            -WIN_VALUE
+        };
+    }
+
+    let cue = before.cue_ball_id();
+    let scratched = cue.is_some_and(|id| !after.is_on_table(id));
+    let potted = after
+        .pocketed
+        .iter()
+        .filter(|record| Some(record.ball_id()) != cue)
+        .count();
+    let (points, fouled, kept) = player.map_or_else(
+        || {
+            let potted = u16::try_from(potted).unwrap_or(u16::MAX);
+            (f32::from(potted), scratched, potted > 0 && !scratched)
+        },
+        |player| {
+            let other = 1 - player;
+            let gained = (after.score(player) - before.score(player))
+                - (after.score(other) - before.score(other));
+            let gained = i16::try_from(gained).unwrap_or(0);
+            (
+                f32::from(gained),
+                after.last_foul().is_some(),
+                after.current_player() == Some(player),
+            )
+        },
+    );
+
+    let next = weight * POSITION_VALUE * ease(after);
+    let mut value = points * POINT_VALUE;
+    if fouled {
+        value -= FOUL_COST;
+    }
+    if kept {
+        value + TURN_VALUE + next
+    } else {
+        value - next
+    }
+}
+
+/// Returns how easy the best pot on offer in `state` looks to the player to
+/// shoot, from 0 to 1. Ball in hand counts as the easiest position of all.
+fn ease(state: &GameState) -> f32 {
+    let Some(cue) = state
+        .cue_ball_id()
+        .and_then(|id| state.find_ball(id))
+        .filter(|_| state.ball_in_hand() == crate::BallInHand::No)
+    else {
+        return 1.0;
+    };
+    state
+        .targets()
+        .into_iter()
+        .filter_map(|id| state.find_ball(id))
+        .flat_map(|target| pots(state, cue, cue.position, target))
+        .map(|candidate| candidate.ease)
+        .fold(0.0, f32::max)
+}
+
+/// Lists the shots on offer to the player to shoot in `state`.
+fn candidates(state: &GameState) -> Vec<Candidate> {
+    let Some(cue_id) = state.cue_ball_id() else {
+        return Vec::new();
+    };
+    let Some(cue) = crate::rules::any_ball(state, cue_id) else {
+        return Vec::new();
+    };
+    let targets: Vec<&Ball> = state
+        .targets()
+        .into_iter()
+        .filter_map(|id| state.find_ball(id))
+        .collect();
+    let in_hand = state.ball_in_hand() != crate::BallInHand::No;
+
+    let mut candidates = Vec::new();
+    if in_hand {
+        for target in &targets {
+            candidates.extend(placed_pots(state, &cue, target));
+        }
+    }
+
+    // Every other shot is played from where the cue ball lies, or with ball
+    // in hand from the first spot it may be placed on.
+    let from = if in_hand {
+        let Some(spot) = placement(state, &cue) else {
+            return candidates;
+        };
+        spot
+    } else if state.is_on_table(cue_id) {
+        cue.position
+    } else {
+        return candidates;
+    };
+    let placed = in_hand.then_some(from);
+    for target in &targets {
+        let offered = pots(state, &cue, from, target)
+            .chain(banks(state, &cue, from, target))
+            .chain(combinations(state, &cue, from, target))
+            .chain(safeties(state, &cue, from, target))
+            .chain(kicks(state, &cue, from, target));
+        candidates.extend(offered.map(|candidate| Candidate {
+            cue_position: placed,
+            ..candidate
+        }));
+    }
+    candidates
+}
+
+/// Returns where the centre of each pocket's mouth is.
+fn pocket_targets(state: &GameState) -> impl Iterator<Item = (Pocket, Vector2D)> {
+    pocket::mouths(state.table).map(|mouth| (mouth.pocket, (mouth.start + mouth.end) * 0.5))
+}
+
+/// Lists the direct pots of `target` for a cue ball played from `from`.
+fn pots<'a>(
+    state: &'a GameState,
+    cue: &'a Ball,
+    from: Vector2D,
+    target: &'a Ball,
+) -> impl Iterator<Item = Candidate> + 'a {
+    pocket_targets(state).filter_map(move |(pocket, point)| {
+        if !path_clear(
+            state,
+            target.position,
+            point,
+            target.radius,
+            &[target.id, cue.id],
+        ) {
+            return None;
+        }
+        let mut candidate = aim_at(state, cue, from, target, point, 1.0)?;
+        candidate.kind = ShotKind::Pot;
+        candidate.called_ball = Some(target.id);
+        candidate.pocket = Some(pocket);
+        Some(candidate)
+    })
+}
+
+/// Lists the banks of `target` off up to [`BANK_RAILS`] cushions for a
+/// cue ball played from `from`, by aiming along the first leg of each path
+/// [`bank_paths`] finds as though the path were unfolded into a line.
+fn banks<'a>(
+    state: &'a GameState,
+    cue: &'a Ball,
+    from: Vector2D,
+    target: &'a Ball,
+) -> impl Iterator<Item = Candidate> + 'a {
+    let ignore = [target.id, cue.id];
+    pocket_targets(state).flat_map(move |(pocket, point)| {
+        (1..=BANK_RAILS)
+            .flat_map(|rails| {
+                bank_paths(
+                    &state.table,
+                    target.position,
+                    point,
+                    target.radius,
+                    rails,
+                    None,
+                )
+            })
+            .filter(|path| legs_clear(state, target.position, path, point, target.radius, &ignore))
+            .filter_map(|path| {
+                let first = path.aim_point() - target.position;
+                let leg = first.length();
+                if leg <= 0.0 {
+                    return None;
+                }
+                let image = target.position + first * (path.length() / leg);
+                let cushions = i32::try_from(path.rails_len()).unwrap_or(i32::MAX);
+                let ease = BANK_EASE.powi(cushions);
+                let mut candidate = aim_at(state, cue, from, target, image, ease)?;
+                candidate.kind = ShotKind::Bank;
+                candidate.called_ball = Some(target.id);
+                candidate.pocket = Some(pocket);
+                Some(candidate)
+            })
+            .collect::<Vec<_>>()
+    })
+}
+
+/// Lists the combinations that send `target` into another ball and that
+/// ball into a pocket, for a cue ball played from `from`.
+fn combinations<'a>(
+    state: &'a GameState,
+    cue: &'a Ball,
+    from: Vector2D,
+    target: &'a Ball,
+) -> impl Iterator<Item = Candidate> + 'a {
+    state
+        .balls
+        .iter()
+        .filter(move |second| second.id != target.id && second.id != cue.id)
+        .flat_map(move |second| {
+            pocket_targets(state).filter_map(move |(pocket, point)| {
+                let line = point - second.position;
+                let distance = line.length();
+                if distance <= 0.0
+                    || !path_clear(state, second.position, point, second.radius, &[second.id])
+                {
+                    return None;
+                }
+                let ghost = second.position - line * ((second.radius + target.radius) / distance);
+                let travel = ghost - target.position;
+                let cut = travel.dot(line) / (travel.length() * distance);
+                if cut < MIN_COMBINATION_CUT_COSINE
+                    || !path_clear(
+                        state,
+                        target.position,
+                        ghost,
+                        target.radius,
+                        &[target.id, second.id, cue.id],
+                    )
+                {
+                    return None;
+                }
+                let mut candidate =
+                    aim_at(state, cue, from, target, ghost, COMBINATION_EASE * cut)?;
+                candidate.kind = ShotKind::Combination;
+                candidate.called_ball = Some(second.id);
+                candidate.pocket = Some(pocket);
+                candidate.speed = (candidate.speed / cut).min(MAX_SPEED);
+                Some(candidate)
+            })
+        })
+}
+
+/// Lists the safeties on `target`: a full hit and thin hits on either
+/// side, played softly to firmly.
+fn safeties<'a>(
+    state: &'a GameState,
+    cue: &'a Ball,
+    from: Vector2D,
+    target: &'a Ball,
+) -> impl Iterator<Item = Candidate> + 'a {
+    let line = target.position - from;
+    let distance = line.length();
+    let contact = cue.radius + target.radius;
+    [0.0, SAFETY_CUT, -SAFETY_CUT]
+        .into_iter()
+        .filter(move |_| distance > contact)
+        .filter_map(move |cut: f32| {
+            // Turn the line to the target by the cut, about the target.
+            let (sin, cos) = math::sin_cos(cut);
+            let towards = line / distance;
+            let side = Vector2D::new(-towards.y, towards.x);
+            let ghost = target.position - (towards * cos + side * sin) * contact;
+            if !path_clear(state, from, ghost, cue.radius, &[cue.id, target.id]) {
+                return None;
+            }
+            Some(Candidate {
+                kind: ShotKind::Safety,
+                object_ball: target.id,
+                called_ball: None,
+                pocket: None,
+                cue_position: None,
+                from,
+                aim: ghost,
+                speed: roll_speed(state, (ghost - from).length() + contact),
+                ease: SAFETY_EASE,
+            })
+        })
+}
+
+/// Lists the kicks at `target` off up to [`BANK_RAILS`] cushions for a cue
+/// ball played from `from`, if the straight line to it is blocked.
+///
+/// The speed is planned on the shortest mirror-law path off each number of
+/// cushions, and the paths are then found at that speed through the
+/// cushions' real rebound, which bends a kick well off the mirror line.
+fn kicks<'a>(
+    state: &'a GameState,
+    cue: &'a Ball,
+    from: Vector2D,
+    target: &'a Ball,
+) -> impl Iterator<Item = Candidate> + 'a {
+    let ignore = [cue.id, target.id];
+    let hidden = !path_clear(state, from, target.position, cue.radius, &ignore);
+    let to = target.position;
+    (1..=BANK_RAILS)
+        .filter(move |_| hidden)
+        .filter_map(move |rails| {
+            let mirror = bank_paths(&state.table, from, to, cue.radius, rails, None);
+            let speed = roll_speed(state, mirror.first()?.length()) * KICK_SPEED;
+            Some((rails, speed.min(MAX_SPEED)))
+        })
+        .flat_map(move |(rails, speed)| {
+            bank_paths(&state.table, from, to, cue.radius, rails, Some(speed))
+                .into_iter()
+                .map(move |path| (path, speed))
+        })
+        .filter(move |(path, _)| legs_clear(state, from, path, to, cue.radius, &ignore))
+        .map(move |(path, speed)| Candidate {
+            kind: ShotKind::Kick,
+            object_ball: target.id,
+            called_ball: None,
+            pocket: None,
+            cue_position: None,
+            from,
+            aim: path.aim_point(),
+            speed,
+            ease: KICK_EASE,
+        })
+}
+
+/// Returns the shot that sends `target` towards `point` off a cue ball at
+/// `from`, if the cue ball has a clear path to it and the cut is not too
+/// thin. Its ease is scaled by `scale`.
+fn aim_at(
+    state: &GameState,
+    cue: &Ball,
+    from: Vector2D,
+    target: &Ball,
+    point: Vector2D,
+    scale: f32,
+) -> Option<Candidate> {
+    let line = point - target.position;
+    let object_travel = line.length();
+    if object_travel <= 0.0 {
+        return None;
+    }
+    let direction = line / object_travel;
+    let ghost = target.position - direction * (cue.radius + target.radius);
+    let approach = ghost - from;
+    let cue_travel = approach.length();
+    if cue_travel <= 0.0 {
+        return None;
+    }
+    let cut = approach.dot(direction) / cue_travel;
+    if cut < MIN_CUT_COSINE || !path_clear(state, from, ghost, cue.radius, &[cue.id, target.id]) {
+        return None;
+    }
+
+    let table = state.table;
+    let diagonal = math::hypot(table.width, table.height);
+    let object_speed = roll_speed(state, object_travel + POT_SLACK) / cut;
+    let speed = math::hypot(object_speed, roll_speed(state, cue_travel));
+    Some(Candidate {
+        kind: ShotKind::Pot,
+        object_ball: target.id,
+        called_ball: None,
+        pocket: None,
+        cue_position: None,
+        from,
+        aim: ghost,
+        speed: speed.min(MAX_SPEED),
+        ease: scale * cut / (1.0 + (cue_travel + object_travel) / diagonal),
+    })
+}
+
+/// Returns a cue speed that rolls a ball about `distance` units.
+fn roll_speed(state: &GameState, distance: f32) -> f32 {
+    // A ball struck at the centre slides first and keeps 5/7 of its speed
+    // once it rolls.
+    let deceleration = state.table.roll_friction * GRAVITY;
+    (2.0 * deceleration * distance).sqrt() * 1.4
+}
+
+/// Returns the direct pots of `target` with ball in hand, placing the cue
+/// ball behind the ghost ball where the rules allow.
+fn placed_pots(state: &GameState, cue: &Ball, target: &Ball) -> Vec<Candidate> {
+    pocket_targets(state)
+        .filter_map(|(pocket, point)| {
+            let line = target.position - point;
+            let distance = line.length();
+            if distance <= 0.0 {
+                return None;
+            }
+            let back = line / distance;
+            let ghost = target.position + back * (cue.radius + target.radius);
+            let spot = PLACEMENT_DISTANCES.iter().find_map(|&radii| {
+                PLACEMENT_ANGLES.iter().find_map(|&angle| {
+                    let (sin, cos) = math::sin_cos(angle);
+                    let turned =
+                        Vector2D::new(back.x * cos - back.y * sin, back.x * sin + back.y * cos);
+                    let spot = ghost + turned * (radii * cue.radius);
+                    state.can_place_cue_ball(spot.x, spot.y).then_some(spot)
+                })
+            })?;
+            let mut placed = cue.clone();
+            placed.position = spot;
+            let found = pots(state, &placed, spot, target)
+                .find(|candidate| candidate.pocket == Some(pocket));
+            found.map(|candidate| Candidate {
+                cue_position: Some(spot),
+                ..candidate
+            })
+        })
+        .collect()
+}
+
+/// Returns a spot the cue ball may be placed on: where it lies if that is
+/// allowed, or else the first allowed point of a grid over the table.
+fn placement(state: &GameState, cue: &Ball) -> Option<Vector2D> {
+    if state.can_place_cue_ball(cue.position.x, cue.position.y) {
+        return Some(cue.position);
+    }
+    let table = state.table;
+    let (columns, rows) = (16_u16, 8_u16);
+    (1..columns)
+        .flat_map(|i| (1..rows).map(move |j| (i, j)))
+        .map(|(i, j)| {
+            Vector2D::new(
+                table.width * f32::from(i) / f32::from(columns),
+                table.height * f32::from(j) / f32::from(rows),
+            )
+        })
+        .find(|spot| state.can_place_cue_ball(spot.x, spot.y))
+}
+
+/// Returns `true` if a ball of `radius` can travel straight from `from` to
+/// `to` without touching a ball other than those in `ignore`.
+fn path_clear(
+    state: &GameState,
+    from: Vector2D,
+    to: Vector2D,
+    radius: f32,
+    ignore: &[u32],
+) -> bool {
+    let span = to - from;
+    let length_squared = span.length_squared();
+    state
+        .balls
+        .iter()
+        .filter(|ball| !ignore.contains(&ball.id))
+        .all(|ball| {
+            let along = if length_squared > 0.0 {
+                ((ball.position - from).dot(span) / length_squared).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            (ball.position - (from + span * along)).length() >= ball.radius + radius
+        })
+}
+
+/// Returns `true` if a ball of `radius` can follow `path` from `from` to
+/// `to` without meeting any ball but those in `ignore`.
+fn legs_clear(
+    state: &GameState,
+    from: Vector2D,
+    path: &BankPath,
+    to: Vector2D,
+    radius: f32,
+    ignore: &[u32],
+) -> bool {
+    let points: Vec<Vector2D> = std::iter::once(from)
+        .chain(path.contacts().iter().copied())
+        .chain(std::iter::once(to))
+        .collect();
+    points
+        .windows(2)
+        .all(|leg| path_clear(state, leg[0], leg[1], radius, ignore))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EventKind, Rotation, Rules, Table};
+
+    /// A cue ball below an object ball lined up with the top side pocket.
+    fn straight_pot() -> GameState {
+        GameState::new(
+            Table::pool(800.0, 400.0),
+            vec![
+                Ball::new(400.0, 300.0, 0.0, 0.0, 10.0),
+                Ball::new(400.0, 150.0, 0.0, 0.0, 10.0),
+            ],
+        )
+    }
+
+    /// Plays until every ball is at rest.
+    fn settle(state: &mut GameState) {
+        for _ in 0..1200 {
+            tick(state, 1.0 / 60.0);
+        }
+    }
+
+    #[test]
+    fn expert_pots_a_straight_ball() {
+        let mut state = straight_pot();
+        let mut planner = Planner::new(Difficulty::Expert, 1);
+        planner.set_iterations(24);
+
+        let shot = planner.play(&mut state).unwrap();
+        settle(&mut state);
+
+        assert_eq!(shot.kind(), ShotKind::Pot);
+        assert_eq!(shot.called_ball(), Some(1));
+        assert_eq!(shot.pocket(), Some(Pocket::TopSide));
+        assert!(!state.is_on_table(1));
+        assert!(state.is_on_table(0));
+    }
+
+    #[test]
+    fn plans_are_reproducible_and_leave_the_state_alone() {
+        let state = straight_pot();
+        let mut first = Planner::new(Difficulty::Beginner, 9);
+        let mut second = Planner::new(Difficulty::Beginner, 9);
+
+        let a = first.plan(&state).unwrap();
+        let b = second.plan(&state).unwrap();
+
+        assert!((a.stroke().aim_angle - b.stroke().aim_angle).abs() < f32::EPSILON);
+        assert!((a.stroke().speed - b.stroke().speed).abs() < f32::EPSILON);
+        assert!(state.events().is_empty());
+        assert!(!state.balls()[0].is_moving());
+    }
+
+    #[test]
+    fn bad_time_caps_fall_back_to_the_default() {
+        let mut planner = Planner::new(Difficulty::Beginner, 1);
+        planner.set_max_time(4.0);
+        assert!((planner.max_time() - 4.0).abs() < f32::EPSILON);
+        for max_time in [f32::NAN, f32::INFINITY, -1.0, 0.0] {
+            planner.set_max_time(max_time);
+            assert!((planner.max_time() - DEFAULT_MAX_TIME).abs() < f32::EPSILON);
+        }
+    }
+
+    #[test]
+    fn kicks_at_a_hidden_ball() {
+        // The 2 stands between the cue ball and the 1, which must be hit.
+        let balls = vec![
+            Ball::pool(0, 200.0, 200.0),
+            Ball::pool(1, 600.0, 200.0),
+            Ball::pool(2, 400.0, 200.0),
+        ];
+        let mut state = GameState::new(Table::pool(800.0, 400.0), balls)
+            .with_rules(Rules::Rotation(Rotation::nine_ball()));
+        let mut planner = Planner::new(Difficulty::Expert, 2);
+        planner.set_iterations(24);
+
+        let shot = planner.play(&mut state).unwrap();
+        settle(&mut state);
+
+        assert_eq!(shot.kind(), ShotKind::Kick);
+        assert_eq!(shot.object_ball(), 1);
+        let first = state
+            .events()
+            .iter()
+            .find(|event| event.kind == EventKind::BallContact)
+            .unwrap();
+        assert!(first.involves(0) && first.involves(1));
+    }
+
+    #[test]
+    fn only_legal_balls_are_played_with_ball_in_hand() {
+        // The 2 hangs over a pocket, but the 1 must be hit first.
+        let table = Table::pool(800.0, 400.0);
+        let balls = vec![
+            Ball::pool(0, 100.0, 200.0),
+            Ball::pool(1, 600.0, 300.0),
+            Ball::pool(2, 770.0, 30.0),
+        ];
+        let state = GameState::new(table, balls).with_rules(Rules::Rotation(Rotation::nine_ball()));
+        let mut planner = Planner::new(Difficulty::Amateur, 4);
+        planner.set_iterations(16);
+
+        let shot = planner.plan(&state).unwrap();
+
+        assert_eq!(shot.object_ball(), 1);
+        let placed = shot.cue_position().unwrap();
+        assert!(placed.x <= table.width * 0.25);
+    }
+}
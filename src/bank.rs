@@ -0,0 +1,586 @@
+//! Bank and kick shots.
+//!
+//! A ball sent into a cushion leaves it at the mirror angle, so the path of
+//! a bank unfolds into a straight line towards the target's image in the
+//! cushions it meets: reflect the target across the last cushion, then the
+//! one before, and so on, and aim at the result. The cushion lines are taken
+//! one ball radius in from the noses, where the ball's centre is when it
+//! touches them.
+//!
+//! Real cushions do not rebound at the mirror angle: they give back less of
+//! a hard hit, and the spin a rolling ball carries into them bends its path
+//! afterwards. Given a speed, the solver rolls the ball out through the same
+//! physics as [`tick`](crate::tick) and corrects the mirror aim by the secant
+//! method until the path passes over the target.
+//!
+//! Paths whose contacts fall in a pocket mouth or on a jaw, or that meet the
+//! cushions in a different order, are discarded.
+
+use wasm_bindgen::prelude::*;
+
+use crate::cushion::{self, Cushion, Rail};
+use crate::pocket::Pocket;
+use crate::{math, physics, Ball, EventKind, GameState, Table, Vector2D};
+
+/// Most cushions a path may use.
+pub const MAX_BANK_RAILS: u32 = 4;
+
+/// How close, in units, a corrected path must pass to its target.
+const TOLERANCE: f32 = 0.01;
+
+/// Secant iterations allowed when correcting an aim for the rebound model.
+const MAX_ITERATIONS: usize = 24;
+
+/// First change of aim, in radians, tried when correcting for the rebound
+/// model. Large enough that the change in miss stands clear of the error
+/// from rolling the path out in steps.
+const PROBE_ANGLE: f32 = 0.02;
+
+/// Largest change of aim, in radians, made by one secant iteration.
+const MAX_CORRECTION: f32 = 0.1;
+
+/// Simulation time, in seconds, per step while rolling a path out.
+const ROLL_STEP: f32 = 1.0 / 60.0;
+
+/// Most steps a path is rolled out for.
+const MAX_ROLL_STEPS: usize = 1800;
+
+/// Every rail, in [`Rail`] order.
+const RAILS: [Rail; 4] = [Rail::Top, Rail::Right, Rail::Bottom, Rail::Left];
+
+/// A way to send a ball to a target off one or more cushions.
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct BankPath {
+    /// The cushions met, in order.
+    rails: Vec<Rail>,
+    /// Where the ball's centre is at each cushion contact, in order.
+    contacts: Vec<Vector2D>,
+    /// Where the ball starts.
+    from: Vector2D,
+    /// Where the path ends.
+    to: Vector2D,
+    /// Direction to send the ball in, in radians from the `x` axis.
+    aim_angle: f32,
+    /// Length of the path, in units.
+    length: f32,
+}
+
+#[wasm_bindgen]
+impl BankPath {
+    /// Returns the number of cushions the path meets.
+    #[must_use]
+    pub fn rails_len(&self) -> usize {
+        self.rails.len()
+    }
+
+    /// Returns the cushion met at the given index, in order.
+    ///
+    /// Panics in Rust if out of bounds; when called from JS this will surface
+    /// as a trap, so callers must bounds-check first.
+    #[must_use]
+    pub fn rail(&self, index: usize) -> Rail {
+        self.rails[index]
+    }
+
+    /// Returns the point to aim at: where the ball's centre is when it
+    /// meets the first cushion.
+    #[must_use]
+    pub fn aim_point(&self) -> Vector2D {
+        self.contacts[0]
+    }
+
+    /// Returns the direction to send the ball in, in radians from the `x`
+    /// axis, as a [`CueStroke`](crate::CueStroke) aim angle.
+    #[must_use]
+    pub fn aim_angle(&self) -> f32 {
+        self.aim_angle
+    }
+
+    /// Returns the whole path, from the start through every cushion contact
+    /// to the target, as flat `x, y` pairs.
+    #[must_use]
+    pub fn path(&self) -> Vec<f32> {
+        std::iter::once(self.from)
+            .chain(self.contacts.iter().copied())
+            .chain(std::iter::once(self.to))
+            .flat_map(|point| [point.x, point.y])
+            .collect()
+    }
+
+    /// Returns the length of the path, in units.
+    #[must_use]
+    pub fn length(&self) -> f32 {
+        self.length
+    }
+}
+
+impl BankPath {
+    /// Returns the cushions met, in order.
+    #[must_use]
+    pub fn rails(&self) -> &[Rail] {
+        &self.rails
+    }
+
+    /// Returns where the ball's centre is at each cushion contact, in order.
+    #[must_use]
+    pub fn contacts(&self) -> &[Vector2D] {
+        &self.contacts
+    }
+}
+
+/// Finds the paths that take a ball of `radius` from `from` to `to` off
+/// exactly `rails` cushions, shortest first.
+///
+/// Without a `speed` the paths follow the mirror law. With one, in units
+/// per second as the ball leaves `from`, they follow the ball through the
+/// physics, and paths the ball would not finish are left out. Other balls
+/// are not considered. `rails` is limited to [`MAX_BANK_RAILS`].
+#[wasm_bindgen]
+#[must_use]
+pub fn bank_paths(
+    table: &Table,
+    from: Vector2D,
+    to: Vector2D,
+    radius: f32,
+    rails: u32,
+    speed: Option<f32>,
+) -> Vec<BankPath> {
+    let count = rails.min(MAX_BANK_RAILS);
+    if count == 0 {
+        return Vec::new();
+    }
+    let cushions = cushion::cushions(*table);
+    let mut paths: Vec<BankPath> = sequences(count)
+        .into_iter()
+        .filter_map(|sequence| {
+            let image = sequence
+                .iter()
+                .rev()
+                .fold(to, |point, &rail| reflect(*table, rail, radius, point));
+            let line = image - from;
+            let angle = math::atan2(line.y, line.x);
+            let solver = Solver {
+                table: *table,
+                cushions: &cushions,
+                from,
+                to,
+                radius,
+                speed,
+                rails: &sequence,
+            };
+            solver.solve(angle)
+        })
+        .collect();
+    paths.sort_by(|a, b| a.length.total_cmp(&b.length));
+    paths
+}
+
+#[wasm_bindgen]
+impl GameState {
+    /// Finds the banks that send ball `id` into `pocket` off `rails`
+    /// cushions, shortest first; see [`bank_paths`]. The aim is for the
+    /// object ball, and the cue ball must be sent to the ghost ball behind
+    /// it on the line of the first leg.
+    #[must_use]
+    pub fn bank_shots(
+        &self,
+        id: u32,
+        pocket: Pocket,
+        rails: u32,
+        speed: Option<f32>,
+    ) -> Vec<BankPath> {
+        let Some(ball) = self.find_ball(id) else {
+            return Vec::new();
+        };
+        let Some(mouth) = crate::pocket::mouths(self.table).find(|mouth| mouth.pocket == pocket)
+        else {
+            return Vec::new();
+        };
+        let target = (mouth.start + mouth.end) * 0.5;
+        bank_paths(
+            &self.table,
+            ball.position,
+            target,
+            ball.radius,
+            rails,
+            speed,
+        )
+    }
+
+    /// Finds the kicks that send the cue ball into a full hit on ball `id`
+    /// off `rails` cushions, shortest first; see [`bank_paths`].
+    #[must_use]
+    pub fn kick_shots(&self, id: u32, rails: u32, speed: Option<f32>) -> Vec<BankPath> {
+        let cue = self.cue_ball_id().and_then(|cue| self.find_ball(cue));
+        let (Some(cue), Some(target)) = (cue, self.find_ball(id)) else {
+            return Vec::new();
+        };
+        bank_paths(
+            &self.table,
+            cue.position,
+            target.position,
+            cue.radius,
+            rails,
+            speed,
+        )
+        .into_iter()
+        .map(|mut path| {
+            // Stop at the contact, a ball's width short of the centre.
+            let last = path.contacts.last().copied().unwrap_or(path.from);
+            let leg = path.to - last;
+            let leg_length = leg.length();
+            let short = (cue.radius + target.radius).min(leg_length);
+            if leg_length > 0.0 {
+                path.to -= leg * (short / leg_length);
+            }
+            path.length -= short;
+            path
+        })
+        .collect()
+    }
+}
+
+/// Lists every order of `count` cushions that never meets the same cushion
+/// twice in a row.
+fn sequences(count: u32) -> Vec<Vec<Rail>> {
+    let mut sequences: Vec<Vec<Rail>> = RAILS.iter().map(|&rail| vec![rail]).collect();
+    for _ in 1..count {
+        let mut longer = Vec::with_capacity(sequences.len() * 3);
+        for sequence in &sequences {
+            for rail in RAILS {
+                if sequence.last() != Some(&rail) {
+                    let mut next = sequence.clone();
+                    next.push(rail);
+                    longer.push(next);
+                }
+            }
+        }
+        sequences = longer;
+    }
+    sequences
+}
+
+/// Reflects `point` across the line a ball of `radius` touches `rail` on.
+fn reflect(table: Table, rail: Rail, radius: f32, point: Vector2D) -> Vector2D {
+    match rail {
+        Rail::Top => Vector2D::new(point.x, 2.0 * radius - point.y),
+        Rail::Bottom => Vector2D::new(point.x, 2.0 * (table.height - radius) - point.y),
+        Rail::Left => Vector2D::new(2.0 * radius - point.x, point.y),
+        Rail::Right => Vector2D::new(2.0 * (table.width - radius) - point.x, point.y),
+    }
+}
+
+/// The fixed inputs of one bank path search.
+struct Solver<'a> {
+    /// The table played on.
+    table: Table,
+    /// Its cushions.
+    cushions: &'a [Cushion],
+    /// Where the ball starts.
+    from: Vector2D,
+    /// Where it must end.
+    to: Vector2D,
+    /// Radius of the ball.
+    radius: f32,
+    /// Speed of the ball as it starts, if rebounds are modelled.
+    speed: Option<f32>,
+    /// The cushions to meet, in order.
+    rails: &'a [Rail],
+}
+
+/// Where a traced path went.
+struct Trace {
+    /// Where the ball's centre was at each cushion contact.
+    contacts: Vec<Vector2D>,
+    /// Signed distance by which the last leg misses the target.
+    miss: f32,
+    /// Length of the path up to the target.
+    length: f32,
+}
+
+impl Solver<'_> {
+    /// Finds the path that starts out at `angle`, corrected for the rebound
+    /// model if a speed was given.
+    fn solve(&self, angle: f32) -> Option<BankPath> {
+        let Some(speed) = self.speed else {
+            let trace = self.mirror(angle)?;
+            return self.finish(angle, trace);
+        };
+        let (mut previous, mut previous_miss) = (angle, self.roll(angle, speed)?.miss);
+        let mut angle = angle + PROBE_ANGLE;
+        let mut trace = self.roll(angle, speed)?;
+        for _ in 0..MAX_ITERATIONS {
+            if trace.miss.abs() < TOLERANCE {
+                break;
+            }
+            let slope = (trace.miss - previous_miss) / (angle - previous);
+            if !slope.is_normal() {
+                return None;
+            }
+            previous = angle;
+            previous_miss = trace.miss;
+            angle -= (trace.miss / slope).clamp(-MAX_CORRECTION, MAX_CORRECTION);
+            trace = self.roll(angle, speed)?;
+        }
+        self.finish(angle, trace)
+    }
+
+    /// Returns the path traced at `angle`, if it ends on the target.
+    fn finish(&self, angle: f32, trace: Trace) -> Option<BankPath> {
+        (trace.miss.abs() < TOLERANCE).then(|| BankPath {
+            rails: self.rails.to_vec(),
+            contacts: trace.contacts,
+            from: self.from,
+            to: self.to,
+            aim_angle: angle,
+            length: trace.length,
+        })
+    }
+
+    /// Follows a ball sent out at `angle` through the expected cushions by
+    /// the mirror law. Returns `None` if it meets another cushion first or
+    /// touches one in a pocket mouth or on a jaw.
+    fn mirror(&self, angle: f32) -> Option<Trace> {
+        let (sin, cos) = math::sin_cos(angle);
+        let mut position = self.from;
+        let mut direction = Vector2D::new(cos, sin);
+        let mut length = 0.0;
+        let mut contacts = Vec::with_capacity(self.rails.len());
+
+        for &expected in self.rails {
+            let (rail, distance) = self.next_rail(position, direction)?;
+            let contact = position + direction * distance;
+            if rail != expected || self.nose(rail, contact).is_none() {
+                return None;
+            }
+            length += distance;
+            position = contact;
+            contacts.push(contact);
+            direction = match rail {
+                Rail::Top | Rail::Bottom => Vector2D::new(direction.x, -direction.y),
+                Rail::Left | Rail::Right => Vector2D::new(-direction.x, direction.y),
+            };
+        }
+
+        // The last leg must reach the target before any other cushion.
+        let offset = self.to - position;
+        let along = offset.dot(direction);
+        let (_, distance) = self.next_rail(position, direction)?;
+        if along <= 0.0 || along > distance + TOLERANCE {
+            return None;
+        }
+        Some(Trace {
+            contacts,
+            miss: direction.x * offset.y - direction.y * offset.x,
+            length: length + offset.length(),
+        })
+    }
+
+    /// Rolls a ball out at `angle` and `speed` through the physics, on an
+    /// otherwise empty table, until it passes the target. Returns `None` if
+    /// it meets the cushions in another order, touches one in a pocket mouth
+    /// or on a jaw, drops, or stops first.
+    fn roll(&self, angle: f32, speed: f32) -> Option<Trace> {
+        let (sin, cos) = math::sin_cos(angle);
+        let velocity = Vector2D::new(cos, sin) * speed;
+        let mut ball = Ball::new(
+            self.from.x,
+            self.from.y,
+            velocity.x,
+            velocity.y,
+            self.radius,
+        );
+        // Natural roll spins about the axis `z x v`.
+        ball.set_spin(-velocity.y / self.radius, velocity.x / self.radius, 0.0);
+        let mut state = GameState::new(self.table, vec![ball]);
+        let mut contacts = Vec::with_capacity(self.rails.len());
+        let mut length = 0.0;
+        let mut last = self.from;
+
+        for _ in 0..MAX_ROLL_STEPS {
+            let before = state.clone();
+            let first_new = state.events.len();
+            physics::advance(&mut state, ROLL_STEP);
+            for event in &state.events[first_new..] {
+                if event.kind != EventKind::CushionContact {
+                    continue;
+                }
+                let rail = event.rail()?;
+                if self.rails.get(contacts.len()) != Some(&rail) {
+                    return None;
+                }
+                // Replay the step up to the contact to find where it was.
+                let mut exact = before.clone();
+                let until = event.time - exact.time;
+                physics::advance(&mut exact, until);
+                let contact = exact.balls.first()?.position;
+                self.nose(rail, contact)?;
+                length += (contact - last).length();
+                last = contact;
+                contacts.push(contact);
+            }
+
+            let ball = state.balls.first()?;
+            if contacts.len() == self.rails.len() {
+                let offset = self.to - ball.position;
+                if offset.dot(ball.velocity) <= 0.0 {
+                    let speed = ball.velocity.length();
+                    if speed <= 0.0 {
+                        return None;
+                    }
+                    let direction = ball.velocity / speed;
+                    return Some(Trace {
+                        contacts,
+                        miss: direction.x * offset.y - direction.y * offset.x,
+                        length: length + (self.to - last).length(),
+                    });
+                }
+            }
+            if !ball.is_moving() {
+                return None;
+            }
+        }
+        None
+    }
+
+    /// Returns the first cushion line a ball at `position` moving along
+    /// `direction` reaches, and how far it travels to get there.
+    fn next_rail(&self, position: Vector2D, direction: Vector2D) -> Option<(Rail, f32)> {
+        let (w, h, r) = (self.table.width, self.table.height, self.radius);
+        let time = |gap: f32, rate: f32| (rate > 0.0).then(|| (gap / rate).max(0.0));
+        [
+            (Rail::Top, time(position.y - r, -direction.y)),
+            (Rail::Right, time(w - r - position.x, direction.x)),
+            (Rail::Bottom, time(h - r - position.y, direction.y)),
+            (Rail::Left, time(position.x - r, -direction.x)),
+        ]
+        .into_iter()
+        .filter_map(|(rail, distance)| distance.map(|distance| (rail, distance)))
+        // A ball that has just left a cushion is not caught by it again.
+        .filter(|&(_, distance)| distance > 1e-4)
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    /// Returns the straight nose of `rail` a ball with its centre at
+    /// `contact` touches, or `None` if it touches a pocket mouth or jaw.
+    fn nose(&self, rail: Rail, contact: Vector2D) -> Option<&Cushion> {
+        self.cushions.iter().find(|cushion| {
+            let span = cushion.end - cushion.start;
+            let straight = match rail {
+                Rail::Top | Rail::Bottom => span.y.abs() <= f32::EPSILON,
+                Rail::Left | Rail::Right => span.x.abs() <= f32::EPSILON,
+            };
+            let along = (contact - cushion.start).dot(span) / span.length_squared();
+            cushion.rail == rail && straight && (0.0..=1.0).contains(&along)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_rail_bank_follows_the_mirror() {
+        let table = Table::new(800.0, 400.0);
+        let from = Vector2D::new(200.0, 200.0);
+        let to = Vector2D::new(600.0, 200.0);
+
+        let paths = bank_paths(&table, from, to, 10.0, 1, None);
+
+        assert_eq!(paths.len(), 4);
+        let top = paths
+            .iter()
+            .find(|path| path.rails() == [Rail::Top])
+            .unwrap();
+        let bottom = paths
+            .iter()
+            .find(|path| path.rails() == [Rail::Bottom])
+            .unwrap();
+        assert!((top.aim_point().x - 400.0).abs() < 0.01);
+        assert!((top.aim_point().y - 10.0).abs() < 0.01);
+        assert!((bottom.aim_point().y - 390.0).abs() < 0.01);
+        assert!((top.length() - bottom.length()).abs() < 0.01);
+        assert!(paths
+            .windows(2)
+            .all(|pair| pair[0].length() <= pair[1].length()));
+    }
+
+    #[test]
+    fn two_rail_paths_meet_both_cushions_in_order() {
+        let table = Table::new(800.0, 400.0);
+        let from = Vector2D::new(150.0, 300.0);
+        let to = Vector2D::new(300.0, 320.0);
+
+        let paths = bank_paths(&table, from, to, 10.0, 2, None);
+
+        let path = paths
+            .iter()
+            .find(|path| path.rails() == [Rail::Top, Rail::Right])
+            .unwrap();
+        assert!((path.contacts()[0].y - 10.0).abs() < 0.01);
+        assert!((path.contacts()[1].x - 790.0).abs() < 0.01);
+        assert_eq!(path.path().len(), 8);
+    }
+
+    #[test]
+    fn pocket_mouths_are_not_banked_off() {
+        // A bank off the top rail aimed straight at the side pocket.
+        let table = Table::pool(800.0, 400.0);
+        let from = Vector2D::new(300.0, 200.0);
+        let to = Vector2D::new(500.0, 200.0);
+
+        let paths = bank_paths(&table, from, to, 10.0, 1, None);
+
+        assert!(paths
+            .iter()
+            .all(|path| !matches!(path.rails()[0], Rail::Top | Rail::Bottom)));
+    }
+
+    #[test]
+    fn rebound_model_lands_the_bank_where_the_physics_does() {
+        let table = Table::new(800.0, 400.0);
+        let from = Vector2D::new(200.0, 250.0);
+        let to = Vector2D::new(600.0, 250.0);
+        let speed = 500.0;
+
+        let mirror = bank_paths(&table, from, to, 10.0, 1, None);
+        let modelled = bank_paths(&table, from, to, 10.0, 1, Some(speed));
+        let (mirror, modelled) = (&mirror[0], &modelled[0]);
+        assert_eq!(mirror.rails(), modelled.rails());
+        // A cushion that gives back less than it takes has to be hit
+        // further along, so the aim is steeper than the mirror's.
+        assert!(modelled.aim_angle().abs() > mirror.aim_angle().abs());
+
+        // Rolling the ball along the modelled aim passes over the target.
+        let (sin, cos) = modelled.aim_angle().sin_cos();
+        let mut ball = Ball::new(from.x, from.y, cos * speed, sin * speed, 10.0);
+        ball.set_spin(-sin * speed / 10.0, cos * speed / 10.0, 0.0);
+        let mut state = GameState::new(table, vec![ball]);
+        let mut closest = f32::INFINITY;
+        for _ in 0..600 {
+            crate::tick(&mut state, 1.0 / 240.0);
+            closest = closest.min((state.balls()[0].position - to).length());
+        }
+        assert!(closest < 10.0, "missed the target by {closest}");
+    }
+
+    #[test]
+    fn kicks_stop_at_the_object_ball() {
+        let table = Table::new(800.0, 400.0);
+        let balls = vec![
+            Ball::new(200.0, 200.0, 0.0, 0.0, 10.0),
+            Ball::new(400.0, 200.0, 0.0, 0.0, 10.0),
+            Ball::new(600.0, 200.0, 0.0, 0.0, 10.0),
+        ];
+        let state = GameState::new(table, balls);
+
+        let kicks = state.kick_shots(2, 1, None);
+
+        assert!(!kicks.is_empty());
+        let path = kicks[0].path();
+        let end = Vector2D::new(path[path.len() - 2], path[path.len() - 1]);
+        assert!(((end - Vector2D::new(600.0, 200.0)).length() - 20.0).abs() < 0.01);
+    }
+}
@@ -0,0 +1,236 @@
+//! Ball-cloth friction.
+//!
+//! A ball struck without spin first skids over the cloth. Sliding friction
+//! opposes the velocity of the contact point, slowing the ball down while
+//! spinning it up, until the contact point is at rest relative to the cloth
+//! (natural roll). From then on the ball rolls, decelerating under the much
+//! weaker rolling resistance until it comes to rest.
+//!
+//! Spin is what makes the sliding phase interesting. Backspin (draw) makes
+//! the contact point run ahead of the ball, so friction pulls the ball back;
+//! topspin (follow) pushes it forward; a ball with no spin at all (stun)
+//! simply picks up roll. Spin about an axis that is not square to the line of
+//! travel produces a sideways friction component and curves the path
+//! (swerve). Sidespin is independent of the other components and bleeds off
+//! under the table's spinning friction.
+//!
+//! Each phase has a constant deceleration, so a friction update over an
+//! interval is solved in closed form, including a slide-to-roll transition
+//! part way through the interval.
+
+use crate::{Ball, Table, Vector2D, GRAVITY, REST_SPEED};
+
+/// Contact point slip speed (in units per second) below which a ball is
+/// treated as rolling without slipping.
+const ROLL_SLIP_THRESHOLD: f32 = 1e-3;
+
+/// Returns the velocity of the point where `ball` touches the cloth.
+///
+/// The contact point sits at `-radius` along the table normal, so it moves
+/// with `v + w x (0, 0, -r)`.
+#[must_use]
+pub fn contact_point_velocity(ball: &Ball) -> Vector2D {
+    let w = ball.angular_velocity;
+    Vector2D::new(
+        ball.velocity.x - ball.radius * w.y,
+        ball.velocity.y + ball.radius * w.x,
+    )
+}
+
+/// Applies `dt` seconds of cloth friction to `ball`.
+pub fn apply_friction(ball: &mut Ball, table: Table, dt: f32) {
+    decay_sidespin(ball, table, dt);
+
+    let mut remaining = dt;
+
+    let slip = contact_point_velocity(ball);
+    let slip_speed = slip.length();
+    if slip_speed > ROLL_SLIP_THRESHOLD {
+        let deceleration = table.slide_friction * GRAVITY;
+        if deceleration <= 0.0 {
+            return;
+        }
+        // The slip decays at 7/2 of the linear deceleration.
+        let slide_time = 2.0 * slip_speed / (7.0 * deceleration);
+        let elapsed = slide_time.min(remaining);
+        let direction = slip / slip_speed;
+        let spin_up = 2.5 * deceleration / ball.radius * elapsed;
+
+        ball.velocity -= direction * (deceleration * elapsed);
+        ball.angular_velocity.x -= direction.y * spin_up;
+        ball.angular_velocity.y += direction.x * spin_up;
+
+        if elapsed < slide_time {
+            return;
+        }
+        remaining -= elapsed;
+    }
+
+    roll(ball, table, remaining);
+}
+
+/// Returns `true` if the contact point of `ball` is not slipping over the
+/// cloth: the ball is rolling, or at rest with at most sidespin.
+#[must_use]
+pub fn is_rolling(ball: &Ball) -> bool {
+    contact_point_velocity(ball).length() <= ROLL_SLIP_THRESHOLD
+}
+
+/// Returns how long, in seconds, a rolling `ball` takes to come to rest, or
+/// infinity if the cloth has no rolling resistance.
+#[must_use]
+pub fn rolling_time(ball: &Ball, table: Table) -> f32 {
+    let deceleration = table.roll_friction * GRAVITY;
+    if deceleration <= 0.0 {
+        return f32::INFINITY;
+    }
+    ball.velocity.length() / deceleration
+}
+
+/// Rolls `ball` for `dt` seconds, moving it as well as slowing it.
+///
+/// The ball must be rolling. Under a constant deceleration the distance
+/// covered is exact, however long the interval, so a ball can be carried to
+/// its next contact or to rest in one call.
+pub fn roll_along(ball: &mut Ball, table: Table, dt: f32) {
+    let time = dt.min(rolling_time(ball, table));
+    let before = ball.velocity;
+    decay_sidespin(ball, table, dt);
+    roll(ball, table, time);
+    ball.position += (before + ball.velocity) * (time * 0.5);
+}
+
+/// Slows the sidespin of `ball` at a constant rate until it stops.
+fn decay_sidespin(ball: &mut Ball, table: Table, dt: f32) {
+    let spin = ball.angular_velocity.z;
+    let decay = 2.5 * table.spin_friction * GRAVITY / ball.radius * dt;
+    ball.angular_velocity.z = if spin.abs() <= decay {
+        0.0
+    } else {
+        spin - decay.copysign(spin)
+    };
+}
+
+/// Decelerates a ball that is rolling without slipping.
+fn roll(ball: &mut Ball, table: Table, dt: f32) {
+    let speed = ball.velocity.length();
+    let new_speed = speed - table.roll_friction * GRAVITY * dt;
+    if new_speed < REST_SPEED || speed <= 0.0 {
+        ball.velocity = Vector2D::ZERO;
+    } else {
+        ball.velocity = ball.velocity * (new_speed / speed);
+    }
+    set_natural_roll(ball);
+}
+
+/// Sets the in-plane angular velocity of `ball` to match its velocity.
+fn set_natural_roll(ball: &mut Ball) {
+    ball.angular_velocity.x = -ball.velocity.y / ball.radius;
+    ball.angular_velocity.y = ball.velocity.x / ball.radius;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stun_shot_reaches_natural_roll_at_five_sevenths_speed() {
+        let table = Table::new(800.0, 400.0);
+        let mut ball = Ball::new(0.0, 0.0, 700.0, 0.0, 10.0);
+
+        apply_friction(&mut ball, table, 1.0);
+
+        // Rolling resistance eats into the ideal 500 units/s during the interval.
+        assert!(ball.velocity.x < 500.0 && ball.velocity.x > 450.0);
+        assert!(contact_point_velocity(&ball).length() < ROLL_SLIP_THRESHOLD);
+    }
+
+    #[test]
+    fn slide_phase_is_split_exactly() {
+        let table = Table::new(800.0, 400.0);
+        let mut whole = Ball::new(0.0, 0.0, 700.0, 0.0, 10.0);
+        let mut split = whole.clone();
+
+        apply_friction(&mut whole, table, 0.1);
+        for _ in 0..10 {
+            apply_friction(&mut split, table, 0.01);
+        }
+
+        assert!((whole.velocity.x - split.velocity.x).abs() < 1e-2);
+        assert!((whole.angular_velocity.y - split.angular_velocity.y).abs() < 1e-3);
+    }
+
+    #[test]
+    fn slow_rolling_ball_stops() {
+        let table = Table::new(800.0, 400.0);
+        let mut ball = Ball::new(0.0, 0.0, 0.0, 2.0, 10.0);
+        set_natural_roll(&mut ball);
+
+        apply_friction(&mut ball, table, 0.1);
+
+        assert!(!ball.is_moving());
+        assert!(ball.angular_velocity.x.abs() < f32::EPSILON);
+    }
+
+    /// Runs `ball` under friction for `millis` milliseconds in 1 ms steps.
+    fn run(ball: &mut Ball, millis: u32) {
+        let table = Table::new(800.0, 400.0);
+        for _ in 0..millis {
+            apply_friction(ball, table, 0.001);
+            ball.position += ball.velocity * 0.001;
+        }
+    }
+
+    #[test]
+    fn draw_reverses_a_stopped_ball() {
+        // A cue ball that has just stopped dead against an object ball but
+        // still carries backspin.
+        let mut ball = Ball::new(0.0, 0.0, 0.0, 0.0, 10.0);
+        ball.set_spin(0.0, -60.0, 0.0);
+
+        run(&mut ball, 500);
+
+        assert!(ball.velocity.x < -100.0);
+        assert!(ball.topspin() > 0.0);
+    }
+
+    #[test]
+    fn follow_drives_a_stopped_ball_forward() {
+        let mut ball = Ball::new(0.0, 0.0, 0.0, 0.0, 10.0);
+        ball.set_spin(0.0, 60.0, 0.0);
+
+        run(&mut ball, 500);
+
+        assert!(ball.velocity.x > 100.0);
+        assert!(ball.velocity.y.abs() < 1e-3);
+    }
+
+    #[test]
+    fn swerve_curves_the_path() {
+        // Spin with a component along the line of travel, as from an
+        // elevated cue struck left of centre.
+        let mut ball = Ball::new(0.0, 0.0, 500.0, 0.0, 10.0);
+        ball.set_spin(-40.0, 0.0, 0.0);
+
+        run(&mut ball, 500);
+
+        assert!(ball.velocity.y.abs() > 10.0);
+        assert!(ball.position.y.abs() > 1.0);
+        assert!(contact_point_velocity(&ball).length() < ROLL_SLIP_THRESHOLD);
+    }
+
+    #[test]
+    fn sidespin_decays_without_moving_the_ball() {
+        let mut ball = Ball::new(0.0, 0.0, 0.0, 0.0, 10.0);
+        ball.set_spin(0.0, 0.0, 30.0);
+
+        run(&mut ball, 500);
+        let midway = ball.sidespin();
+        run(&mut ball, 5000);
+
+        assert!(midway > 0.0 && midway < 30.0);
+        assert!(ball.sidespin().abs() < f32::EPSILON);
+        assert!(!ball.is_moving());
+        assert!(ball.position.length() < f32::EPSILON);
+    }
+}
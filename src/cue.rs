@@ -0,0 +1,205 @@
+//! Cue strokes: turning a cue's aim, speed and tip placement into ball motion.
+//!
+//! The tip is treated as a frictionless impact between the cue and the ball.
+//! The impulse acts along the cue's axis at the point where the tip meets the
+//! ball, so its offset from the centre decides how much of the stroke turns
+//! into spin rather than speed. The component of the impulse that points into
+//! the table is absorbed by the slate; the ball does not jump.
+//!
+//! Side english also pushes the shaft sideways at impact, which deflects the
+//! cue ball away from the side of the tip (squirt). That deflection depends on
+//! the effective end mass of the shaft and is applied on top of the
+//! rigid-body result.
+
+use wasm_bindgen::prelude::*;
+
+use crate::{math, Ball, Vector2D, Vector3D};
+
+/// Mass of the cue, in kilograms.
+const CUE_MASS: f32 = 0.54;
+
+/// Effective mass of the end of the shaft that takes part in the impact, in
+/// kilograms. Lower values mean less squirt.
+const CUE_END_MASS: f32 = 0.01;
+
+/// Coefficient of restitution between the tip and the ball.
+const TIP_RESTITUTION: f32 = 0.75;
+
+/// Largest tip offset, as a fraction of the ball radius, before the tip
+/// would slip off the ball. Offsets beyond it are pulled back to the limit.
+pub const MISCUE_LIMIT: f32 = 0.6;
+
+/// The parameters of a single cue stroke.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CueStroke {
+    /// Direction of the cue in the table plane, in radians from the `x` axis.
+    pub aim_angle: f32,
+    /// Speed of the cue at impact, in units per second.
+    pub speed: f32,
+    /// Horizontal tip offset as a fraction of the ball radius; positive
+    /// values hit right of centre (right english) on a `y`-down canvas.
+    pub tip_x: f32,
+    /// Vertical tip offset as a fraction of the ball radius; positive values
+    /// hit above centre (follow), negative values below it (draw).
+    pub tip_y: f32,
+    /// Elevation of the butt of the cue above horizontal, in radians.
+    pub elevation: f32,
+}
+
+#[wasm_bindgen]
+impl CueStroke {
+    /// Creates a new `CueStroke`.
+    #[wasm_bindgen(constructor)]
+    #[must_use]
+    pub fn new(aim_angle: f32, speed: f32, tip_x: f32, tip_y: f32, elevation: f32) -> Self {
+        Self {
+            aim_angle,
+            speed,
+            tip_x,
+            tip_y,
+            elevation,
+        }
+    }
+
+    /// Returns `true` if the stroke can be played: its speed is positive and
+    /// finite, and its aim, tip offsets and elevation are finite.
+    #[must_use]
+    pub fn is_valid(&self) -> bool {
+        self.speed.is_finite()
+            && self.speed > 0.0
+            && self.aim_angle.is_finite()
+            && self.tip_x.is_finite()
+            && self.tip_y.is_finite()
+            && self.elevation.is_finite()
+    }
+}
+
+/// Computes the linear and angular velocity given to `ball` by `stroke`.
+#[must_use]
+pub fn impact(ball: &Ball, stroke: &CueStroke) -> (Vector2D, Vector3D) {
+    let radius = ball.radius;
+    let (tip_x, tip_y) = clamp_tip(stroke.tip_x, stroke.tip_y);
+    let (sin_aim, cos_aim) = math::sin_cos(stroke.aim_angle);
+    let (sin_elev, cos_elev) = math::sin_cos(stroke.elevation);
+
+    // Cue axis, and the two directions spanning the face the tip meets.
+    let axis = Vector3D::new(cos_elev * cos_aim, cos_elev * sin_aim, -sin_elev);
+    let right = Vector3D::new(-sin_aim, cos_aim, 0.0);
+    let up = Vector3D::new(sin_elev * cos_aim, sin_elev * sin_aim, cos_elev);
+
+    let a = tip_x * radius;
+    let b = tip_y * radius;
+    let depth = (radius * radius - a * a - b * b).sqrt();
+    let contact = right * a + up * b - axis * depth;
+
+    let lever = contact.cross(axis);
+    let inertia = 0.4 * ball.mass * radius * radius;
+    let inv_effective_mass = 1.0 / CUE_MASS + 1.0 / ball.mass + lever.dot(lever) / inertia;
+    let impulse = (1.0 + TIP_RESTITUTION) * stroke.speed / inv_effective_mass;
+
+    let velocity = axis.plane() * (impulse / ball.mass);
+    let angular_velocity = lever * (impulse / inertia);
+    (squirt(velocity, tip_x, ball.mass), angular_velocity)
+}
+
+/// Pulls a tip offset back inside [`MISCUE_LIMIT`].
+fn clamp_tip(tip_x: f32, tip_y: f32) -> (f32, f32) {
+    let offset = Vector2D::new(tip_x, tip_y).length();
+    if offset <= MISCUE_LIMIT {
+        return (tip_x, tip_y);
+    }
+    let scale = MISCUE_LIMIT / offset;
+    (tip_x * scale, tip_y * scale)
+}
+
+/// Deflects `velocity` away from the side of the ball the tip struck.
+///
+/// Uses the squirt angle of a ball struck `tip_x` radii off centre by a
+/// shaft with [`CUE_END_MASS`] at its tip.
+fn squirt(velocity: Vector2D, tip_x: f32, ball_mass: f32) -> Vector2D {
+    let cos_offset = (1.0 - tip_x * tip_x).sqrt();
+    let angle = math::atan2(
+        2.5 * tip_x * cos_offset,
+        1.0 + ball_mass / CUE_END_MASS + 2.5 * cos_offset * cos_offset,
+    );
+    // Right english (positive `tip_x`) pushes the ball to the left.
+    let (sin, cos) = math::sin_cos(-angle);
+    Vector2D::new(
+        velocity.x * cos - velocity.y * sin,
+        velocity.x * sin + velocity.y * cos,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cue_ball() -> Ball {
+        Ball::new(0.0, 0.0, 0.0, 0.0, 10.0)
+    }
+
+    #[test]
+    fn centre_ball_hit_goes_straight_without_spin() {
+        let (velocity, spin) = impact(&cue_ball(), &CueStroke::new(0.0, 300.0, 0.0, 0.0, 0.0));
+
+        let mass_ratio = crate::DEFAULT_BALL_MASS / CUE_MASS;
+        let expected = (1.0 + TIP_RESTITUTION) * 300.0 / (1.0 + mass_ratio);
+        assert!((velocity.x - expected).abs() < 1e-2);
+        assert!(velocity.y.abs() < 1e-4);
+        assert!(spin.length() < 1e-4);
+    }
+
+    #[test]
+    fn high_and_low_hits_give_follow_and_draw() {
+        let mut ball = cue_ball();
+        let (velocity, spin) = impact(&ball, &CueStroke::new(0.0, 300.0, 0.0, 0.4, 0.0));
+        ball.velocity = velocity;
+        ball.angular_velocity = spin;
+        assert!(ball.topspin() > 0.0);
+
+        let (velocity, spin) = impact(&ball, &CueStroke::new(0.0, 300.0, 0.0, -0.4, 0.0));
+        ball.velocity = velocity;
+        ball.angular_velocity = spin;
+        assert!(ball.topspin() < 0.0);
+        assert!(ball.sidespin().abs() < 1e-4);
+    }
+
+    #[test]
+    fn side_english_spins_and_squirts_the_other_way() {
+        let (right_v, right_w) = impact(&cue_ball(), &CueStroke::new(0.0, 300.0, 0.4, 0.0, 0.0));
+        let (left_v, left_w) = impact(&cue_ball(), &CueStroke::new(0.0, 300.0, -0.4, 0.0, 0.0));
+
+        assert!(right_w.z * left_w.z < 0.0);
+        // Right english is +y on a y-down canvas; the ball squirts to -y.
+        assert!(right_v.y < 0.0 && left_v.y > 0.0);
+        let squirt_angle = (right_v.y / right_v.x).abs().atan().to_degrees();
+        assert!(squirt_angle > 0.5 && squirt_angle < 5.0);
+    }
+
+    #[test]
+    fn off_centre_hits_convert_speed_into_spin() {
+        let (centre, _) = impact(&cue_ball(), &CueStroke::new(0.0, 300.0, 0.0, 0.0, 0.0));
+        let (low, _) = impact(&cue_ball(), &CueStroke::new(0.0, 300.0, 0.0, -0.5, 0.0));
+        assert!(low.length() < centre.length());
+    }
+
+    #[test]
+    fn elevated_side_hit_tilts_spin_axis_for_swerve() {
+        let (velocity, spin) = impact(&cue_ball(), &CueStroke::new(0.0, 300.0, 0.4, 0.0, 0.5));
+
+        // Spin about the line of travel is what curves a masse shot.
+        let along = spin.plane().dot(velocity) / velocity.length();
+        assert!(along.abs() > 1.0);
+    }
+
+    #[test]
+    fn tip_beyond_miscue_limit_is_clamped() {
+        let (x, y) = clamp_tip(0.9, 0.0);
+        assert!((x - MISCUE_LIMIT).abs() < 1e-6);
+        assert!(y.abs() < 1e-6);
+        let (velocity, _) = impact(&cue_ball(), &CueStroke::new(0.0, 300.0, 0.0, -2.0, 0.0));
+        assert!(velocity.x.is_finite() && velocity.x > 0.0);
+    }
+}
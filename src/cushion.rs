@@ -0,0 +1,355 @@
+//! Cushions: rail geometry and the ball-cushion rebound model.
+//!
+//! Each rail is a straight cushion nose running between the pocket mouths
+//! along one edge of the table. At every open pocket the cushion turns into
+//! a jaw, angled back towards the hole at the pocket's jaw angle; the corner
+//! where nose and jaw meet is the pocket point, which balls can strike just
+//! like a face.
+//!
+//! The nose of the cushion sits above the centre of the ball, so the contact
+//! normal tilts downward and part of every impact is absorbed by the slate.
+//! The remaining rebound is scaled by a restitution that falls off with
+//! impact speed. Friction at the contact point acts on the ball's spin:
+//! running english carries the ball further along the rail, check english
+//! holds it back, and topspin or backspin changes how it leaves the cushion.
+
+use wasm_bindgen::prelude::*;
+
+use crate::{math, pocket};
+use crate::{Ball, Table, Vector2D, Vector3D};
+
+/// Identifies the rail a cushion segment belongs to.
+///
+/// "Top" is the rail at `y = 0`, "bottom" the rail at `y = height`. Pocket
+/// jaws belong to the rail they are cut into.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Rail {
+    /// The rail along `y = 0`.
+    Top = 0,
+    /// The rail along `x = width`.
+    Right = 1,
+    /// The rail along `y = height`.
+    Bottom = 2,
+    /// The rail along `x = 0`.
+    Left = 3,
+}
+
+/// Lowest restitution the cushion falls to, however hard it is hit.
+const MIN_CUSHION_RESTITUTION: f32 = 0.5;
+
+/// A straight, one-sided piece of cushion.
+#[derive(Clone, Copy, Debug)]
+pub struct Cushion {
+    /// The rail this piece belongs to.
+    pub rail: Rail,
+    /// First end point.
+    pub start: Vector2D,
+    /// Second end point.
+    pub end: Vector2D,
+    /// Unit normal pointing away from the cushion, into play.
+    pub normal: Vector2D,
+}
+
+impl Cushion {
+    /// Creates a cushion whose normal faces towards `inside`.
+    fn facing(rail: Rail, start: Vector2D, end: Vector2D, inside: Vector2D) -> Self {
+        let direction = end - start;
+        let mut normal = Vector2D::new(-direction.y, direction.x) / direction.length();
+        if (inside - start).dot(normal) < 0.0 {
+            normal = -normal;
+        }
+        Self {
+            rail,
+            start,
+            end,
+            normal,
+        }
+    }
+
+    /// Returns the point of the cushion closest to `point`.
+    #[must_use]
+    pub fn closest_point(&self, point: Vector2D) -> Vector2D {
+        let span = self.end - self.start;
+        let along = ((point - self.start).dot(span) / span.length_squared()).clamp(0.0, 1.0);
+        self.start + span * along
+    }
+
+    /// Returns the time until `ball` touches this cushion, either on its face
+    /// or on one of its end points, assuming straight-line motion.
+    ///
+    /// Balls already touching the face while moving into it report an
+    /// immediate contact; balls behind the cushion never touch it.
+    #[must_use]
+    pub fn time_of_impact(&self, ball: &Ball) -> Option<f32> {
+        let distance = (ball.position - self.start).dot(self.normal);
+        if distance < 0.0 {
+            return None;
+        }
+
+        let mut earliest = None;
+        let closing_speed = -ball.velocity.dot(self.normal);
+        if closing_speed > 0.0 {
+            let time = ((distance - ball.radius) / closing_speed).max(0.0);
+            let span = self.end - self.start;
+            let at_impact = ball.position + ball.velocity * time;
+            let along = (at_impact - self.start).dot(span) / span.length_squared();
+            if (0.0..=1.0).contains(&along) {
+                earliest = Some(time);
+            }
+        }
+        for point in [self.start, self.end] {
+            if let Some(time) = point_time_of_impact(ball, point) {
+                earliest = Some(earliest.map_or(time, |best: f32| best.min(time)));
+            }
+        }
+        earliest
+    }
+
+    /// Returns `true` if `point` is within `distance` of the box bounding
+    /// this cushion.
+    #[must_use]
+    pub fn is_near(&self, point: Vector2D, distance: f32) -> bool {
+        point.x >= self.start.x.min(self.end.x) - distance
+            && point.x <= self.start.x.max(self.end.x) + distance
+            && point.y >= self.start.y.min(self.end.y) - distance
+            && point.y <= self.start.y.max(self.end.y) + distance
+    }
+
+    /// Returns `true` if `ball` overlaps this cushion from the front.
+    #[must_use]
+    pub fn overlaps(&self, ball: &Ball) -> bool {
+        (ball.position - self.start).dot(self.normal) >= 0.0
+            && (ball.position - self.closest_point(ball.position)).length() < ball.radius
+    }
+}
+
+/// Returns the time until `ball` touches the fixed `point`.
+fn point_time_of_impact(ball: &Ball, point: Vector2D) -> Option<f32> {
+    let offset = ball.position - point;
+    let approach = offset.dot(ball.velocity);
+    if approach >= 0.0 {
+        return None;
+    }
+    let gap = offset.length_squared() - ball.radius * ball.radius;
+    if gap <= 0.0 {
+        return Some(0.0);
+    }
+    let discriminant = approach * approach - ball.velocity.length_squared() * gap;
+    if discriminant < 0.0 {
+        return None;
+    }
+    Some(gap / (-approach + discriminant.sqrt()))
+}
+
+/// Lays out every cushion segment of `table`.
+///
+/// Rails run between pocket mouths, and every open pocket adds a jaw on
+/// each side of its mouth.
+#[must_use]
+pub fn cushions(table: Table) -> Vec<Cushion> {
+    let (w, h) = (table.width, table.height);
+    let centre = Vector2D::new(w * 0.5, h * 0.5);
+    let corner = table.corner_pockets.mouth * std::f32::consts::FRAC_1_SQRT_2;
+    let side = table.side_pockets.mouth * 0.5;
+
+    let mut cushions = Vec::with_capacity(18);
+    let mut rail = |rail: Rail, start: (f32, f32), end: (f32, f32)| {
+        let (start, end) = (Vector2D::new(start.0, start.1), Vector2D::new(end.0, end.1));
+        if (end - start).length_squared() > 0.0 {
+            cushions.push(Cushion::facing(rail, start, end, centre));
+        }
+    };
+
+    if table.side_pockets.is_open() {
+        rail(Rail::Top, (corner, 0.0), (w * 0.5 - side, 0.0));
+        rail(Rail::Top, (w * 0.5 + side, 0.0), (w - corner, 0.0));
+        rail(Rail::Bottom, (corner, h), (w * 0.5 - side, h));
+        rail(Rail::Bottom, (w * 0.5 + side, h), (w - corner, h));
+    } else {
+        rail(Rail::Top, (corner, 0.0), (w - corner, 0.0));
+        rail(Rail::Bottom, (corner, h), (w - corner, h));
+    }
+    rail(Rail::Left, (0.0, corner), (0.0, h - corner));
+    rail(Rail::Right, (w, corner), (w, h - corner));
+
+    for mouth in pocket::mouths(table) {
+        let middle = (mouth.start + mouth.end) * 0.5;
+        for point in [mouth.start, mouth.end] {
+            let (rail, along, outward) = rail_at(table, point, middle);
+            let (sin, cos) = math::sin_cos(mouth.spec.jaw_angle);
+            let length = mouth.spec.mouth * 0.5 + mouth.spec.shelf_depth;
+            let back = point + (along * cos + outward * sin) * length;
+            cushions.push(Cushion::facing(rail, point, back, middle + mouth.normal));
+        }
+    }
+
+    cushions
+}
+
+/// Returns the rail a pocket point lies on, the direction along that rail
+/// away from the pocket, and the rail's outward normal.
+fn rail_at(table: Table, point: Vector2D, mouth_middle: Vector2D) -> (Rail, Vector2D, Vector2D) {
+    let away_x = Vector2D::new((point.x - mouth_middle.x).signum(), 0.0);
+    let away_y = Vector2D::new(0.0, (point.y - mouth_middle.y).signum());
+    if point.y <= 0.0 {
+        (Rail::Top, away_x, Vector2D::new(0.0, -1.0))
+    } else if point.y >= table.height {
+        (Rail::Bottom, away_x, Vector2D::new(0.0, 1.0))
+    } else if point.x <= 0.0 {
+        (Rail::Left, away_y, Vector2D::new(-1.0, 0.0))
+    } else {
+        (Rail::Right, away_y, Vector2D::new(1.0, 0.0))
+    }
+}
+
+/// Returns the restitution of the cushion for an impact at `speed`.
+#[must_use]
+pub fn restitution(table: Table, speed: f32) -> f32 {
+    (table.cushion_restitution - table.cushion_restitution_falloff * speed)
+        .max(MIN_CUSHION_RESTITUTION.min(table.cushion_restitution))
+}
+
+/// Bounces `ball` off `cushion`, placing it flush against the cushion.
+///
+/// Returns the speed at which the ball struck the cushion, along the contact
+/// normal, or zero if it was not moving into it.
+pub fn resolve(ball: &mut Ball, cushion: &Cushion, table: Table) -> f32 {
+    let closest = cushion.closest_point(ball.position);
+    let offset = ball.position - closest;
+    let distance = offset.length();
+    let n = if distance > f32::EPSILON {
+        offset / distance
+    } else {
+        cushion.normal
+    };
+    if distance < ball.radius {
+        ball.position = closest + n * ball.radius;
+    }
+
+    let approach = ball.velocity.dot(n);
+    if approach >= 0.0 {
+        return 0.0;
+    }
+
+    // The nose meets the ball above its centre, so the cushion pushes the
+    // ball both back into play and down into the slate.
+    let sin_tilt = ((table.cushion_height - ball.radius) / ball.radius).clamp(-1.0, 1.0);
+    let cos_tilt = (1.0 - sin_tilt * sin_tilt).sqrt();
+    let push = Vector3D::new(n.x * cos_tilt, n.y * cos_tilt, -sin_tilt);
+    let arm = push * -ball.radius;
+
+    let e = restitution(table, -approach);
+    let normal_impulse = -(1.0 + e) * ball.mass * Vector3D::from_plane(ball.velocity).dot(push);
+    ball.velocity += (push * (normal_impulse / ball.mass)).plane();
+
+    let point_velocity = Vector3D::from_plane(ball.velocity) + ball.angular_velocity.cross(arm);
+    let slip = point_velocity - push * point_velocity.dot(push);
+    let slip_speed = slip.length();
+    if slip_speed <= f32::EPSILON {
+        return -approach;
+    }
+    // As between two balls, the surface resists slip with 7/2 of the
+    // ball's inverse mass.
+    let magnitude = (slip_speed * ball.mass / 3.5).min(table.cushion_friction * normal_impulse);
+    let impulse = slip * (-magnitude / slip_speed);
+    ball.velocity += impulse.plane() / ball.mass;
+    ball.angular_velocity += arm.cross(impulse) * (2.5 / (ball.mass * ball.radius * ball.radius));
+    -approach
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn top_rail(table: Table) -> Cushion {
+        cushions(table)
+            .into_iter()
+            .find(|c| c.rail == Rail::Top)
+            .unwrap()
+    }
+
+    #[test]
+    fn pool_table_has_rails_and_jaws() {
+        assert_eq!(cushions(Table::new(800.0, 400.0)).len(), 4);
+        assert_eq!(cushions(Table::pool(800.0, 400.0)).len(), 6 + 12);
+        let table = Table::pool(800.0, 400.0);
+        let all = cushions(table);
+        let centre = Vector2D::new(400.0, 200.0);
+        for rail in &all[..6] {
+            assert!((centre - rail.start).dot(rail.normal) > 0.0);
+        }
+        // Both jaws of every pocket face into its throat.
+        for mouth in pocket::mouths(table) {
+            let throat = (mouth.start + mouth.end) * 0.5 + mouth.normal * 5.0;
+            let facing = all[6..]
+                .iter()
+                .filter(|jaw| {
+                    let from_point = |point: Vector2D| (jaw.start - point).length() < 1e-3;
+                    from_point(mouth.start) || from_point(mouth.end)
+                })
+                .filter(|jaw| (throat - jaw.start).dot(jaw.normal) > 0.0)
+                .count();
+            assert_eq!(facing, 2);
+        }
+    }
+
+    #[test]
+    fn harder_impacts_rebound_proportionally_less() {
+        let table = Table::new(800.0, 400.0);
+        let rail = top_rail(table);
+        let mut slow = Ball::new(400.0, 10.0, 0.0, -100.0, 10.0);
+        let mut fast = Ball::new(400.0, 10.0, 0.0, -1500.0, 10.0);
+
+        resolve(&mut slow, &rail, table);
+        resolve(&mut fast, &rail, table);
+
+        let slow_ratio = slow.velocity.y / 100.0;
+        let fast_ratio = fast.velocity.y / 1500.0;
+        assert!(slow_ratio > fast_ratio && fast_ratio > 0.3);
+        assert!(slow_ratio < 1.0);
+    }
+
+    #[test]
+    fn running_english_widens_the_rebound() {
+        let table = Table::new(800.0, 400.0);
+        let rail = top_rail(table);
+        let rebound = |sidespin: f32| {
+            let mut ball = Ball::new(400.0, 10.0, 300.0, -300.0, 10.0);
+            ball.set_spin(0.0, 0.0, sidespin);
+            resolve(&mut ball, &rail, table);
+            ball.velocity.x
+        };
+
+        let running = rebound(-40.0);
+        let plain = rebound(0.0);
+        let check = rebound(40.0);
+        assert!(running > plain && plain > check);
+    }
+
+    #[test]
+    fn rebound_picks_up_sidespin_from_the_cushion() {
+        let table = Table::new(800.0, 400.0);
+        let mut ball = Ball::new(400.0, 10.0, 300.0, -300.0, 10.0);
+        resolve(&mut ball, &top_rail(table), table);
+        assert!(ball.sidespin().abs() > 1.0);
+    }
+
+    #[test]
+    fn ball_meets_pocket_point_before_the_mouth() {
+        let table = Table::pool(800.0, 400.0);
+        let point = Vector2D::new(400.0 - table.side_pockets.mouth * 0.5, 0.0);
+        let jaw = cushions(table)
+            .into_iter()
+            .find(|c| (c.start - point).length() < 1e-3)
+            .unwrap();
+        // Centre inside the mouth, but close enough to the point to clip it.
+        let ball = Ball::new(point.x + 2.0, 100.0, 0.0, -100.0, 10.0);
+
+        let time = jaw.time_of_impact(&ball).unwrap();
+
+        let expected = (90.0 + 10.0 - 96.0_f32.sqrt()) / 100.0;
+        assert!((time - expected).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,181 @@
+//! Simulation events.
+//!
+//! While it advances the table, the simulation records what happens in the
+//! order it happens: balls striking each other or a cushion, balls dropping
+//! into pockets and balls coming to rest. Rules, sound and replays all read
+//! this stream rather than trying to reconstruct it from ball positions.
+//!
+//! Events are a flat record so they can cross into JS as they are; fields
+//! that do not apply to an event's kind are `None`.
+
+use wasm_bindgen::prelude::*;
+
+use crate::snapshot::{Decode, Encode, Reader, SnapshotError};
+use crate::{Pocket, Rail};
+
+/// What kind of thing an [`Event`] reports.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EventKind {
+    /// Two balls struck each other.
+    BallContact = 0,
+    /// A ball struck a cushion.
+    CushionContact = 1,
+    /// A ball dropped into a pocket.
+    Pocketed = 2,
+    /// A ball came to rest.
+    BallStopped = 3,
+    /// The last moving ball came to rest, or left the table.
+    AllStopped = 4,
+}
+
+/// Something that happened on the table.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Event {
+    /// What happened.
+    pub kind: EventKind,
+    /// Simulation time at which it happened, in seconds.
+    pub time: f32,
+    /// The ball involved, or the first of two balls in contact.
+    ball: Option<u32>,
+    /// The second ball of a ball-ball contact.
+    other_ball: Option<u32>,
+    /// The rail struck in a cushion contact.
+    rail: Option<Rail>,
+    /// The pocket a ball dropped into.
+    pocket: Option<Pocket>,
+    /// Closing speed along the contact normal, in units per second.
+    speed: Option<f32>,
+}
+
+#[wasm_bindgen]
+impl Event {
+    /// Returns the id of the ball involved, or of the first of two balls in
+    /// contact. Undefined for [`EventKind::AllStopped`].
+    #[must_use]
+    pub fn ball(&self) -> Option<u32> {
+        self.ball
+    }
+
+    /// Returns the id of the second ball in a ball-ball contact.
+    #[must_use]
+    pub fn other_ball(&self) -> Option<u32> {
+        self.other_ball
+    }
+
+    /// Returns the rail struck in a cushion contact.
+    #[must_use]
+    pub fn rail(&self) -> Option<Rail> {
+        self.rail
+    }
+
+    /// Returns the pocket a ball dropped into.
+    #[must_use]
+    pub fn pocket(&self) -> Option<Pocket> {
+        self.pocket
+    }
+
+    /// Returns the closing speed of a contact along its normal, in units per
+    /// second.
+    #[must_use]
+    pub fn speed(&self) -> Option<f32> {
+        self.speed
+    }
+}
+
+impl Event {
+    /// An event of `kind` at `time` with no details filled in.
+    const fn at(kind: EventKind, time: f32) -> Self {
+        Self {
+            kind,
+            time,
+            ball: None,
+            other_ball: None,
+            rail: None,
+            pocket: None,
+            speed: None,
+        }
+    }
+
+    /// Balls `ball` and `other` struck each other at `speed`.
+    #[must_use]
+    pub const fn ball_contact(time: f32, ball: u32, other: u32, speed: f32) -> Self {
+        Self {
+            ball: Some(ball),
+            other_ball: Some(other),
+            speed: Some(speed),
+            ..Self::at(EventKind::BallContact, time)
+        }
+    }
+
+    /// Ball `ball` struck `rail` at `speed`.
+    #[must_use]
+    pub const fn cushion_contact(time: f32, ball: u32, rail: Rail, speed: f32) -> Self {
+        Self {
+            ball: Some(ball),
+            rail: Some(rail),
+            speed: Some(speed),
+            ..Self::at(EventKind::CushionContact, time)
+        }
+    }
+
+    /// Ball `ball` dropped into `pocket`.
+    #[must_use]
+    pub const fn pocketed(time: f32, ball: u32, pocket: Pocket) -> Self {
+        Self {
+            ball: Some(ball),
+            pocket: Some(pocket),
+            ..Self::at(EventKind::Pocketed, time)
+        }
+    }
+
+    /// Ball `ball` came to rest.
+    #[must_use]
+    pub const fn ball_stopped(time: f32, ball: u32) -> Self {
+        Self {
+            ball: Some(ball),
+            ..Self::at(EventKind::BallStopped, time)
+        }
+    }
+
+    /// Every ball on the table is at rest.
+    #[must_use]
+    pub const fn all_stopped(time: f32) -> Self {
+        Self::at(EventKind::AllStopped, time)
+    }
+
+    /// Returns `true` if `ball` took part in this event.
+    #[must_use]
+    pub fn involves(&self, ball: u32) -> bool {
+        self.ball == Some(ball) || self.other_ball == Some(ball)
+    }
+}
+
+impl Encode for Event {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.kind.encode(out);
+        self.time.encode(out);
+        self.ball.encode(out);
+        self.other_ball.encode(out);
+        self.rail.encode(out);
+        self.pocket.encode(out);
+        self.speed.encode(out);
+    }
+}
+
+impl Decode for Event {
+    fn decode(input: &mut Reader<'_>) -> Result<Self, SnapshotError> {
+        Ok(Self {
+            kind: input.read()?,
+            time: input.read()?,
+            ball: input.read()?,
+            other_ball: input.read()?,
+            rail: input.read()?,
+            pocket: input.read()?,
+            speed: input.read()?,
+        })
+    }
+}
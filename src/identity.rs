@@ -0,0 +1,165 @@
+//! Ball identity: what a ball is and how it looks.
+//!
+//! A ball's [`BallKind`] says what part it plays in the game (cue ball,
+//! solid or stripe, a red or one of the snooker colours, the yellow or red
+//! of carom) and its display colour is a packed `0xRRGGBB` value the
+//! frontend can draw with directly.
+
+use wasm_bindgen::prelude::*;
+
+/// What a ball is, independent of the game being played.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BallKind {
+    /// An unmarked ball with no particular role.
+    Plain = 0,
+    /// The white cue ball.
+    Cue = 1,
+    /// A pool ball numbered 1 to 7, coloured all over.
+    Solid = 2,
+    /// The black 8 ball.
+    Eight = 3,
+    /// A pool ball numbered 9 to 15, with a coloured band.
+    Stripe = 4,
+    /// A snooker red, worth one point.
+    Red = 5,
+    /// The snooker yellow, worth two points.
+    Yellow = 6,
+    /// The snooker green, worth three points.
+    Green = 7,
+    /// The snooker brown, worth four points.
+    Brown = 8,
+    /// The snooker blue, worth five points.
+    Blue = 9,
+    /// The snooker pink, worth six points.
+    Pink = 10,
+    /// The snooker black, worth seven points.
+    Black = 11,
+    /// The second player's cue ball in carom, yellow.
+    SecondCue = 12,
+    /// The red object ball of carom.
+    CaromRed = 13,
+}
+
+impl BallKind {
+    /// The six snooker colours, in the order they are potted at the end of
+    /// a frame.
+    pub const COLOURS: [Self; 6] = [
+        Self::Yellow,
+        Self::Green,
+        Self::Brown,
+        Self::Blue,
+        Self::Pink,
+        Self::Black,
+    ];
+
+    /// Returns the kind of the pool ball numbered `number`, with 0 being the
+    /// cue ball. Numbers above 15 give [`BallKind::Plain`].
+    #[must_use]
+    pub const fn pool(number: u32) -> Self {
+        match number {
+            0 => Self::Cue,
+            1..=7 => Self::Solid,
+            8 => Self::Eight,
+            9..=15 => Self::Stripe,
+            _ => Self::Plain,
+        }
+    }
+
+    /// Returns the points a snooker ball of this kind is worth, or zero for
+    /// balls that are not snooker balls.
+    #[must_use]
+    pub const fn value(self) -> u32 {
+        match self {
+            Self::Red => 1,
+            Self::Yellow => 2,
+            Self::Green => 3,
+            Self::Brown => 4,
+            Self::Blue => 5,
+            Self::Pink => 6,
+            Self::Black => 7,
+            _ => 0,
+        }
+    }
+
+    /// Returns `true` for the snooker colours, yellow through black.
+    #[must_use]
+    pub const fn is_colour(self) -> bool {
+        self.value() > 1
+    }
+}
+
+/// Colour of the cue ball and of unmarked balls.
+pub const WHITE: u32 = 0x00F4_F1E6;
+
+/// Colours of the pool balls 1 to 8; the stripes 9 to 15 repeat 1 to 7.
+const POOL_COLORS: [u32; 8] = [
+    0x00F2_C12E, // yellow
+    0x001E_4BA8, // blue
+    0x00C8_2A24, // red
+    0x005A_2D82, // purple
+    0x00EE_7A1C, // orange
+    0x0018_7A3C, // green
+    0x0076_1F1F, // maroon
+    0x0014_1414, // black
+];
+
+/// Returns the display colour of the pool ball numbered `number`.
+#[must_use]
+pub const fn pool_color(number: u32) -> u32 {
+    match number {
+        1..=15 => POOL_COLORS[((number - 1) % 8) as usize],
+        _ => WHITE,
+    }
+}
+
+/// Returns the display colour of a ball of `kind` that has no number.
+#[must_use]
+pub const fn kind_color(kind: BallKind) -> u32 {
+    match kind {
+        BallKind::Plain | BallKind::Cue => WHITE,
+        BallKind::Solid | BallKind::Stripe => POOL_COLORS[0],
+        BallKind::Eight | BallKind::Black => POOL_COLORS[7],
+        BallKind::Red => 0x00B7_1C1C,
+        BallKind::Yellow => 0x00F5_D000,
+        BallKind::Green => 0x0000_7A33,
+        BallKind::Brown => 0x006B_3F1D,
+        BallKind::Blue => 0x0014_4FC6,
+        BallKind::Pink => 0x00F2_8DB2,
+        BallKind::SecondCue => 0x00F2_D54B,
+        BallKind::CaromRed => 0x00C4_1E24,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pool_numbers_map_to_suits() {
+        assert_eq!(BallKind::pool(0), BallKind::Cue);
+        assert_eq!(BallKind::pool(3), BallKind::Solid);
+        assert_eq!(BallKind::pool(8), BallKind::Eight);
+        assert_eq!(BallKind::pool(12), BallKind::Stripe);
+        assert_eq!(BallKind::pool(16), BallKind::Plain);
+    }
+
+    #[test]
+    fn stripes_share_the_colour_of_their_solid() {
+        for number in 1..8 {
+            assert_eq!(pool_color(number), pool_color(number + 8));
+        }
+        assert_eq!(pool_color(8), kind_color(BallKind::Eight));
+        assert_eq!(pool_color(0), WHITE);
+    }
+
+    #[test]
+    fn snooker_values_run_from_red_to_black() {
+        assert_eq!(BallKind::Red.value(), 1);
+        let values: Vec<u32> = BallKind::COLOURS.iter().map(|c| c.value()).collect();
+        assert_eq!(values, [2, 3, 4, 5, 6, 7]);
+        assert!(!BallKind::Red.is_colour() && BallKind::Pink.is_colour());
+        assert_eq!(BallKind::Stripe.value(), 0);
+    }
+}
@@ -1,11 +1,155 @@
 #![warn(clippy::all, clippy::pedantic, clippy::nursery, clippy::cargo)]
 #![allow(clippy::restriction)]
+// `#[wasm_bindgen]` exports cannot be `const fn`.
+#![allow(clippy::missing_const_for_fn)]
+// `mul_add` falls back to a slow software routine on targets without FMA,
+// including `wasm32-unknown-unknown`.
+#![allow(clippy::suboptimal_flops)]
+// `#[wasm_bindgen]` adds `unsafe` glue to every exported type, which this lint
+// reports on each of them that derives `Deserialize`.
+#![allow(clippy::unsafe_derive_deserialize)]
+
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
 
 use wasm_bindgen::prelude::*;
 
+mod ai;
+mod bank;
+mod cloth;
+mod cue;
+mod cushion;
+mod event;
+mod identity;
+mod match_play;
+mod math;
+mod physics;
+mod pocket;
+mod predict;
+mod rack;
+mod replay;
+mod rng;
+mod rules;
+#[cfg(feature = "serde")]
+mod save;
+mod snapshot;
+mod stepper;
+mod trajectory;
+
+pub use ai::{Difficulty, PlannedShot, Planner, ShotKind};
+pub use bank::{bank_paths, BankPath, MAX_BANK_RAILS};
+pub use cue::{CueStroke, MISCUE_LIMIT};
+pub use cushion::Rail;
+pub use event::{Event, EventKind};
+pub use identity::BallKind;
+pub use match_play::{BreakFormat, Discipline, Match, MatchFormat, MATCH_VERSION};
+pub use pocket::{Pocket, PocketSpec, PocketedBall};
+pub use predict::Prediction;
+pub use replay::{Replay, ReplayFrames, REPLAY_VERSION};
+pub use rules::{
+    BallInHand, Carom, CaromGame, EightBall, Foul, Group, Rotation, Rules, ShotRecord, Snooker,
+    StraightPool,
+};
+#[cfg(feature = "serde")]
+pub use save::{SaveError, SAVE_VERSION};
+pub use snapshot::{SnapshotError, SNAPSHOT_VERSION};
+pub use stepper::{RenderState, Stepper};
+pub use trajectory::Trajectory;
+
+use rng::Rng;
+
+/// Default mass of a ball, in kilograms (a regulation pool ball).
+pub const DEFAULT_BALL_MASS: f32 = 0.17;
+
+/// Coefficient of restitution applied to ball-to-ball contacts.
+pub const BALL_RESTITUTION: f32 = 0.95;
+
+/// Number of simulation length units per metre.
+///
+/// The default 800 by 400 table corresponds to the 2.54 m by 1.27 m playing
+/// surface of a 9ft pool table.
+pub const UNITS_PER_METER: f32 = 315.0;
+
+/// Gravitational acceleration, in simulation units per second squared.
+pub const GRAVITY: f32 = 9.81 * UNITS_PER_METER;
+
+/// Radius of a regulation pool ball (57.15 mm), in units.
+pub const POOL_BALL_RADIUS: f32 = 0.028_575 * UNITS_PER_METER;
+
+/// Radius of a snooker ball (52.5 mm), in units.
+pub const SNOOKER_BALL_RADIUS: f32 = 0.026_25 * UNITS_PER_METER;
+
+/// Mass of a snooker ball, in kilograms.
+pub const SNOOKER_BALL_MASS: f32 = 0.142;
+
+/// Radius of a carom ball (61.5 mm), in units.
+pub const CAROM_BALL_RADIUS: f32 = 0.030_75 * UNITS_PER_METER;
+
+/// Mass of a carom ball, in kilograms.
+pub const CAROM_BALL_MASS: f32 = 0.21;
+
+/// Default sliding friction coefficient between ball and cloth.
+pub const DEFAULT_SLIDE_FRICTION: f32 = 0.2;
+
+/// Default rolling resistance coefficient between ball and cloth.
+pub const DEFAULT_ROLL_FRICTION: f32 = 0.01;
+
+/// Default spinning friction coefficient, which bleeds off sidespin.
+pub const DEFAULT_SPIN_FRICTION: f32 = 0.014;
+
+/// Default height of the cushion nose above the cloth, in units.
+///
+/// Regulation noses sit at about 63.5% of the diameter of a
+/// [pool ball](POOL_BALL_RADIUS).
+pub const DEFAULT_CUSHION_HEIGHT: f32 = 1.27 * POOL_BALL_RADIUS;
+
+/// Default restitution of a cushion struck gently.
+pub const DEFAULT_CUSHION_RESTITUTION: f32 = 0.9;
+
+/// Default loss of cushion restitution per unit per second of impact speed.
+pub const DEFAULT_CUSHION_RESTITUTION_FALLOFF: f32 = 0.0002;
+
+/// Default coefficient of friction between ball and cushion.
+pub const DEFAULT_CUSHION_FRICTION: f32 = 0.2;
+
+/// Height of the cushion nose on a snooker table, in units.
+///
+/// As on a pool table, the nose sits at about 63.5% of the ball diameter.
+pub const SNOOKER_CUSHION_HEIGHT: f32 = 1.27 * SNOOKER_BALL_RADIUS;
+
+/// Height of the cushion nose on a carom table (37 mm), in units.
+pub const CAROM_CUSHION_HEIGHT: f32 = 0.037 * UNITS_PER_METER;
+
+/// Sliding friction of the cloth on a heated carom table. Heating keeps the
+/// cloth dry and smooth, so balls skid and roll further than on pool cloth.
+pub const HEATED_SLIDE_FRICTION: f32 = 0.16;
+
+/// Rolling resistance of the cloth on a heated carom table.
+pub const HEATED_ROLL_FRICTION: f32 = 0.006;
+
+/// Distance of the baulk line from the baulk cushion, as a fraction of the
+/// table length.
+pub const SNOOKER_BAULK_LINE: f32 = 737.0 / 3569.0;
+
+/// Radius of the D, as a fraction of the table length.
+pub const SNOOKER_D_RADIUS: f32 = 292.0 / 3569.0;
+
+/// Coefficient of friction between two balls at the moment of contact.
+///
+/// This is what lets spin be transferred between balls and what throws the
+/// object ball slightly off the line of centres.
+pub const BALL_FRICTION: f32 = 0.06;
+
+/// Speed (in units per second) below which a rolling ball comes to rest.
+pub const REST_SPEED: f32 = 0.5;
+
+/// Id given to the cue ball by the rack constructors and by
+/// [`GameState::new`].
+pub const CUE_BALL_ID: u32 = 0;
+
 /// A 2D vector representing a position or velocity in the simulation space.
 #[wasm_bindgen]
 #[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Vector2D {
     /// The horizontal component.
     pub x: f32,
@@ -17,112 +161,585 @@ pub struct Vector2D {
 impl Vector2D {
     /// Creates a new `Vector2D` with the given components.
     #[wasm_bindgen(constructor)]
-    pub fn new(x: f32, y: f32) -> Vector2D {
-        Vector2D { x, y }
+    #[must_use]
+    pub fn new(x: f32, y: f32) -> Self {
+        Self { x, y }
+    }
+}
+
+impl Vector2D {
+    /// The zero vector.
+    pub const ZERO: Self = Self { x: 0.0, y: 0.0 };
+
+    /// Returns the dot product of `self` and `other`.
+    #[must_use]
+    pub fn dot(self, other: Self) -> f32 {
+        self.x * other.x + self.y * other.y
+    }
+
+    /// Returns the squared Euclidean length of the vector.
+    #[must_use]
+    pub fn length_squared(self) -> f32 {
+        self.dot(self)
+    }
+
+    /// Returns the Euclidean length of the vector.
+    #[must_use]
+    pub fn length(self) -> f32 {
+        self.length_squared().sqrt()
+    }
+
+    /// Returns `true` if both components are finite.
+    #[must_use]
+    pub const fn is_finite(self) -> bool {
+        self.x.is_finite() && self.y.is_finite()
+    }
+}
+
+impl Add for Vector2D {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self::new(self.x + rhs.x, self.y + rhs.y)
+    }
+}
+
+impl AddAssign for Vector2D {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for Vector2D {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self::new(self.x - rhs.x, self.y - rhs.y)
+    }
+}
+
+impl SubAssign for Vector2D {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl Mul<f32> for Vector2D {
+    type Output = Self;
+
+    fn mul(self, rhs: f32) -> Self {
+        Self::new(self.x * rhs, self.y * rhs)
+    }
+}
+
+impl Div<f32> for Vector2D {
+    type Output = Self;
+
+    fn div(self, rhs: f32) -> Self {
+        Self::new(self.x / rhs, self.y / rhs)
+    }
+}
+
+impl Neg for Vector2D {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::new(-self.x, -self.y)
     }
 }
 
 /// Creates a new `Vector2D` from `x` and `y`.
 #[wasm_bindgen]
+#[must_use]
 pub fn new_vector2d(x: f32, y: f32) -> Vector2D {
     Vector2D { x, y }
 }
 
-/// A ball in the pool simulation with position, velocity, and radius.
+/// A 3D vector, used for angular velocities about the table axes.
+///
+/// `x` and `y` lie in the plane of the table and `z` points up out of the
+/// cloth, so rotation about `z` is sidespin.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Vector3D {
+    /// The component along the table's horizontal axis.
+    pub x: f32,
+    /// The component along the table's vertical axis.
+    pub y: f32,
+    /// The component perpendicular to the table surface.
+    pub z: f32,
+}
+
+#[wasm_bindgen]
+impl Vector3D {
+    /// Creates a new `Vector3D` with the given components.
+    #[wasm_bindgen(constructor)]
+    #[must_use]
+    pub fn new(x: f32, y: f32, z: f32) -> Self {
+        Self { x, y, z }
+    }
+}
+
+impl Vector3D {
+    /// The zero vector.
+    pub const ZERO: Self = Self {
+        x: 0.0,
+        y: 0.0,
+        z: 0.0,
+    };
+
+    /// Lifts an in-plane vector into 3D with a zero `z` component.
+    #[must_use]
+    pub const fn from_plane(v: Vector2D) -> Self {
+        Self {
+            x: v.x,
+            y: v.y,
+            z: 0.0,
+        }
+    }
+
+    /// Returns `true` if every component is finite.
+    #[must_use]
+    pub const fn is_finite(self) -> bool {
+        self.x.is_finite() && self.y.is_finite() && self.z.is_finite()
+    }
+
+    /// Returns the in-plane (`x`, `y`) part of the vector.
+    #[must_use]
+    pub const fn plane(self) -> Vector2D {
+        Vector2D {
+            x: self.x,
+            y: self.y,
+        }
+    }
+
+    /// Returns the dot product of `self` and `other`.
+    #[must_use]
+    pub fn dot(self, other: Self) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    /// Returns the cross product `self x other`.
+    #[must_use]
+    pub fn cross(self, other: Self) -> Self {
+        Self::new(
+            self.y * other.z - self.z * other.y,
+            self.z * other.x - self.x * other.z,
+            self.x * other.y - self.y * other.x,
+        )
+    }
+
+    /// Returns the Euclidean length of the vector.
+    #[must_use]
+    pub fn length(self) -> f32 {
+        self.dot(self).sqrt()
+    }
+}
+
+impl Add for Vector3D {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self::new(self.x + rhs.x, self.y + rhs.y, self.z + rhs.z)
+    }
+}
+
+impl AddAssign for Vector3D {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for Vector3D {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self::new(self.x - rhs.x, self.y - rhs.y, self.z - rhs.z)
+    }
+}
+
+impl SubAssign for Vector3D {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl Mul<f32> for Vector3D {
+    type Output = Self;
+
+    fn mul(self, rhs: f32) -> Self {
+        Self::new(self.x * rhs, self.y * rhs, self.z * rhs)
+    }
+}
+
+impl Neg for Vector3D {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::new(-self.x, -self.y, -self.z)
+    }
+}
+
+/// A ball in the pool simulation with position, velocity, radius, and mass.
 #[wasm_bindgen]
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Ball {
+    /// Identifies the ball for as long as it exists, even after it has been
+    /// pocketed and removed from play.
+    pub id: u32,
+    /// The number printed on the ball, or zero for unnumbered balls.
+    pub number: u32,
+    /// What the ball is: cue ball, solid, stripe, snooker red and so on.
+    pub kind: BallKind,
+    /// Display colour of the ball, packed as `0xRRGGBB`.
+    pub color: u32,
     /// The current position of the ball.
     pub position: Vector2D,
     /// The current velocity of the ball.
     pub velocity: Vector2D,
     /// The radius of the ball.
     pub radius: f32,
+    /// The mass of the ball, used to weight collision impulses.
+    pub mass: f32,
+    /// The angular velocity of the ball, in radians per second.
+    ///
+    /// Rotation about `z` is sidespin; the in-plane components carry
+    /// topspin and backspin (and, when tilted off the line of travel, the
+    /// swerve of a masse shot).
+    pub angular_velocity: Vector3D,
 }
 
 #[wasm_bindgen]
 impl Ball {
     /// Creates a new `Ball` given position, velocity, and radius.
+    ///
+    /// The ball is a plain white ball of [`DEFAULT_BALL_MASS`]; assign
+    /// `mass` afterwards for heavier or lighter balls.
     #[wasm_bindgen(constructor)]
-    pub fn new(
-        x: f32,
-        y: f32,
-        vx: f32,
-        vy: f32,
-        radius: f32,
-    ) -> Ball {
-        Ball {
+    #[must_use]
+    pub fn new(x: f32, y: f32, vx: f32, vy: f32, radius: f32) -> Self {
+        Self {
+            id: 0,
+            number: 0,
+            kind: BallKind::Plain,
+            color: identity::WHITE,
             position: Vector2D { x, y },
             velocity: Vector2D { x: vx, y: vy },
             radius,
+            mass: DEFAULT_BALL_MASS,
+            angular_velocity: Vector3D::ZERO,
+        }
+    }
+
+    /// Creates the stationary pool ball numbered `number` at `(x, y)`, with 0
+    /// being the cue ball.
+    #[must_use]
+    pub fn pool(number: u32, x: f32, y: f32) -> Self {
+        Self {
+            number,
+            kind: BallKind::pool(number),
+            color: identity::pool_color(number),
+            ..Self::new(x, y, 0.0, 0.0, POOL_BALL_RADIUS)
+        }
+    }
+
+    /// Creates a stationary snooker ball of `kind` at `(x, y)`.
+    #[must_use]
+    pub fn snooker(kind: BallKind, x: f32, y: f32) -> Self {
+        Self {
+            kind,
+            color: identity::kind_color(kind),
+            mass: SNOOKER_BALL_MASS,
+            ..Self::new(x, y, 0.0, 0.0, SNOOKER_BALL_RADIUS)
+        }
+    }
+
+    /// Creates a stationary carom ball of `kind` at `(x, y)`.
+    #[must_use]
+    pub fn carom(kind: BallKind, x: f32, y: f32) -> Self {
+        Self {
+            kind,
+            color: identity::kind_color(kind),
+            mass: CAROM_BALL_MASS,
+            ..Self::new(x, y, 0.0, 0.0, CAROM_BALL_RADIUS)
         }
     }
 
+    /// Returns `true` for the cue ball.
+    #[must_use]
+    pub fn is_cue(&self) -> bool {
+        self.kind == BallKind::Cue
+    }
+
+    /// Returns the points the ball is worth in snooker, or zero.
+    #[must_use]
+    pub fn value(&self) -> u32 {
+        self.kind.value()
+    }
+
     /// Returns the x coordinate of the ball.
+    #[must_use]
     pub fn x(&self) -> f32 {
         self.position.x
     }
 
     /// Returns the y coordinate of the ball.
+    #[must_use]
     pub fn y(&self) -> f32 {
         self.position.y
     }
 
     /// Returns the radius of the ball.
+    #[must_use]
     pub fn radius(&self) -> f32 {
         self.radius
     }
+
+    /// Returns `true` while the ball is still travelling across the cloth.
+    ///
+    /// A ball held in place by backspin is still moving: friction will set it
+    /// in motion again. Pure sidespin on a stationary ball does not count.
+    #[must_use]
+    pub fn is_moving(&self) -> bool {
+        self.velocity.length_squared() > 0.0 || self.angular_velocity.plane().length_squared() > 0.0
+    }
+
+    /// Sets the angular velocity of the ball, in radians per second.
+    pub fn set_spin(&mut self, wx: f32, wy: f32, wz: f32) {
+        self.angular_velocity = Vector3D::new(wx, wy, wz);
+    }
+
+    /// Returns the topspin of the ball about its direction of travel, in
+    /// radians per second.
+    ///
+    /// Positive values are follow (natural roll has `speed / radius`),
+    /// negative values are draw. A stationary ball reports zero.
+    #[must_use]
+    pub fn topspin(&self) -> f32 {
+        let speed = self.velocity.length();
+        if speed <= 0.0 {
+            return 0.0;
+        }
+        // Natural roll spins about the axis `z x v`.
+        let axis = Vector2D::new(-self.velocity.y, self.velocity.x) / speed;
+        self.angular_velocity.plane().dot(axis)
+    }
+
+    /// Returns the sidespin of the ball in radians per second; positive
+    /// values turn counter-clockwise when viewed from above.
+    #[must_use]
+    pub fn sidespin(&self) -> f32 {
+        self.angular_velocity.z
+    }
+}
+
+impl Ball {
+    /// Returns `true` if the ball's position and motion are finite and its
+    /// radius and mass finite and positive.
+    #[must_use]
+    pub fn is_finite(&self) -> bool {
+        self.position.is_finite()
+            && self.velocity.is_finite()
+            && self.angular_velocity.is_finite()
+            && self.radius.is_finite()
+            && self.radius > 0.0
+            && self.mass.is_finite()
+            && self.mass > 0.0
+    }
 }
 
 /// Creates a new `Ball` via helper function.
 #[wasm_bindgen]
-pub fn new_ball(
-    x: f32,
-    y: f32,
-    vx: f32,
-    vy: f32,
-    radius: f32,
-) -> Ball {
+#[must_use]
+pub fn new_ball(x: f32, y: f32, vx: f32, vy: f32, radius: f32) -> Ball {
     Ball::new(x, y, vx, vy, radius)
 }
 
-/// A rectangular pool table area.
+/// A rectangular pool table area covered in cloth.
 #[wasm_bindgen]
 #[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Table {
     /// The width of the table.
     pub width: f32,
     /// The height of the table.
     pub height: f32,
+    /// Sliding friction coefficient of the cloth, applied while the ball
+    /// skids over the cloth.
+    pub slide_friction: f32,
+    /// Rolling resistance coefficient of the cloth, applied once the ball
+    /// has reached natural roll.
+    pub roll_friction: f32,
+    /// Spinning friction coefficient of the cloth, which slows sidespin.
+    pub spin_friction: f32,
+    /// Shape of the four corner pockets.
+    pub corner_pockets: PocketSpec,
+    /// Shape of the two side pockets, in the middle of the long rails.
+    pub side_pockets: PocketSpec,
+    /// Height of the cushion nose above the cloth.
+    pub cushion_height: f32,
+    /// Restitution of the cushions for a gentle impact.
+    pub cushion_restitution: f32,
+    /// Loss of cushion restitution per unit per second of impact speed.
+    pub cushion_restitution_falloff: f32,
+    /// Coefficient of friction between ball and cushion.
+    pub cushion_friction: f32,
+    /// Distance of the baulk line from the left cushion, or zero if the
+    /// table has none.
+    pub baulk_line: f32,
+    /// Radius of the D, the half-circle on the baulk line the cue ball is
+    /// played from in snooker, or zero if the table has none.
+    pub d_radius: f32,
 }
 
 #[wasm_bindgen]
 impl Table {
-    /// Creates a new `Table` with the given dimensions.
+    /// Creates a new pocketless `Table` with the given dimensions and
+    /// default cloth.
     #[wasm_bindgen(constructor)]
-    pub fn new(width: f32, height: f32) -> Table {
-        Table { width, height }
+    #[must_use]
+    pub fn new(width: f32, height: f32) -> Self {
+        Self {
+            width,
+            height,
+            slide_friction: DEFAULT_SLIDE_FRICTION,
+            roll_friction: DEFAULT_ROLL_FRICTION,
+            spin_friction: DEFAULT_SPIN_FRICTION,
+            corner_pockets: PocketSpec::NONE,
+            side_pockets: PocketSpec::NONE,
+            cushion_height: DEFAULT_CUSHION_HEIGHT,
+            cushion_restitution: DEFAULT_CUSHION_RESTITUTION,
+            cushion_restitution_falloff: DEFAULT_CUSHION_RESTITUTION_FALLOFF,
+            cushion_friction: DEFAULT_CUSHION_FRICTION,
+            baulk_line: 0.0,
+            d_radius: 0.0,
+        }
+    }
+
+    /// Creates a pool `Table` with regulation corner and side pockets.
+    #[must_use]
+    pub fn pool(width: f32, height: f32) -> Self {
+        Self {
+            corner_pockets: PocketSpec::CORNER,
+            side_pockets: PocketSpec::SIDE,
+            ..Self::new(width, height)
+        }
+    }
+
+    /// Creates a full-size 12ft snooker `Table`, 3569 by 1778 mm inside the
+    /// cushions, with snooker pockets, the baulk line and the D.
+    #[must_use]
+    pub fn snooker() -> Self {
+        let length = 3.569 * UNITS_PER_METER;
+        Self {
+            corner_pockets: PocketSpec::SNOOKER_CORNER,
+            side_pockets: PocketSpec::SNOOKER_SIDE,
+            cushion_height: SNOOKER_CUSHION_HEIGHT,
+            baulk_line: length * SNOOKER_BAULK_LINE,
+            d_radius: length * SNOOKER_D_RADIUS,
+            ..Self::new(length, 1.778 * UNITS_PER_METER)
+        }
+    }
+
+    /// Creates a heated 2.84 m match `Table` for carom billiards: no
+    /// pockets, low cushions and fast cloth.
+    #[must_use]
+    pub fn carom() -> Self {
+        Self {
+            slide_friction: HEATED_SLIDE_FRICTION,
+            roll_friction: HEATED_ROLL_FRICTION,
+            cushion_height: CAROM_CUSHION_HEIGHT,
+            ..Self::new(2.84 * UNITS_PER_METER, 1.42 * UNITS_PER_METER)
+        }
+    }
+}
+
+impl Table {
+    /// Returns the distance of the baulk line from the left cushion and the
+    /// radius of the D.
+    ///
+    /// Tables without markings get them scaled from a full-size snooker
+    /// table, so snooker can still be played on them.
+    #[must_use]
+    pub fn baulk(&self) -> (f32, f32) {
+        if self.baulk_line > 0.0 && self.d_radius > 0.0 {
+            (self.baulk_line, self.d_radius)
+        } else {
+            (
+                self.width * SNOOKER_BAULK_LINE,
+                self.width * SNOOKER_D_RADIUS,
+            )
+        }
+    }
+
+    /// Returns `true` if every dimension and coefficient of the table is
+    /// finite and its playing area is not empty.
+    #[must_use]
+    pub fn is_finite(&self) -> bool {
+        [
+            self.slide_friction,
+            self.roll_friction,
+            self.spin_friction,
+            self.cushion_height,
+            self.cushion_restitution,
+            self.cushion_restitution_falloff,
+            self.cushion_friction,
+            self.baulk_line,
+            self.d_radius,
+        ]
+        .iter()
+        .all(|value| value.is_finite())
+            && self.width.is_finite()
+            && self.width > 0.0
+            && self.height.is_finite()
+            && self.height > 0.0
+            && self.corner_pockets.is_finite()
+            && self.side_pockets.is_finite()
+    }
+
+    /// Returns `true` if `position` lies within the D.
+    #[must_use]
+    pub fn in_d(&self, position: Vector2D) -> bool {
+        let (line, radius) = self.baulk();
+        let centre = Vector2D::new(line, self.height * 0.5);
+        position.x <= line && (position - centre).length() <= radius
     }
 }
 
 /// Creates a new `Table` via helper function.
 #[wasm_bindgen]
+#[must_use]
 pub fn new_table(width: f32, height: f32) -> Table {
     Table::new(width, height)
 }
 
 /// The complete game state for the pool simulation.
 #[wasm_bindgen]
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GameState {
     /// The balls currently in play.
     balls: Vec<Ball>,
     /// The table on which the balls move.
     table: Table,
+    /// Balls that have dropped into a pocket, in the order they fell.
+    pocketed: Vec<PocketedBall>,
+    /// The rules of the game being played, if any.
+    rules: Option<Rules>,
+    /// Events recorded since they were last drained, oldest first.
+    events: Vec<Event>,
+    /// Simulation time elapsed, in seconds.
+    time: f32,
+    /// Time remaining, in seconds, until the next cloth friction update.
+    friction_countdown: f32,
 }
 
 #[wasm_bindgen]
 impl GameState {
     /// Returns the number of balls in the game state.
+    #[must_use]
     pub fn balls_len(&self) -> usize {
         self.balls.len()
     }
@@ -131,114 +748,606 @@ impl GameState {
     ///
     /// Panics in Rust if out of bounds; when called from JS via wasm-bindgen
     /// this will surface as a trap, so callers must bounds-check first.
+    #[must_use]
     pub fn ball(&self, index: usize) -> Ball {
         self.balls[index].clone()
     }
 
+    /// Returns the ids of the balls in play, in index order.
+    #[must_use]
+    pub fn ball_ids(&self) -> Vec<u32> {
+        self.balls.iter().map(|ball| ball.id).collect()
+    }
+
+    /// Returns the ball in play with the given id, or `undefined` if it has
+    /// been pocketed or never existed.
+    #[must_use]
+    pub fn ball_by_id(&self, id: u32) -> Option<Ball> {
+        self.find_ball(id).cloned()
+    }
+
+    /// Returns `true` if the ball with the given id is still in play.
+    #[must_use]
+    pub fn is_on_table(&self, id: u32) -> bool {
+        self.find_ball(id).is_some()
+    }
+
+    /// Returns the pocketed ball record for the given id, or `undefined` if
+    /// that ball has not been pocketed.
+    #[must_use]
+    pub fn pocketed_by_id(&self, id: u32) -> Option<PocketedBall> {
+        self.pocketed
+            .iter()
+            .find(|record| record.ball_id() == id)
+            .cloned()
+    }
+
     /// Returns the table width.
+    #[must_use]
     pub fn table_width(&self) -> f32 {
         self.table.width
     }
 
     /// Returns the table height.
+    #[must_use]
     pub fn table_height(&self) -> f32 {
         self.table.height
     }
-}
 
-/// Creates a new `GameState` with a single moving ball on a default-sized table.
-///
-/// The table is 800 by 400 units, and the ball is placed near the center with a
-/// small initial velocity.
-#[wasm_bindgen]
-pub fn new_game_state_single_ball() -> GameState {
-    let table = Table { width: 800.0, height: 400.0 };
-    let ball = Ball {
-        position: Vector2D {
-            x: table.width * 0.5,
-            y: table.height * 0.5,
-        },
-        velocity: Vector2D { x: 120.0, y: 60.0 },
-        radius: 10.0,
-    };
+    /// Returns the number of balls that have been pocketed.
+    #[must_use]
+    pub fn pocketed_len(&self) -> usize {
+        self.pocketed.len()
+    }
 
-    GameState {
-        balls: vec![ball],
-        table,
+    /// Returns the pocketed ball record at the given index, in the order the
+    /// balls dropped.
+    ///
+    /// Panics in Rust if out of bounds; when called from JS via wasm-bindgen
+    /// this will surface as a trap, so callers must bounds-check first.
+    #[must_use]
+    pub fn pocketed_ball(&self, index: usize) -> PocketedBall {
+        self.pocketed[index].clone()
     }
-}
 
-/// Advances the simulation forward by a time step `dt` (in seconds).
-///
-/// This updates all ball positions according to their velocities and applies
-/// simple wall-collision response against the table bounds. When a ball hits a
-/// wall (considering its radius), its corresponding velocity component is
-/// inverted to create a bounce effect.
-#[wasm_bindgen]
-pub fn tick(state: &mut GameState, dt: f32) {
-    if dt <= 0.0 {
-        return;
+    /// Returns the simulation time elapsed, in seconds.
+    #[must_use]
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    /// Removes and returns every event recorded since the last call, oldest
+    /// first.
+    ///
+    /// Events accumulate until drained, so callers that care about them
+    /// should drain after every [`tick`].
+    pub fn drain_events(&mut self) -> Vec<Event> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Racks a game of eight-ball on `table`, with the cue ball on the head
+    /// spot, and plays it under [`EightBall`] rules.
+    ///
+    /// Ball ids are the ball numbers, with the cue ball as 0. Open positions
+    /// in the rack are shuffled by `seed`, and each ball may sit up to `gap`
+    /// units away from its neighbours.
+    #[must_use]
+    pub fn eight_ball(table: &Table, seed: u32, gap: f32) -> Self {
+        let balls = rack::eight_ball(*table, &mut Rng::new(seed.into()), gap);
+        Self::new(*table, balls).with_rules(Rules::EightBall(EightBall::new()))
+    }
+
+    /// Racks a game of nine-ball on `table` and plays it under
+    /// [`Rotation`] rules; see [`GameState::eight_ball`].
+    #[must_use]
+    pub fn nine_ball(table: &Table, seed: u32, gap: f32) -> Self {
+        let balls = rack::nine_ball(*table, &mut Rng::new(seed.into()), gap);
+        Self::new(*table, balls).with_rules(Rules::Rotation(Rotation::nine_ball()))
+    }
+
+    /// Racks a game of ten-ball on `table` and plays it under call-shot
+    /// [`Rotation`] rules; see [`GameState::eight_ball`].
+    #[must_use]
+    pub fn ten_ball(table: &Table, seed: u32, gap: f32) -> Self {
+        let balls = rack::ten_ball(*table, &mut Rng::new(seed.into()), gap);
+        Self::new(*table, balls).with_rules(Rules::Rotation(Rotation::ten_ball()))
+    }
+
+    /// Racks a game of straight pool on `table` and plays it to `target`
+    /// points under [`StraightPool`] rules; see [`GameState::eight_ball`].
+    #[must_use]
+    pub fn straight_pool(table: &Table, seed: u32, gap: f32, target: u32) -> Self {
+        let balls = rack::straight_pool(*table, &mut Rng::new(seed.into()), gap);
+        Self::new(*table, balls).with_rules(Rules::StraightPool(StraightPool::new(target)))
+    }
+
+    /// Sets up a frame of snooker on `table`, with the cue ball in the D,
+    /// and plays it under [`Snooker`] rules.
+    ///
+    /// The reds have ids `1..=15` and the colours, from yellow to black,
+    /// `16..=21`. `seed` and `gap` loosen the triangle of reds as for
+    /// [`GameState::eight_ball`].
+    #[must_use]
+    pub fn snooker(table: &Table, seed: u32, gap: f32) -> Self {
+        let balls = rack::snooker(*table, &mut Rng::new(seed.into()), gap);
+        Self::new(*table, balls).with_rules(Rules::Snooker(Snooker::new()))
+    }
+
+    /// Sets up the carom opening position on `table` and plays `game` to
+    /// `target` points under [`Carom`] rules.
+    ///
+    /// The white, yellow and red have ids 0, 1 and 2; player 0 breaks with
+    /// the white.
+    #[must_use]
+    pub fn carom(table: &Table, game: CaromGame, target: u32) -> Self {
+        Self::new(*table, rack::carom(*table)).with_rules(Rules::Carom(Carom::new(game, target)))
+    }
+
+    /// Returns the player to shoot, or `undefined` when no rules are set.
+    #[must_use]
+    pub fn current_player(&self) -> Option<u32> {
+        self.rules.as_ref().map(Rules::current_player)
+    }
+
+    /// Returns the winner once the game is over, or `undefined`.
+    #[must_use]
+    pub fn winner(&self) -> Option<u32> {
+        self.rules.as_ref().and_then(Rules::winner)
+    }
+
+    /// Returns the foul committed on the last shot, or `undefined`.
+    #[must_use]
+    pub fn last_foul(&self) -> Option<Foul> {
+        self.rules.as_ref().and_then(Rules::last_foul)
+    }
+
+    /// Returns where the cue ball may be placed before the next shot.
+    #[must_use]
+    pub fn ball_in_hand(&self) -> BallInHand {
+        self.rules
+            .as_ref()
+            .map_or(BallInHand::No, Rules::ball_in_hand)
+    }
+
+    /// Returns the ids of the balls the player to shoot may hit first.
+    /// Without rules, that is every ball but the cue ball.
+    #[must_use]
+    pub fn targets(&self) -> Vec<u32> {
+        if let Some(rules) = &self.rules {
+            return rules.targets(self);
+        }
+        let cue = self.cue_ball_id();
+        self.balls
+            .iter()
+            .filter(|ball| Some(ball.id) != cue)
+            .map(|ball| ball.id)
+            .collect()
+    }
+
+    /// Returns the eight-ball group of `player`, or `undefined` while the
+    /// table is open or in other games.
+    #[must_use]
+    pub fn group(&self, player: u32) -> Option<Group> {
+        match &self.rules {
+            Some(Rules::EightBall(game)) => game.group(player),
+            _ => None,
+        }
+    }
+
+    /// Calls `pocket` for the coming shot. Returns `false` if the game being
+    /// played has no called shots.
+    pub fn call_pocket(&mut self, pocket: Pocket) -> bool {
+        match &mut self.rules {
+            Some(Rules::EightBall(game)) => {
+                game.call_pocket(pocket);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Calls ball `ball` into `pocket` for the coming shot. Returns `false`
+    /// if the game being played has no called shots.
+    pub fn call_shot(&mut self, ball: u32, pocket: Pocket) -> bool {
+        match &mut self.rules {
+            Some(Rules::Rotation(game)) => {
+                game.call_shot(ball, pocket);
+                true
+            }
+            Some(Rules::StraightPool(game)) => {
+                game.call_shot(ball, pocket);
+                true
+            }
+            _ => false,
+        }
     }
 
-    let width = state.table.width;
-    let height = state.table.height;
+    /// Declares the coming shot a push-out. Returns `false` unless the game
+    /// allows one now.
+    pub fn push_out(&mut self) -> bool {
+        match &mut self.rules {
+            Some(Rules::Rotation(game)) => game.push_out(),
+            _ => false,
+        }
+    }
+
+    /// After an opponent's push-out, hands the table back to them instead
+    /// of playing on. Returns `false` if the last shot was not a push-out.
+    pub fn pass(&mut self) -> bool {
+        match &mut self.rules {
+            Some(Rules::Rotation(game)) => game.pass(),
+            _ => false,
+        }
+    }
+
+    /// Returns how many fouls in a row `player` has committed, in games
+    /// where that matters.
+    #[must_use]
+    pub fn consecutive_fouls(&self, player: u32) -> u32 {
+        match &self.rules {
+            Some(Rules::Rotation(game)) => game.consecutive_fouls(player),
+            Some(Rules::StraightPool(game)) => game.consecutive_fouls(player),
+            _ => 0,
+        }
+    }
 
-    for ball in &mut state.balls {
-        // Integrate position.
-        ball.position.x += ball.velocity.x * dt;
-        ball.position.y += ball.velocity.y * dt;
+    /// Returns the points `player` has scored, in games that keep score.
+    /// Straight pool penalties can make it negative.
+    #[must_use]
+    pub fn score(&self, player: u32) -> i32 {
+        let points = match &self.rules {
+            Some(Rules::Snooker(game)) => game.score(player),
+            Some(Rules::Carom(game)) => game.score(player),
+            Some(Rules::StraightPool(game)) => return game.score(player),
+            _ => 0,
+        };
+        i32::try_from(points).unwrap_or(i32::MAX)
+    }
+
+    /// Returns the longest run `player` has made in one inning of straight
+    /// pool.
+    #[must_use]
+    pub fn high_run(&self, player: u32) -> u32 {
+        match &self.rules {
+            Some(Rules::StraightPool(game)) => game.high_run(player),
+            _ => 0,
+        }
+    }
+
+    /// Returns the points the last foul gave away in a frame of snooker.
+    #[must_use]
+    pub fn penalty(&self) -> u32 {
+        match &self.rules {
+            Some(Rules::Snooker(game)) => game.penalty(),
+            _ => 0,
+        }
+    }
 
-        // Left wall.
-        if ball.position.x - ball.radius < 0.0 {
-            ball.position.x = ball.radius;
-            ball.velocity.x = -ball.velocity.x;
+    /// Returns the kind of ball that is on in a frame of snooker, or
+    /// `undefined` while any colour may be played and in other games.
+    #[must_use]
+    pub fn ball_on(&self) -> Option<BallKind> {
+        match &self.rules {
+            Some(Rules::Snooker(game)) => game.ball_on(),
+            _ => None,
         }
+    }
+
+    /// Returns `true` if the coming snooker shot is a free ball.
+    #[must_use]
+    pub fn free_ball(&self) -> bool {
+        matches!(&self.rules, Some(Rules::Snooker(game)) if game.free_ball())
+    }
 
-        // Right wall.
-        if ball.position.x + ball.radius > width {
-            ball.position.x = width - ball.radius;
-            ball.velocity.x = -ball.velocity.x;
+    /// Returns `true` if the last snooker shot was called a foul and a miss.
+    #[must_use]
+    pub fn missed(&self) -> bool {
+        matches!(&self.rules, Some(Rules::Snooker(game)) if game.missed())
+    }
+
+    /// Nominates ball `ball` for the coming snooker shot, as the colour to
+    /// pot after a red or as a free ball. Returns `false` in other games.
+    pub fn nominate(&mut self, ball: u32) -> bool {
+        match &mut self.rules {
+            Some(Rules::Snooker(game)) => {
+                game.nominate(ball);
+                true
+            }
+            _ => false,
         }
+    }
 
-        // Top wall.
-        if ball.position.y - ball.radius < 0.0 {
-            ball.position.y = ball.radius;
-            ball.velocity.y = -ball.velocity.y;
+    /// After a foul and a miss, puts the balls back where they were and has
+    /// the offender play again. Returns `false` if the last shot was not a
+    /// miss.
+    pub fn replay_miss(&mut self) -> bool {
+        match self.rules.take() {
+            Some(Rules::Snooker(mut game)) => {
+                let replayed = game.replay(self);
+                self.rules = Some(Rules::Snooker(game));
+                replayed
+            }
+            rules => {
+                self.rules = rules;
+                false
+            }
         }
+    }
 
-        // Bottom wall.
-        if ball.position.y + ball.radius > height {
-            ball.position.y = height - ball.radius;
-            ball.velocity.y = -ball.velocity.y;
+    /// Places the cue ball at `(x, y)` while the player has ball in hand,
+    /// returning it to the table if it was pocketed.
+    ///
+    /// Returns `false`, leaving the table as it was, if the player does not
+    /// have ball in hand, the spot is outside the area they may use, or it
+    /// overlaps another ball.
+    pub fn place_cue_ball(&mut self, x: f32, y: f32) -> bool {
+        if !self.can_place_cue_ball(x, y) {
+            return false;
         }
+        let Some(cue) = self.cue_ball_id() else {
+            return false;
+        };
+        rules::place_ball(self, cue, Vector2D::new(x, y))
+    }
+
+    /// Returns `true` if [`GameState::place_cue_ball`] would accept `(x, y)`.
+    #[must_use]
+    pub fn can_place_cue_ball(&self, x: f32, y: f32) -> bool {
+        let position = Vector2D::new(x, y);
+        let head_string = self.table.width * 0.25;
+        let allowed = match self.ball_in_hand() {
+            BallInHand::No => false,
+            BallInHand::Anywhere => true,
+            BallInHand::Kitchen => x <= head_string,
+            BallInHand::InD => self.table.in_d(position),
+        };
+        let Some(cue) = self.cue_ball_id().and_then(|id| rules::any_ball(self, id)) else {
+            return false;
+        };
+        allowed && rules::is_clear(self, cue.id, position, cue.radius)
+    }
+
+    /// Strikes the cue ball (the ball of kind [`BallKind::Cue`], or in
+    /// carom the ball of the player to shoot) with `stroke`.
+    ///
+    /// The cue ball's velocity and spin are replaced by those imparted by the
+    /// cue, including squirt from side english. Under rules, this starts the
+    /// shot that is judged once the table comes to rest. Returns `false` if
+    /// the stroke is not [valid](CueStroke::is_valid), the cue ball is not on
+    /// the table or the rules allow no shot now.
+    pub fn strike(&mut self, stroke: &CueStroke) -> bool {
+        if !stroke.is_valid() {
+            return false;
+        }
+        let Some(cue) = self.cue_ball_id().filter(|&id| self.is_on_table(id)) else {
+            return false;
+        };
+        if !rules::begin_shot(self) {
+            return false;
+        }
+        let Some(cue_ball) = self.balls.iter_mut().find(|ball| ball.id == cue) else {
+            return false;
+        };
+        let (velocity, angular_velocity) = cue::impact(cue_ball, stroke);
+        cue_ball.velocity = velocity;
+        cue_ball.angular_velocity = angular_velocity;
+        true
     }
 }
 
+impl GameState {
+    /// Creates a new `GameState` from a table and a set of balls.
+    ///
+    /// Balls are given ids by their position in `balls`. If none of them is
+    /// a cue ball, the first one becomes the cue ball.
+    #[must_use]
+    pub fn new(table: Table, mut balls: Vec<Ball>) -> Self {
+        for (id, ball) in (0..).zip(&mut balls) {
+            ball.id = id;
+        }
+        if !balls.iter().any(Ball::is_cue) {
+            if let Some(first) = balls.first_mut() {
+                first.kind = BallKind::Cue;
+            }
+        }
+        Self {
+            balls,
+            table,
+            pocketed: Vec::new(),
+            rules: None,
+            events: Vec::new(),
+            time: 0.0,
+            friction_countdown: 0.0,
+        }
+    }
+
+    /// Plays the game under `rules` from now on.
+    #[must_use]
+    pub fn with_rules(mut self, rules: Rules) -> Self {
+        self.rules = Some(rules);
+        self
+    }
+
+    /// Returns the rules of the game being played, if any.
+    #[must_use]
+    pub const fn rules(&self) -> Option<&Rules> {
+        self.rules.as_ref()
+    }
+
+    /// Returns the id of the cue ball, whether on the table or pocketed.
+    ///
+    /// In games where each player has their own cue ball, this is the ball
+    /// of the player to shoot.
+    #[must_use]
+    pub fn cue_ball_id(&self) -> Option<u32> {
+        if let Some(cue) = self.rules.as_ref().and_then(Rules::cue_ball) {
+            return Some(cue);
+        }
+        self.balls
+            .iter()
+            .find(|ball| ball.is_cue())
+            .map(|ball| ball.id)
+            .or_else(|| {
+                self.pocketed
+                    .iter()
+                    .find(|record| record.ball().is_cue())
+                    .map(PocketedBall::ball_id)
+            })
+    }
+
+    /// Returns the balls currently in play.
+    #[must_use]
+    pub fn balls(&self) -> &[Ball] {
+        &self.balls
+    }
+
+    /// Returns the ball in play with the given id.
+    #[must_use]
+    pub fn find_ball(&self, id: u32) -> Option<&Ball> {
+        self.balls.iter().find(|ball| ball.id == id)
+    }
+
+    /// Returns the table on which the balls move.
+    #[must_use]
+    pub const fn table(&self) -> Table {
+        self.table
+    }
+
+    /// Returns the balls that have been pocketed, in the order they fell.
+    #[must_use]
+    pub fn pocketed(&self) -> &[PocketedBall] {
+        &self.pocketed
+    }
+
+    /// Returns the events recorded since they were last drained, oldest
+    /// first.
+    #[must_use]
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    /// Returns `true` if this state is one play could reach: every ball is
+    /// [finite](Ball::is_finite) and has its own id, the
+    /// [table](Table::is_finite) and the clock are finite, and the rules
+    /// refer only to players 0 and 1 and to balls that exist.
+    ///
+    /// Saves and snapshots can be edited, so states read from them are
+    /// checked with this before they are played on.
+    #[must_use]
+    pub fn is_consistent(&self) -> bool {
+        let mut ids: Vec<u32> = self
+            .balls
+            .iter()
+            .map(|ball| ball.id)
+            .chain(self.pocketed.iter().map(PocketedBall::ball_id))
+            .collect();
+        ids.sort_unstable();
+        ids.dedup();
+        ids.len() == self.balls.len() + self.pocketed.len()
+            && self.balls.iter().all(Ball::is_finite)
+            && self.pocketed.iter().all(|record| record.ball().is_finite())
+            && self.table.is_finite()
+            && self.time.is_finite()
+            && self.friction_countdown.is_finite()
+            && self
+                .rules
+                .as_ref()
+                .is_none_or(|rules| rules.is_consistent(self))
+    }
+}
+
+/// Creates a new `GameState` with a single moving ball on a default-sized table.
+///
+/// The table is an 800 by 400 unit pool table, and the ball is placed near the
+/// center with a small initial velocity.
+#[wasm_bindgen]
+#[must_use]
+pub fn new_game_state_single_ball() -> GameState {
+    let table = Table::pool(800.0, 400.0);
+    let ball = Ball::new(table.width * 0.5, table.height * 0.5, 120.0, 60.0, 10.0);
+
+    GameState::new(table, vec![ball])
+}
+
+/// Advances the simulation forward by a time step `dt` (in seconds).
+///
+/// A `dt` that is not positive and finite is ignored, and a single call
+/// simulates at most ten seconds of play.
+///
+/// Balls move along their velocities and every ball-to-ball and cushion
+/// contact within the step is found by its exact time of impact and resolved
+/// in time order, so fast shots cannot tunnel through other balls and the
+/// result does not depend on how the caller splits time into frames. Ball
+/// contacts use an impulse weighted by mass and [`BALL_RESTITUTION`]. Balls
+/// rebound from cushions with a restitution that falls off with impact speed,
+/// and friction against the cushion nose lets spin change the rebound angle.
+///
+/// Cloth friction is applied on a fixed internal schedule: balls slide until
+/// they reach natural roll, then roll with the table's rolling resistance
+/// until they drop below [`REST_SPEED`] and stop.
+///
+/// Cushions end in angled jaws at pocket mouths. A ball that crosses a
+/// pocket's shelf drops, is removed from play and is recorded with its pocket
+/// and the time it fell.
+///
+/// Contacts, pocketed balls and balls coming to rest are recorded as events
+/// on `state`; see [`GameState::drain_events`]. If the game has rules, they
+/// follow those events and judge each shot once the table comes to rest.
+#[wasm_bindgen]
+pub fn tick(state: &mut GameState, dt: f32) {
+    if !(dt.is_finite() && dt > 0.0) {
+        return;
+    }
+
+    let first_new = state.events.len();
+    physics::advance(state, dt);
+    rules::observe(state, first_new);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// A table with no cloth friction, so collisions can be checked in isolation.
+    fn frictionless_table(width: f32, height: f32) -> Table {
+        Table {
+            slide_friction: 0.0,
+            roll_friction: 0.0,
+            spin_friction: 0.0,
+            ..Table::new(width, height)
+        }
+    }
+
     #[test]
     fn tick_moves_ball_when_dt_positive() {
         let mut state = new_game_state_single_ball();
         let initial_x = state.balls[0].position.x;
         let initial_y = state.balls[0].position.y;
         tick(&mut state, 0.5);
-        assert_ne!(state.balls[0].position.x, initial_x);
-        assert_ne!(state.balls[0].position.y, initial_y);
+        assert!((state.balls[0].position.x - initial_x).abs() > f32::EPSILON);
+        assert!((state.balls[0].position.y - initial_y).abs() > f32::EPSILON);
+    }
+
+    #[test]
+    fn tick_ignores_non_finite_dt_and_caps_huge_ones() {
+        let mut state = new_game_state_single_ball();
+        let initial = state.balls[0].position;
+
+        tick(&mut state, f32::NAN);
+        tick(&mut state, f32::INFINITY);
+
+        assert!((state.balls[0].position - initial).length() < f32::EPSILON);
+        assert!(state.time().abs() < f32::EPSILON);
+
+        tick(&mut state, f32::MAX);
+
+        assert!(state.time() <= 10.0 + 1e-3);
     }
 
     #[test]
     fn wall_bounce_inverts_velocity_x() {
-        let table = Table { width: 100.0, height: 100.0 };
-        let mut state = GameState {
-            balls: vec![Ball {
-                position: Vector2D { x: 95.0, y: 50.0 },
-                velocity: Vector2D { x: 50.0, y: 0.0 },
-                radius: 10.0,
-            }],
-            table,
-        };
+        let table = Table::new(100.0, 100.0);
+        let mut state = GameState::new(table, vec![Ball::new(95.0, 50.0, 50.0, 0.0, 10.0)]);
 
         tick(&mut state, 0.5);
 
@@ -249,15 +1358,8 @@ mod tests {
 
     #[test]
     fn wall_bounce_inverts_velocity_y() {
-        let table = Table { width: 100.0, height: 100.0 };
-        let mut state = GameState {
-            balls: vec![Ball {
-                position: Vector2D { x: 50.0, y: 95.0 },
-                velocity: Vector2D { x: 0.0, y: 50.0 },
-                radius: 10.0,
-            }],
-            table,
-        };
+        let table = Table::new(100.0, 100.0);
+        let mut state = GameState::new(table, vec![Ball::new(50.0, 95.0, 0.0, 50.0, 10.0)]);
 
         tick(&mut state, 0.5);
 
@@ -265,4 +1367,449 @@ mod tests {
         assert!(ball.position.y <= table.height - ball.radius + f32::EPSILON);
         assert!(ball.velocity.y < 0.0);
     }
-}
\ No newline at end of file
+
+    fn momentum(balls: &[Ball]) -> Vector2D {
+        balls
+            .iter()
+            .fold(Vector2D::ZERO, |acc, b| acc + b.velocity * b.mass)
+    }
+
+    #[test]
+    fn head_on_collision_transfers_velocity() {
+        let table = frictionless_table(800.0, 400.0);
+        let mut state = GameState::new(
+            table,
+            vec![
+                Ball::new(100.0, 200.0, 200.0, 0.0, 10.0),
+                Ball::new(125.0, 200.0, 0.0, 0.0, 10.0),
+            ],
+        );
+
+        tick(&mut state, 0.05);
+
+        let (cue, object) = (&state.balls[0], &state.balls[1]);
+        assert!(cue.velocity.x.abs() < 10.0 + f32::EPSILON);
+        assert!(object.velocity.x > 180.0);
+        assert!(object.velocity.y.abs() < f32::EPSILON);
+        assert!((object.position - cue.position).length() >= 20.0 - 1e-3);
+    }
+
+    #[test]
+    fn glancing_collision_splits_at_right_angles() {
+        let table = frictionless_table(800.0, 400.0);
+        // Contact normal at 45 degrees: half-ball overlap along the diagonal.
+        let offset = 20.0 / 2.0_f32.sqrt();
+        let mut state = GameState::new(
+            table,
+            vec![
+                Ball::new(300.0 - offset, 200.0, 100.0, 0.0, 10.0),
+                Ball::new(300.0, 200.0 + offset - 0.5, 0.0, 0.0, 10.0),
+            ],
+        );
+        let before = momentum(&state.balls);
+
+        tick(&mut state, 0.01);
+
+        let (cue, object) = (&state.balls[0], &state.balls[1]);
+        assert!(cue.velocity.y < 0.0);
+        assert!(object.velocity.x > 0.0 && object.velocity.y > 0.0);
+        // With near-elastic restitution the outgoing paths are close to 90 degrees.
+        let cos_angle =
+            cue.velocity.dot(object.velocity) / (cue.velocity.length() * object.velocity.length());
+        assert!(cos_angle.abs() < 0.1);
+        let after = momentum(&state.balls);
+        assert!((after - before).length() < 1e-4);
+    }
+
+    #[test]
+    fn simultaneous_contacts_propagate_through_line() {
+        let table = frictionless_table(800.0, 400.0);
+        let mut state = GameState::new(
+            table,
+            vec![
+                Ball::new(99.0, 200.0, 300.0, 0.0, 10.0),
+                Ball::new(120.0, 200.0, 0.0, 0.0, 10.0),
+                Ball::new(140.0, 200.0, 0.0, 0.0, 10.0),
+                Ball::new(160.0, 200.0, 0.0, 0.0, 10.0),
+            ],
+        );
+        let before = momentum(&state.balls);
+
+        tick(&mut state, 0.01);
+
+        let last = &state.balls[3];
+        assert!(last.velocity.x > 200.0);
+        for ball in &state.balls[..3] {
+            assert!(ball.velocity.x < last.velocity.x);
+        }
+        let after = momentum(&state.balls);
+        assert!((after - before).length() < 1e-3);
+        for (i, a) in state.balls.iter().enumerate() {
+            for b in &state.balls[i + 1..] {
+                assert!((b.position - a.position).length() >= 20.0 - 1e-2);
+            }
+        }
+    }
+
+    #[test]
+    fn fast_ball_does_not_tunnel_through_object_ball() {
+        let table = frictionless_table(800.0, 400.0);
+        let mut state = GameState::new(
+            table,
+            vec![
+                Ball::new(100.0, 200.0, 4000.0, 0.0, 10.0),
+                Ball::new(160.0, 200.0, 0.0, 0.0, 10.0),
+            ],
+        );
+
+        // A single 50 ms step moves the cue ball 200 units, far past the object ball.
+        tick(&mut state, 0.05);
+
+        assert!(state.balls[1].velocity.x > 3000.0);
+        assert!(state.balls[0].position.x < state.balls[1].position.x);
+    }
+
+    #[test]
+    fn result_is_independent_of_frame_rate() {
+        let table = frictionless_table(800.0, 400.0);
+        let initial = GameState::new(
+            table,
+            vec![
+                Ball::new(100.0, 180.0, 900.0, 150.0, 10.0),
+                Ball::new(300.0, 210.0, 0.0, 0.0, 10.0),
+                Ball::new(500.0, 250.0, -200.0, 0.0, 10.0),
+                Ball::new(700.0, 100.0, 0.0, 300.0, 10.0),
+            ],
+        );
+        let mut coarse = GameState::new(table, initial.balls.clone());
+        let mut fine = GameState::new(table, initial.balls);
+
+        for _ in 0..20 {
+            tick(&mut coarse, 0.05);
+        }
+        for _ in 0..1000 {
+            tick(&mut fine, 0.001);
+        }
+
+        for (a, b) in coarse.balls.iter().zip(&fine.balls) {
+            assert!((a.position - b.position).length() < 0.05);
+            assert!((a.velocity - b.velocity).length() < 0.05);
+        }
+    }
+
+    #[test]
+    fn friction_brings_ball_to_rest() {
+        let table = Table::new(800.0, 400.0);
+        let mut state = GameState::new(table, vec![Ball::new(100.0, 200.0, 300.0, 0.0, 10.0)]);
+
+        let mut previous_speed = state.balls[0].velocity.length();
+        for _ in 0..200 {
+            tick(&mut state, 0.05);
+            let speed = state.balls[0].velocity.length();
+            assert!(speed <= previous_speed + 1e-3);
+            previous_speed = speed;
+        }
+
+        let ball = &state.balls[0];
+        assert!(ball.velocity.length() < f32::EPSILON);
+        assert!(ball.position.x > 100.0);
+    }
+
+    #[test]
+    fn friction_is_independent_of_frame_rate() {
+        let table = Table::new(800.0, 400.0);
+        let ball = Ball::new(100.0, 200.0, 400.0, 50.0, 10.0);
+        let mut coarse = GameState::new(table, vec![ball.clone()]);
+        let mut fine = GameState::new(table, vec![ball]);
+
+        for _ in 0..30 {
+            tick(&mut coarse, 0.05);
+        }
+        for _ in 0..1500 {
+            tick(&mut fine, 0.001);
+        }
+
+        let (a, b) = (&coarse.balls[0], &fine.balls[0]);
+        assert!((a.position - b.position).length() < 0.05);
+        assert!((a.velocity - b.velocity).length() < 0.05);
+    }
+
+    #[test]
+    fn stun_draw_and_follow_shots() {
+        let table = Table::new(800.0, 400.0);
+        let shot = |spin: f32| {
+            let mut cue = Ball::new(300.0, 200.0, 400.0, 0.0, 10.0);
+            cue.set_spin(0.0, spin, 0.0);
+            let object = Ball::new(340.0, 200.0, 0.0, 0.0, 10.0);
+            let mut state = GameState::new(table, vec![cue, object]);
+            for _ in 0..60 {
+                tick(&mut state, 1.0 / 60.0);
+            }
+            state.balls[0].position.x
+        };
+
+        // Spin at the moment of contact decides where the cue ball ends up.
+        let stun = shot(0.0);
+        let draw = shot(-200.0);
+        let follow = shot(200.0);
+        assert!(draw < 300.0);
+        assert!(follow > stun + 50.0);
+        assert!((stun - 320.0).abs() < 30.0);
+    }
+
+    #[test]
+    fn a_found_contact_is_resolved_once() {
+        // After this break, rounding leaves the balls a sliver apart at some
+        // of the next shot's contacts.
+        let mut state = GameState::eight_ball(&Table::pool(800.0, 400.0), 7, 0.0);
+        assert!(state.strike(&CueStroke::new(0.0, 900.0, 0.0, 0.0, 0.0)));
+        while state.balls.iter().any(Ball::is_moving) {
+            tick(&mut state, 1.0 / 60.0);
+        }
+        state.drain_events();
+
+        assert!(state.strike(&CueStroke::new(2.0, 500.0, 0.0, 0.2, 0.0)));
+        while state.balls.iter().any(Ball::is_moving) {
+            tick(&mut state, 1.0 / 60.0);
+        }
+
+        let contacts = state
+            .events()
+            .iter()
+            .filter(|event| event.kind == EventKind::BallContact)
+            .count();
+        assert!(contacts > 0 && contacts < 50);
+    }
+
+    #[test]
+    fn strike_sets_cue_ball_in_motion() {
+        let table = Table::new(800.0, 400.0);
+        let mut state = GameState::new(
+            table,
+            vec![
+                Ball::new(200.0, 200.0, 0.0, 0.0, 10.0),
+                Ball::new(600.0, 200.0, 0.0, 0.0, 10.0),
+            ],
+        );
+
+        assert!(state.strike(&CueStroke::new(0.0, 400.0, 0.0, 0.3, 0.0)));
+
+        let cue_ball = &state.balls[0];
+        assert!(cue_ball.velocity.x > 400.0);
+        assert!(cue_ball.topspin() > 0.0);
+        assert!(!state.balls[1].is_moving());
+        assert!(!GameState::new(table, Vec::new()).strike(&CueStroke::new(0.0, 1.0, 0.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn strike_refuses_invalid_strokes() {
+        let mut state = GameState::eight_ball(&Table::pool(800.0, 400.0), 1, 0.0);
+        let invalid = [
+            CueStroke::new(0.0, 0.0, 0.0, 0.0, 0.0),
+            CueStroke::new(0.0, -400.0, 0.0, 0.0, 0.0),
+            CueStroke::new(0.0, f32::NAN, 0.0, 0.0, 0.0),
+            CueStroke::new(0.0, f32::INFINITY, 0.0, 0.0, 0.0),
+            CueStroke::new(f32::NAN, 400.0, 0.0, 0.0, 0.0),
+            CueStroke::new(0.0, 400.0, f32::INFINITY, 0.0, 0.0),
+            CueStroke::new(0.0, 400.0, 0.0, f32::NAN, 0.0),
+            CueStroke::new(0.0, 400.0, 0.0, 0.0, f32::NEG_INFINITY),
+        ];
+
+        for stroke in &invalid {
+            assert!(!stroke.is_valid());
+            assert!(!state.strike(stroke));
+        }
+        assert!(!state.balls.iter().any(Ball::is_moving));
+        assert!(state.strike(&CueStroke::new(0.0, 400.0, 0.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn ball_rolled_into_corner_is_pocketed() {
+        let table = Table::pool(800.0, 400.0);
+        let mut state = GameState::new(
+            table,
+            vec![
+                Ball::new(400.0, 200.0, 0.0, 0.0, 10.0),
+                Ball::new(100.0, 100.0, -400.0, -400.0, 10.0),
+            ],
+        );
+
+        for _ in 0..60 {
+            tick(&mut state, 1.0 / 60.0);
+        }
+
+        assert_eq!(state.balls_len(), 1);
+        assert_eq!(state.balls[0].id, CUE_BALL_ID);
+        let record = &state.pocketed()[0];
+        assert_eq!(record.ball_id(), 1);
+        assert_eq!(record.pocket, Pocket::TopLeft);
+        assert!(record.time > 0.0 && record.time < state.time());
+    }
+
+    #[test]
+    fn ball_outside_mouth_rebounds_from_rail() {
+        let table = Table::pool(800.0, 400.0);
+        let mut state = GameState::new(table, vec![Ball::new(250.0, 100.0, 0.0, -300.0, 10.0)]);
+
+        for _ in 0..30 {
+            tick(&mut state, 1.0 / 60.0);
+        }
+
+        assert_eq!(state.balls_len(), 1);
+        assert!(state.pocketed().is_empty());
+        assert!(state.balls[0].velocity.y > 0.0);
+    }
+
+    #[test]
+    fn pocketing_time_is_independent_of_frame_rate() {
+        let table = Table::pool(800.0, 400.0);
+        let ball = Ball::new(400.0, 300.0, 0.0, 500.0, 10.0);
+        let mut coarse = GameState::new(table, vec![ball.clone()]);
+        let mut fine = GameState::new(table, vec![ball]);
+
+        for _ in 0..10 {
+            tick(&mut coarse, 0.05);
+        }
+        for _ in 0..500 {
+            tick(&mut fine, 0.001);
+        }
+
+        let (a, b) = (&coarse.pocketed()[0], &fine.pocketed()[0]);
+        assert_eq!(a.pocket, Pocket::BottomSide);
+        assert_eq!(a.pocket, b.pocket);
+        assert!((a.time - b.time).abs() < 1e-4);
+    }
+
+    #[test]
+    fn events_report_contacts_in_order_and_final_stop() {
+        let table = Table::pool(800.0, 400.0);
+        let mut state = GameState::new(
+            table,
+            vec![
+                Ball::new(200.0, 200.0, 400.0, 0.0, 10.0),
+                Ball::new(400.0, 200.0, 0.0, 0.0, 10.0),
+            ],
+        );
+
+        let mut events = Vec::new();
+        for _ in 0..600 {
+            tick(&mut state, 1.0 / 60.0);
+            events.extend(state.drain_events());
+        }
+
+        assert!(state.events().is_empty());
+        let first = events[0];
+        assert_eq!(first.kind, EventKind::BallContact);
+        assert_eq!((first.ball(), first.other_ball()), (Some(0), Some(1)));
+        let speed = first.speed().unwrap();
+        // Cloth friction has taken the cue ball down to natural roll.
+        assert!(speed > 250.0 && speed < 400.0);
+
+        // The object ball runs on into the right rail.
+        assert!(events.iter().any(|e| e.kind == EventKind::CushionContact
+            && e.ball() == Some(1)
+            && e.rail() == Some(Rail::Right)));
+
+        let stopped = |id| {
+            events
+                .iter()
+                .any(|e| e.kind == EventKind::BallStopped && e.ball() == Some(id))
+        };
+        assert!(stopped(0) && stopped(1));
+        let last = events[events.len() - 1];
+        assert_eq!(last.kind, EventKind::AllStopped);
+        assert!(events.windows(2).all(|pair| pair[0].time <= pair[1].time));
+    }
+
+    #[test]
+    fn pocketing_the_last_moving_ball_stops_the_table() {
+        let table = Table::pool(800.0, 400.0);
+        let mut state = GameState::new(
+            table,
+            vec![
+                Ball::new(400.0, 200.0, 0.0, 0.0, 10.0),
+                Ball::new(400.0, 300.0, 0.0, 500.0, 10.0),
+            ],
+        );
+
+        for _ in 0..60 {
+            tick(&mut state, 1.0 / 60.0);
+        }
+
+        let events = state.drain_events();
+        let kinds: Vec<_> = events.iter().map(|e| e.kind).collect();
+        assert_eq!(kinds, [EventKind::Pocketed, EventKind::AllStopped]);
+        assert_eq!(events[0].ball(), Some(1));
+        assert_eq!(events[0].pocket(), Some(Pocket::BottomSide));
+        assert!((events[0].time - state.pocketed()[0].time).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn racked_break_settles() {
+        let table = Table::pool(800.0, 400.0);
+        let mut state = GameState::eight_ball(&table, 9, 0.2);
+        assert_eq!(state.balls_len(), 16);
+        assert!(state
+            .balls()
+            .iter()
+            .zip(0..)
+            .all(|(ball, id)| ball.id == id));
+
+        assert!(state.strike(&CueStroke::new(0.0, 1200.0, 0.0, 0.0, 0.0)));
+        for _ in 0..1200 {
+            tick(&mut state, 1.0 / 60.0);
+        }
+
+        let events = state.drain_events();
+        assert!(events.iter().any(|e| e.kind == EventKind::BallContact));
+        assert_eq!(events.last().map(|e| e.kind), Some(EventKind::AllStopped));
+        let inside = |b: &Ball| (0.0..=800.0).contains(&b.x()) && (0.0..=400.0).contains(&b.y());
+        assert!(state.balls().iter().all(inside));
+    }
+
+    #[test]
+    fn queries_by_id_follow_a_ball_into_the_pocket() {
+        let table = Table::pool(800.0, 400.0);
+        let mut state = GameState::new(
+            table,
+            vec![Ball::pool(0, 400.0, 200.0), Ball::pool(8, 400.0, 300.0)],
+        );
+        let eight = state.ball_by_id(1).map(|ball| (ball.kind, ball.number));
+        assert_eq!(eight, Some((BallKind::Eight, 8)));
+        assert_eq!(state.ball_ids(), [0, 1]);
+
+        state.balls[1].velocity = Vector2D::new(0.0, 500.0);
+        for _ in 0..60 {
+            tick(&mut state, 1.0 / 60.0);
+        }
+
+        assert!(!state.is_on_table(1) && state.ball_by_id(1).is_none());
+        assert_eq!(state.ball_ids(), [0]);
+        let record = state.pocketed_by_id(1).map(|record| record.pocket);
+        assert_eq!(record, Some(Pocket::BottomSide));
+        assert!(state.pocketed_by_id(0).is_none());
+    }
+
+    #[test]
+    fn first_ball_becomes_the_cue_ball_unless_one_is_given() {
+        let table = Table::new(800.0, 400.0);
+        let plain = GameState::new(table, vec![Ball::new(100.0, 100.0, 0.0, 0.0, 10.0)]);
+        assert!(plain.balls()[0].is_cue());
+
+        let racked = GameState::new(
+            table,
+            vec![Ball::pool(1, 100.0, 100.0), Ball::pool(0, 300.0, 100.0)],
+        );
+        assert!(!racked.balls()[0].is_cue() && racked.balls()[1].is_cue());
+    }
+
+    #[test]
+    fn snooker_table_marks_baulk_and_the_d() {
+        let table = Table::snooker();
+        let (line, radius) = table.baulk();
+        assert!((table.width - 1124.2).abs() < 0.1);
+        assert!((line - 232.2).abs() < 0.1 && (radius - 92.0).abs() < 0.1);
+        assert!(table.in_d(Vector2D::new(line - 10.0, table.height * 0.5 + 50.0)));
+        assert!(!table.in_d(Vector2D::new(line + 10.0, table.height * 0.5)));
+    }
+}
@@ -0,0 +1,672 @@
+//! Matches: a series of frames between two players.
+//!
+//! A [`Match`] racks each frame in turn, plays it through the frame's
+//! [`GameState`], and keeps the score in frames until one player has won the
+//! race. Who breaks the first frame is settled by the lag (or a coin toss);
+//! after that the [`BreakFormat`] decides. Innings and timeouts are counted
+//! as the frames are played.
+//!
+//! Frame players are numbered from the breaker: player 0 of a frame's rules
+//! is whichever match player broke it. Everything the match reports uses
+//! match players.
+//!
+//! A match, including the frame in play, can be saved with
+//! [`Match::to_bytes`] to pause it and read back with [`Match::from_bytes`]
+//! to resume. The bytes use the layout of [snapshots](GameState::to_bytes)
+//! under their own signature `PSMT` and [`MATCH_VERSION`]. With the `serde`
+//! feature, [`Match::to_json`] and [`Match::from_json`] do the same in
+//! JSON.
+
+use std::cmp::Ordering;
+
+use wasm_bindgen::prelude::*;
+
+use crate::snapshot::{seal, unseal, Decode, Encode, Reader, SnapshotError};
+use crate::{
+    tick, CaromGame, CueStroke, GameState, PlannedShot, Pocket, Prediction, Table, Trajectory,
+};
+
+/// Format version written by [`Match::to_bytes`].
+pub const MATCH_VERSION: u16 = 1;
+
+/// The bytes every saved match starts with.
+const SIGNATURE: [u8; 4] = *b"PSMT";
+
+/// The game each frame of a match is played under.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Discipline {
+    /// Eight-ball.
+    EightBall = 0,
+    /// Nine-ball.
+    NineBall = 1,
+    /// Ten-ball.
+    TenBall = 2,
+    /// Straight pool, each frame played to the format's points.
+    StraightPool = 3,
+    /// Snooker.
+    Snooker = 4,
+    /// Straight rail carom, each frame played to the format's points.
+    StraightRail = 5,
+    /// Balkline carom, each frame played to the format's points.
+    Balkline = 6,
+    /// Three-cushion carom, each frame played to the format's points.
+    ThreeCushion = 7,
+}
+
+impl Discipline {
+    /// Returns the points a frame is usually played to in games that count
+    /// points, or zero for games won by a single ball.
+    #[must_use]
+    pub const fn default_points(self) -> u32 {
+        match self {
+            Self::StraightPool => 100,
+            Self::StraightRail | Self::Balkline => 200,
+            Self::ThreeCushion => 40,
+            Self::EightBall | Self::NineBall | Self::TenBall | Self::Snooker => 0,
+        }
+    }
+}
+
+/// Who breaks each frame after the first.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BreakFormat {
+    /// The winner of the last frame.
+    Winner = 0,
+    /// The players take turns.
+    Alternate = 1,
+    /// The loser of the last frame.
+    Loser = 2,
+}
+
+/// How a match is played.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MatchFormat {
+    /// The game of every frame.
+    pub discipline: Discipline,
+    /// Frames a player needs to win the match.
+    pub race_to: u32,
+    /// Who breaks each frame after the first.
+    pub break_format: BreakFormat,
+    /// Points a frame is played to, in games that count points.
+    pub points: u32,
+    /// Timeouts each player may take per frame.
+    pub timeouts: u32,
+    /// Seed of the first rack; each frame after it uses the next seed.
+    pub seed: u32,
+    /// Looseness of the racks; see [`GameState::eight_ball`].
+    pub gap: f32,
+}
+
+#[wasm_bindgen]
+impl MatchFormat {
+    /// Creates a race to `race_to` frames of `discipline`, with alternate
+    /// breaks, one timeout per frame, the usual points for the discipline
+    /// and tight racks.
+    #[wasm_bindgen(constructor)]
+    #[must_use]
+    pub fn new(discipline: Discipline, race_to: u32) -> Self {
+        Self {
+            discipline,
+            race_to,
+            break_format: BreakFormat::Alternate,
+            points: discipline.default_points(),
+            timeouts: 1,
+            seed: 0,
+            gap: 0.0,
+        }
+    }
+}
+
+/// A match between two players.
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Match {
+    /// How the match is played.
+    format: MatchFormat,
+    /// The table every frame is played on.
+    table: Table,
+    /// Names of the two players.
+    names: [String; 2],
+    /// Winner of each finished frame, in order.
+    results: Vec<u32>,
+    /// Frames started so far, including the one in play.
+    frames_started: u32,
+    /// The player who broke the frame in play.
+    breaker: Option<u32>,
+    /// The player who was at the table after the last shot.
+    at_table: Option<u32>,
+    /// Innings each player has begun over the whole match.
+    innings: [u32; 2],
+    /// Timeouts each player has left in the frame in play.
+    timeouts_left: [u32; 2],
+    /// The frame in play, once the match has started.
+    frame: Option<GameState>,
+}
+
+#[wasm_bindgen]
+impl Match {
+    /// Creates a match between `first` and `second` (players 0 and 1) on
+    /// `table`. No frame is racked until the lag decides who breaks.
+    #[wasm_bindgen(constructor)]
+    #[must_use]
+    pub fn new(format: &MatchFormat, table: &Table, first: String, second: String) -> Self {
+        Self {
+            format: *format,
+            table: *table,
+            names: [first, second],
+            results: Vec::new(),
+            frames_started: 0,
+            breaker: None,
+            at_table: None,
+            innings: [0, 0],
+            timeouts_left: [0, 0],
+            frame: None,
+        }
+    }
+
+    /// Returns the format of the match.
+    #[must_use]
+    pub fn format(&self) -> MatchFormat {
+        self.format
+    }
+
+    /// Returns the name of `player`.
+    #[must_use]
+    pub fn player_name(&self, player: u32) -> String {
+        self.names.get(player as usize).cloned().unwrap_or_default()
+    }
+
+    /// Settles the lag from the distance each player's ball came to rest
+    /// from the head cushion. The nearer ball wins and its player breaks the
+    /// first frame, which is racked.
+    ///
+    /// Returns the winner of the lag, or `undefined` if the distances are
+    /// level (lag again) or the match has already started.
+    pub fn lag(&mut self, first: f32, second: f32) -> Option<u32> {
+        if self.frame.is_some() {
+            return None;
+        }
+        let winner = match first.partial_cmp(&second)? {
+            Ordering::Less => 0,
+            Ordering::Greater => 1,
+            Ordering::Equal => return None,
+        };
+        self.start(winner);
+        Some(winner)
+    }
+
+    /// Starts the match with `breaker` breaking the first frame, as after a
+    /// coin toss. Returns `false` if the match has already started.
+    pub fn start(&mut self, breaker: u32) -> bool {
+        if self.frame.is_some() || breaker > 1 {
+            return false;
+        }
+        self.rack(breaker);
+        true
+    }
+
+    /// Racks the next frame once the one in play has been won. Returns
+    /// `false` if the frame is still in play or the match is over.
+    pub fn next_frame(&mut self) -> bool {
+        let (Some(&last_winner), Some(breaker)) = (self.results.last(), self.breaker) else {
+            return false;
+        };
+        if self.winner().is_some() || self.results.len() < self.frames_started as usize {
+            return false;
+        }
+        let next = match self.format.break_format {
+            BreakFormat::Winner => last_winner,
+            BreakFormat::Alternate => 1 - breaker,
+            BreakFormat::Loser => 1 - last_winner,
+        };
+        self.rack(next);
+        true
+    }
+
+    /// Returns the winner of the match, once a player has won the race.
+    #[must_use]
+    pub fn winner(&self) -> Option<u32> {
+        (0..2).find(|&player| self.frames_won(player) >= self.format.race_to)
+    }
+
+    /// Returns the frames `player` has won.
+    #[must_use]
+    pub fn frames_won(&self, player: u32) -> u32 {
+        let won = self.results.iter().filter(|&&winner| winner == player);
+        u32::try_from(won.count()).unwrap_or(u32::MAX)
+    }
+
+    /// Returns the winner of each finished frame, in order.
+    #[must_use]
+    pub fn results(&self) -> Vec<u32> {
+        self.results.clone()
+    }
+
+    /// Returns the number of the frame in play, counting from 1, or 0 before
+    /// the match has started.
+    #[must_use]
+    pub fn frame_number(&self) -> u32 {
+        self.frames_started
+    }
+
+    /// Returns the player who broke the frame in play.
+    #[must_use]
+    pub fn breaker(&self) -> Option<u32> {
+        self.breaker
+    }
+
+    /// Returns the player to shoot, or `undefined` before the match starts.
+    #[must_use]
+    pub fn current_player(&self) -> Option<u32> {
+        let player = self.frame.as_ref()?.current_player()?;
+        self.to_match_player(player)
+    }
+
+    /// Returns how many innings `player` has begun over the match.
+    #[must_use]
+    pub fn innings(&self, player: u32) -> u32 {
+        self.innings.get(player as usize).copied().unwrap_or(0)
+    }
+
+    /// Returns how many timeouts `player` has left in this frame.
+    #[must_use]
+    pub fn timeouts_left(&self, player: u32) -> u32 {
+        self.timeouts_left
+            .get(player as usize)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Takes a timeout for `player`. Returns `false` if they have none left.
+    pub fn take_timeout(&mut self, player: u32) -> bool {
+        match self.timeouts_left.get_mut(player as usize) {
+            Some(left) if *left > 0 => {
+                *left -= 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns a copy of the frame in play, or `undefined` before the match
+    /// starts.
+    #[must_use]
+    pub fn frame_state(&self) -> Option<GameState> {
+        self.frame.clone()
+    }
+
+    /// Advances the frame in play by `dt` seconds; see [`tick`].
+    pub fn tick(&mut self, dt: f32) {
+        if let Some(frame) = &mut self.frame {
+            tick(frame, dt);
+        }
+        self.follow();
+    }
+
+    /// Strikes the cue ball in the frame in play; see
+    /// [`GameState::strike`].
+    pub fn strike(&mut self, stroke: &CueStroke) -> bool {
+        self.act(|frame| frame.strike(stroke))
+    }
+
+    /// Plays a shot chosen by a [`Planner`](crate::Planner) in the frame in
+    /// play; see [`GameState::play_shot`].
+    pub fn play_shot(&mut self, shot: &PlannedShot) -> bool {
+        self.act(|frame| frame.play_shot(shot))
+    }
+
+    /// Predicts a stroke in the frame in play without playing it; see
+    /// [`GameState::predict`]. Returns `undefined` before the match starts.
+    #[must_use]
+    pub fn predict(&self, stroke: &CueStroke, max_time: f32) -> Option<Prediction> {
+        self.frame
+            .as_ref()
+            .map(|frame| frame.predict(stroke, max_time))
+    }
+
+    /// Traces the cue ball for a stroke in the frame in play without
+    /// playing it; see [`GameState::trajectory`]. Returns `undefined` before
+    /// the match starts.
+    #[must_use]
+    pub fn trajectory(&self, stroke: &CueStroke, max_time: f32) -> Option<Trajectory> {
+        self.frame
+            .as_ref()
+            .map(|frame| frame.trajectory(stroke, max_time))
+    }
+
+    /// Places the cue ball in the frame in play; see
+    /// [`GameState::place_cue_ball`].
+    pub fn place_cue_ball(&mut self, x: f32, y: f32) -> bool {
+        self.act(|frame| frame.place_cue_ball(x, y))
+    }
+
+    /// Calls a pocket in the frame in play; see [`GameState::call_pocket`].
+    pub fn call_pocket(&mut self, pocket: Pocket) -> bool {
+        self.act(|frame| frame.call_pocket(pocket))
+    }
+
+    /// Calls a shot in the frame in play; see [`GameState::call_shot`].
+    pub fn call_shot(&mut self, ball: u32, pocket: Pocket) -> bool {
+        self.act(|frame| frame.call_shot(ball, pocket))
+    }
+
+    /// Declares a push-out in the frame in play; see
+    /// [`GameState::push_out`].
+    pub fn push_out(&mut self) -> bool {
+        self.act(GameState::push_out)
+    }
+
+    /// Passes after a push-out in the frame in play; see
+    /// [`GameState::pass`].
+    pub fn pass(&mut self) -> bool {
+        self.act(GameState::pass)
+    }
+
+    /// Nominates a ball in the frame in play; see [`GameState::nominate`].
+    pub fn nominate(&mut self, ball: u32) -> bool {
+        self.act(|frame| frame.nominate(ball))
+    }
+
+    /// Replays a miss in the frame in play; see
+    /// [`GameState::replay_miss`].
+    pub fn replay_miss(&mut self) -> bool {
+        self.act(GameState::replay_miss)
+    }
+
+    /// Returns this match, including the frame in play, in its binary
+    /// format at [`MATCH_VERSION`]. In JS this is a `Uint8Array`.
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        seal(SIGNATURE, MATCH_VERSION, self)
+    }
+
+    /// Reads a match back from bytes written by [`Match::to_bytes`]. In JS,
+    /// bytes that cannot be read throw an `Error` saying why.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`SnapshotError`] if the bytes are not a whole, intact
+    /// match of a version this build reads, or describe one play could not
+    /// reach.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        unseal(bytes, SIGNATURE, MATCH_VERSION)
+    }
+}
+
+impl Match {
+    /// Returns the frame in play, once the match has started.
+    #[must_use]
+    pub const fn frame(&self) -> Option<&GameState> {
+        self.frame.as_ref()
+    }
+
+    /// Racks a new frame for `breaker` to break.
+    fn rack(&mut self, breaker: u32) {
+        let format = self.format;
+        let table = &self.table;
+        let seed = format.seed.wrapping_add(self.frames_started);
+        let (gap, points) = (format.gap, format.points);
+        let frame = match format.discipline {
+            Discipline::EightBall => GameState::eight_ball(table, seed, gap),
+            Discipline::NineBall => GameState::nine_ball(table, seed, gap),
+            Discipline::TenBall => GameState::ten_ball(table, seed, gap),
+            Discipline::StraightPool => GameState::straight_pool(table, seed, gap, points),
+            Discipline::Snooker => GameState::snooker(table, seed, gap),
+            Discipline::StraightRail => GameState::carom(table, CaromGame::StraightRail, points),
+            Discipline::Balkline => GameState::carom(table, CaromGame::Balkline, points),
+            Discipline::ThreeCushion => GameState::carom(table, CaromGame::ThreeCushion, points),
+        };
+        self.frame = Some(frame);
+        self.frames_started += 1;
+        self.breaker = Some(breaker);
+        self.timeouts_left = [format.timeouts; 2];
+        self.at_table = None;
+        self.follow();
+    }
+
+    /// Runs `action` on the frame in play and catches up with its outcome.
+    fn act(&mut self, action: impl FnOnce(&mut GameState) -> bool) -> bool {
+        let Some(frame) = &mut self.frame else {
+            return false;
+        };
+        let done = action(frame);
+        self.follow();
+        done
+    }
+
+    /// Counts a new inning whenever the turn has passed and records the
+    /// winner of a frame that has just ended.
+    fn follow(&mut self) {
+        let Some(frame) = &self.frame else {
+            return;
+        };
+        let to_shoot = self.current_player();
+        let frame_winner = frame
+            .winner()
+            .and_then(|player| self.to_match_player(player));
+
+        if let Some(player) = to_shoot.filter(|&player| Some(player) != self.at_table) {
+            self.innings[player as usize] += 1;
+            self.at_table = Some(player);
+        }
+        if let Some(winner) = frame_winner {
+            if self.results.len() < self.frames_started as usize {
+                self.results.push(winner);
+            }
+        }
+    }
+
+    /// Returns `true` if this match is one play could reach: every player it
+    /// records is 0 or 1, a frame in play has a breaker and has been counted,
+    /// no player has more timeouts than the format gives, the table and racks
+    /// are finite, and the frame in play is
+    /// [consistent](GameState::is_consistent).
+    #[must_use]
+    pub fn is_consistent(&self) -> bool {
+        let is_player = |player: &u32| *player < 2;
+        self.results.iter().all(is_player)
+            && self.breaker.iter().all(is_player)
+            && self.at_table.iter().all(is_player)
+            && self.frame.is_some() == self.breaker.is_some()
+            && (self.frame.is_none() || self.frames_started >= 1)
+            && self.results.len() <= self.frames_started as usize
+            && self
+                .timeouts_left
+                .iter()
+                .all(|&left| left <= self.format.timeouts)
+            && self.format.gap.is_finite()
+            && self.table.is_finite()
+            && self.frame.as_ref().is_none_or(GameState::is_consistent)
+    }
+
+    /// Turns a player of the frame in play into a match player.
+    fn to_match_player(&self, frame_player: u32) -> Option<u32> {
+        self.breaker.map(|breaker| (breaker + frame_player) % 2)
+    }
+}
+
+impl Encode for Match {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.format.encode(out);
+        self.table.encode(out);
+        self.names.encode(out);
+        self.results.encode(out);
+        self.frames_started.encode(out);
+        self.breaker.encode(out);
+        self.at_table.encode(out);
+        self.innings.encode(out);
+        self.timeouts_left.encode(out);
+        self.frame.encode(out);
+    }
+}
+
+impl Decode for Match {
+    fn decode(input: &mut Reader<'_>) -> Result<Self, SnapshotError> {
+        let game = Self {
+            format: input.read()?,
+            table: input.read()?,
+            names: input.read()?,
+            results: input.read()?,
+            frames_started: input.read()?,
+            breaker: input.read()?,
+            at_table: input.read()?,
+            innings: input.read()?,
+            timeouts_left: input.read()?,
+            frame: input.read()?,
+        };
+        if !game.is_consistent() {
+            return Err(SnapshotError::Invalid);
+        }
+        Ok(game)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use super::*;
+    use crate::rules::testing::{pool_game, pot_in_top_side};
+    use crate::{Rotation, Rules};
+
+    fn nine_ball_match(break_format: BreakFormat) -> Match {
+        let format = MatchFormat {
+            break_format,
+            ..MatchFormat::new(Discipline::NineBall, 2)
+        };
+        Match::new(
+            &format,
+            &Table::pool(800.0, 400.0),
+            "Ann".into(),
+            "Bo".into(),
+        )
+    }
+
+    /// Replaces the frame in play with one where its breaker is lined up to
+    /// pot the 9 into the top side pocket, and plays the shot.
+    fn win_frame(game: &mut Match) {
+        let balls = pot_in_top_side(9, &[]);
+        game.frame = Some(pool_game(balls, Rules::Rotation(Rotation::nine_ball())));
+        assert!(game.strike(&CueStroke::new(-FRAC_PI_2, 200.0, 0.0, 0.0, 0.0)));
+        for _ in 0..600 {
+            game.tick(1.0 / 60.0);
+        }
+    }
+
+    #[test]
+    fn lag_goes_to_the_ball_nearest_the_head_cushion() {
+        let mut game = nine_ball_match(BreakFormat::Alternate);
+        assert_eq!(game.lag(12.0, 12.0), None);
+        assert_eq!(game.lag(30.0, 12.0), Some(1));
+
+        assert_eq!(game.breaker(), Some(1));
+        assert_eq!(game.current_player(), Some(1));
+        assert_eq!(game.frame_number(), 1);
+        assert_eq!(game.innings(1), 1);
+        assert_eq!(game.lag(1.0, 2.0), None);
+    }
+
+    #[test]
+    fn frames_count_towards_the_race() {
+        let mut game = nine_ball_match(BreakFormat::Winner);
+        assert!(game.start(0));
+
+        win_frame(&mut game);
+        assert_eq!(game.results(), [0]);
+        assert!(game.next_frame());
+        assert_eq!(game.breaker(), Some(0));
+
+        win_frame(&mut game);
+        assert_eq!(game.frames_won(0), 2);
+        assert_eq!(game.winner(), Some(0));
+        assert!(!game.next_frame());
+    }
+
+    #[test]
+    fn break_format_picks_the_next_breaker() {
+        for (format, breaker) in [
+            (BreakFormat::Winner, 1),
+            (BreakFormat::Alternate, 0),
+            (BreakFormat::Loser, 0),
+        ] {
+            let mut game = nine_ball_match(format);
+            assert!(game.start(1));
+            assert!(!game.next_frame());
+            win_frame(&mut game);
+            assert_eq!(game.results(), [1]);
+            assert!(game.next_frame());
+            assert_eq!(game.breaker(), Some(breaker), "{format:?}");
+        }
+    }
+
+    #[test]
+    fn timeouts_are_limited_per_frame() {
+        let mut game = nine_ball_match(BreakFormat::Alternate);
+        assert!(game.start(0));
+        assert!(game.take_timeout(1));
+        assert!(!game.take_timeout(1));
+        assert_eq!(game.timeouts_left(0), 1);
+
+        win_frame(&mut game);
+        assert!(game.next_frame());
+        assert_eq!(game.timeouts_left(1), 1);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn match_survives_a_pause() {
+        let mut game = nine_ball_match(BreakFormat::Alternate);
+        assert!(game.start(0));
+        win_frame(&mut game);
+
+        let saved = serde_json::to_string(&game).unwrap();
+        let resumed: Match = serde_json::from_str(&saved).unwrap();
+
+        assert_eq!(resumed.results(), game.results());
+        assert_eq!(resumed.player_name(1), "Bo");
+        assert_eq!(resumed.innings(0), game.innings(0));
+        assert_eq!(
+            resumed.frame().map(|frame| frame.balls().len()),
+            game.frame().map(|frame| frame.balls().len())
+        );
+    }
+
+    #[test]
+    fn match_survives_a_pause_in_bytes() {
+        let mut game = nine_ball_match(BreakFormat::Winner);
+        assert!(game.start(1));
+        win_frame(&mut game);
+
+        let saved = game.to_bytes();
+        let resumed = Match::from_bytes(&saved).unwrap();
+
+        assert_eq!(&saved[..4], b"PSMT");
+        assert_eq!(resumed.to_bytes(), saved);
+        assert_eq!(resumed.results(), game.results());
+        assert_eq!(resumed.player_name(0), "Ann");
+
+        let refused = |edited: &Match| {
+            Match::from_bytes(&edited.to_bytes()).unwrap_err() == SnapshotError::Invalid
+        };
+        let mut edited = game.clone();
+        edited.at_table = Some(2);
+        assert!(refused(&edited));
+        let mut edited = game.clone();
+        edited.breaker = None;
+        assert!(refused(&edited));
+        let mut edited = game.clone();
+        edited.timeouts_left[1] = edited.format.timeouts + 1;
+        assert!(refused(&edited));
+        let mut edited = nine_ball_match(BreakFormat::Winner);
+        assert!(edited.start(0));
+        edited.frames_started = 0;
+        assert!(refused(&edited));
+    }
+}
@@ -0,0 +1,162 @@
+//! Transcendental functions used by the simulation.
+//!
+//! IEEE 754 fixes the result of `+`, `-`, `*`, `/`, `sqrt` and fused
+//! multiply-add to the last bit, and Rust evaluates float expressions as
+//! written, without reassociating or contracting them, so those agree on
+//! every target. Sines, arctangents, logarithms and `hypot` are not covered:
+//! the standard library leaves them to the platform's maths library, and
+//! native x86-64 and WebAssembly can differ in the last bit. A long shot turns
+//! that into visibly different play, which breaks online games and replays
+//! shared between platforms.
+//!
+//! The simulation calls these functions through this module. With the
+//! `deterministic` feature they come from `libm`, a pure Rust port of musl's
+//! maths library that computes the same bits on every target. Without it
+//! they are the standard library's, which may be faster where the platform
+//! has native versions.
+//!
+//! Everything else that could vary is already fixed: contacts resolve in
+//! time order with ties broken by ball order, friction runs on a fixed
+//! schedule, and no state is kept in hashed collections.
+
+/// Returns the sine and cosine of `x`, in radians.
+#[cfg(feature = "deterministic")]
+pub fn sin_cos(x: f32) -> (f32, f32) {
+    libm::sincosf(x)
+}
+
+/// Returns the sine and cosine of `x`, in radians.
+#[cfg(not(feature = "deterministic"))]
+pub fn sin_cos(x: f32) -> (f32, f32) {
+    x.sin_cos()
+}
+
+/// Returns the cosine of `x`, in radians.
+#[cfg(feature = "deterministic")]
+pub fn cos(x: f32) -> f32 {
+    libm::cosf(x)
+}
+
+/// Returns the cosine of `x`, in radians.
+#[cfg(not(feature = "deterministic"))]
+pub fn cos(x: f32) -> f32 {
+    x.cos()
+}
+
+/// Returns the angle of the point `(x, y)` from the `x` axis, in radians.
+#[cfg(feature = "deterministic")]
+pub fn atan2(y: f32, x: f32) -> f32 {
+    libm::atan2f(y, x)
+}
+
+/// Returns the angle of the point `(x, y)` from the `x` axis, in radians.
+#[cfg(not(feature = "deterministic"))]
+pub fn atan2(y: f32, x: f32) -> f32 {
+    y.atan2(x)
+}
+
+/// Returns the length of the hypotenuse of a right triangle with legs `x`
+/// and `y`.
+#[cfg(feature = "deterministic")]
+pub fn hypot(x: f32, y: f32) -> f32 {
+    libm::hypotf(x, y)
+}
+
+/// Returns the length of the hypotenuse of a right triangle with legs `x`
+/// and `y`.
+#[cfg(not(feature = "deterministic"))]
+pub fn hypot(x: f32, y: f32) -> f32 {
+    x.hypot(y)
+}
+
+/// Returns the natural logarithm of `x`.
+#[cfg(feature = "deterministic")]
+pub fn ln(x: f32) -> f32 {
+    libm::logf(x)
+}
+
+/// Returns the natural logarithm of `x`.
+#[cfg(not(feature = "deterministic"))]
+pub fn ln(x: f32) -> f32 {
+    x.ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn functions_agree_with_the_standard_library() {
+        for step in 0..64_u8 {
+            let x = f32::from(step) * 0.1 - 3.2;
+            let (sin, cos) = sin_cos(x);
+            assert!((sin - x.sin()).abs() < 1e-6 && (cos - x.cos()).abs() < 1e-6);
+            assert!((super::cos(x) - x.cos()).abs() < 1e-6);
+            assert!((atan2(x, 1.5) - x.atan2(1.5)).abs() < 1e-6);
+            assert!((hypot(x, 2.0) - x.hypot(2.0)).abs() < 1e-5);
+            assert!((ln(x + 3.3) - (x + 3.3).ln()).abs() < 1e-5);
+        }
+    }
+
+    #[cfg(feature = "deterministic")]
+    mod golden {
+        //! Hashes of shots played in deterministic mode. They are the same
+        //! on every target; a change to any of them means the simulation
+        //! itself has changed, and every stored replay with it.
+        //!
+        //! CI runs them both natively and in WebAssembly under Node, with
+        //! `wasm-bindgen-test-runner` as the wasm32 test runner, to hold
+        //! them to that.
+
+        use crate::{tick, Ball, CaromGame, CueStroke, Difficulty, GameState, Planner, Table};
+
+        const STEP: f32 = 1.0 / 120.0;
+
+        /// Strikes `state` with `stroke` and ticks it until every ball stops.
+        fn play(mut state: GameState, stroke: &CueStroke) -> GameState {
+            assert!(state.strike(stroke));
+            while state.balls().iter().any(Ball::is_moving) {
+                tick(&mut state, STEP);
+            }
+            state
+        }
+
+        #[cfg_attr(not(target_arch = "wasm32"), test)]
+        #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+        fn eight_ball_break() {
+            let state = GameState::eight_ball(&Table::pool(800.0, 400.0), 1, 0.0);
+            let stroke = CueStroke::new(0.01, 950.0, 0.15, -0.2, 0.05);
+
+            assert_eq!(play(state, &stroke).state_hash(), 0x0867_91FF_59A4_D300);
+        }
+
+        #[cfg_attr(not(target_arch = "wasm32"), test)]
+        #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+        fn snooker_break() {
+            let state = GameState::snooker(&Table::snooker(), 2, 0.0);
+            let stroke = CueStroke::new(-0.05, 700.0, -0.3, 0.1, 0.0);
+
+            assert_eq!(play(state, &stroke).state_hash(), 0x5E67_7FA1_7DAC_CDC8);
+        }
+
+        #[cfg_attr(not(target_arch = "wasm32"), test)]
+        #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+        fn three_cushion_opening() {
+            let state = GameState::carom(&Table::carom(), CaromGame::ThreeCushion, 15);
+            let stroke = CueStroke::new(0.4, 800.0, 0.4, 0.2, 0.1);
+
+            assert_eq!(play(state, &stroke).state_hash(), 0xB6D8_57EE_91A6_4DDC);
+        }
+
+        #[cfg_attr(not(target_arch = "wasm32"), test)]
+        #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+        fn computer_shot() {
+            let mut state = GameState::nine_ball(&Table::pool(800.0, 400.0), 3, 0.0);
+            let mut planner = Planner::new(Difficulty::Amateur, 9);
+            let stroke = planner.plan(&state).unwrap().stroke();
+            state = play(state, &stroke);
+
+            assert_eq!(state.state_hash(), 0x2CEF_51D1_4195_4563);
+        }
+    }
+}
@@ -0,0 +1,628 @@
+//! Event-driven collision detection and response.
+//!
+//! Within a step, balls travel in straight lines between contacts. Rather than
+//! integrating the whole step and then repairing overlaps, [`advance`] solves
+//! for the exact time of impact of every ball-ball and ball-cushion pair, and
+//! of every ball reaching a pocket, moves the whole table forward to the
+//! earliest one, resolves it, and repeats until the step is used up. A fast
+//! ball can therefore never skip over another ball or a cushion, and the
+//! outcome does not depend on how the caller slices time.
+//!
+//! Cloth friction changes velocities continuously, so it is applied in
+//! [`FRICTION_STEP`] increments on a schedule carried across calls. The
+//! straight-line assumption only has to hold between two friction updates,
+//! and because the schedule is independent of the step size, a shot plays
+//! out the same at any frame rate.
+//!
+//! Every contact, pocketed ball and ball coming to rest is recorded as an
+//! [`Event`] on the state as it is resolved. Overlaps repaired by the
+//! discrete fallback are not reported.
+
+use crate::cushion::{self, Cushion};
+use crate::event::Event;
+use crate::pocket::{self, Pocket, PocketMouth, PocketedBall};
+use crate::{
+    cloth, Ball, GameState, Table, Vector2D, Vector3D, BALL_FRICTION, BALL_RESTITUTION, GRAVITY,
+};
+
+/// Interval, in seconds, between cloth friction updates.
+pub const FRICTION_STEP: f32 = 1.0 / 240.0;
+
+/// Upper bound on the number of contacts resolved within a single step.
+///
+/// Tight clusters can generate long chains of zero-time contacts; once the
+/// budget is exhausted the remainder of the step falls back to discrete
+/// overlap resolution so the state always stays valid.
+const MAX_CONTACTS_PER_STEP: usize = 1024;
+
+/// Upper bound on the number of friction updates within a single call to
+/// [`advance`], ten seconds of play.
+///
+/// Time beyond it is dropped, so one enormous step cannot stall the caller.
+const MAX_FRICTION_STEPS_PER_CALL: u32 = 2400;
+
+/// Slack, in units, added to how far a ball can travel within a step when
+/// ruling out cushions and pockets it cannot reach, to cover rounding.
+const REACH_MARGIN: f32 = 1.0;
+
+/// Maximum number of relaxation passes used by the discrete fallback.
+const COLLISION_ITERATIONS: usize = 8;
+
+/// A contact that will occur during the current step.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Contact {
+    /// Two balls, by index, with `.0 < .1`.
+    Ball(usize, usize),
+    /// A ball, by index, and a cushion, by index.
+    Cushion(usize, usize),
+    /// A ball, by index, dropping into a pocket.
+    Pocket(usize, Pocket),
+}
+
+/// Advances `state` by `dt` seconds, interleaving contacts and friction.
+///
+/// `dt` must be finite. At most [`MAX_FRICTION_STEPS_PER_CALL`] friction
+/// updates are made; any time left after them is dropped.
+pub fn advance(state: &mut GameState, dt: f32) {
+    advance_with_friction_step(state, dt, FRICTION_STEP);
+}
+
+/// Advances `state` as [`advance`] does, but applies friction every
+/// `friction_step` seconds instead of every [`FRICTION_STEP`].
+///
+/// A longer step is cheaper and less faithful: balls run straight for
+/// longer between friction updates, so curves from spin and the points where
+/// balls stop come out slightly off.
+pub fn advance_with_friction_step(state: &mut GameState, dt: f32, friction_step: f32) {
+    run(state, dt, friction_step, false);
+}
+
+/// Advances `state` as [`advance_with_friction_step`] does, but lets the
+/// balls coast whenever every one of them is rolling.
+///
+/// A rolling ball decelerates at a constant rate along a straight line, so
+/// instead of stepping through friction updates the table is carried in one
+/// go to the next contact with a cushion, a pocket or a ball at rest, or to
+/// the next ball coming to rest, and stepped again only from there. Only the
+/// spells of sliding after contacts cost friction updates. Coasting stops
+/// short wherever two moving balls might meet, but is otherwise exact.
+pub fn advance_coasting(state: &mut GameState, dt: f32, friction_step: f32) {
+    run(state, dt, friction_step, true);
+}
+
+/// Advances `state` by `dt` seconds with friction updates every
+/// `friction_step` seconds, coasting between contacts if `coasting`.
+fn run(state: &mut GameState, dt: f32, friction_step: f32, coasting: bool) {
+    let table = state.table;
+    let cushions = cushion::cushions(table);
+    let mouths: Vec<PocketMouth> = pocket::mouths(table).collect();
+    let mut remaining = dt;
+    for _ in 0..MAX_FRICTION_STEPS_PER_CALL {
+        if coasting {
+            if let Some(time) = coast(state, &cushions, &mouths, remaining, friction_step) {
+                remaining -= time;
+                state.friction_countdown = friction_step;
+                if remaining <= 0.0 {
+                    return;
+                }
+                continue;
+            }
+        }
+        let interval = remaining.min(state.friction_countdown);
+        advance_contacts(state, &cushions, &mouths, interval);
+        remaining -= interval;
+        state.friction_countdown -= interval;
+
+        if state.friction_countdown <= 0.0 {
+            let mut any_stopped = false;
+            for ball in &mut state.balls {
+                let was_moving = ball.is_moving();
+                cloth::apply_friction(ball, table, friction_step);
+                if was_moving && !ball.is_moving() {
+                    state.events.push(Event::ball_stopped(state.time, ball.id));
+                    any_stopped = true;
+                }
+            }
+            if any_stopped {
+                record_all_stopped(state);
+            }
+            state.friction_countdown += friction_step;
+        }
+        if remaining <= 0.0 {
+            return;
+        }
+    }
+}
+
+/// Carries every ball forward by up to `dt` seconds of rolling and returns
+/// the time taken, or returns `None` if a ball is sliding or the balls could
+/// not go `min_time` without a contact.
+///
+/// Each moving ball could go as far as its first contact with a cushion, a
+/// pocket or a ball at rest, or until it stops; the table goes as far as the
+/// earliest of those, and no further than any two moving balls are sure to
+/// stay apart.
+fn coast(
+    state: &mut GameState,
+    cushions: &[Cushion],
+    mouths: &[PocketMouth],
+    dt: f32,
+    min_time: f32,
+) -> Option<f32> {
+    let table = state.table;
+    if !state.balls.iter().all(cloth::is_rolling) {
+        return None;
+    }
+    let mut time = dt;
+    for (i, a) in state.balls.iter().enumerate() {
+        let speed = a.velocity.length();
+        if speed <= 0.0 {
+            continue;
+        }
+        // How far the ball rolls before it stops, and how long it would
+        // take to get there without slowing down.
+        let stop = cloth::rolling_time(a, table);
+        let distance = speed * stop * 0.5;
+        let mut horizon = distance / speed;
+        if let Some((until, _)) = next_contact(std::slice::from_ref(a), cushions, mouths, horizon) {
+            horizon = until;
+        }
+        for b in &state.balls {
+            if b.velocity.length_squared() <= 0.0 {
+                if let Some(until) = ball_time_of_impact(a, b) {
+                    horizon = horizon.min(until);
+                }
+            }
+        }
+        time = time.min(if horizon * speed >= distance {
+            stop
+        } else {
+            rolling_time_to(speed, horizon * speed, table)
+        });
+        for b in state.balls.iter().skip(i + 1) {
+            if b.velocity.length_squared() > 0.0 {
+                time = time.min(time_apart(a, b, table));
+            }
+        }
+    }
+    if time < min_time && time < dt {
+        return None;
+    }
+
+    let mut stops: Vec<(f32, u32)> = Vec::new();
+    for ball in &mut state.balls {
+        let was_moving = ball.is_moving();
+        let stop = cloth::rolling_time(ball, table);
+        cloth::roll_along(ball, table, time);
+        if was_moving && !ball.is_moving() {
+            stops.push((stop.min(time), ball.id));
+        }
+    }
+    stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+    for &(stop, id) in &stops {
+        state
+            .events
+            .push(Event::ball_stopped(state.time + stop, id));
+    }
+    state.time += time;
+    if !stops.is_empty() {
+        record_all_stopped(state);
+    }
+    Some(time)
+}
+
+/// Returns a time for which the rolling balls `a` and `b` are sure not to
+/// meet, as long as both keep rolling.
+///
+/// They close no faster than their speeds added. Along the line between
+/// them, too, the gap changes at their separating speed, which rolling
+/// resistance can turn around no faster than the difference of their
+/// decelerations; whichever bound lasts longer holds.
+fn time_apart(a: &Ball, b: &Ball, table: Table) -> f32 {
+    let offset = b.position - a.position;
+    let distance = offset.length();
+    let gap = (distance - a.radius - b.radius).max(0.0);
+    let closing = a.velocity.length() + b.velocity.length();
+    let by_speed = gap / closing;
+    if distance <= 0.0 {
+        return by_speed;
+    }
+    let separating = (b.velocity - a.velocity).dot(offset) / distance;
+    let turning = 0.5
+        * table.roll_friction
+        * GRAVITY
+        * (b.velocity / b.velocity.length() - a.velocity / a.velocity.length()).length();
+    // Positive root of gap + separating t - turning t^2 = 0.
+    let root = (separating * separating + 4.0 * turning * gap).sqrt();
+    let by_line = if separating >= 0.0 {
+        (separating + root) / (2.0 * turning)
+    } else {
+        2.0 * gap / (root - separating)
+    };
+    by_speed.max(if by_line.is_nan() { 0.0 } else { by_line })
+}
+
+/// Returns the time a ball rolling at `speed` takes to cover `distance`,
+/// which must be no further than it rolls before stopping.
+fn rolling_time_to(speed: f32, distance: f32, table: Table) -> f32 {
+    // Smaller root of distance = speed t - deceleration t^2 / 2, in the
+    // cancellation-free form.
+    let deceleration = table.roll_friction * GRAVITY;
+    let discriminant = speed * speed - 2.0 * deceleration * distance;
+    2.0 * distance / (speed + discriminant.max(0.0).sqrt())
+}
+
+/// Advances the balls by `dt` seconds, resolving every contact in time order.
+fn advance_contacts(state: &mut GameState, cushions: &[Cushion], mouths: &[PocketMouth], dt: f32) {
+    let table = state.table;
+    let mut remaining = dt;
+    for _ in 0..MAX_CONTACTS_PER_STEP {
+        let Some((time, contact)) = next_contact(&state.balls, cushions, mouths, remaining) else {
+            drift(state, remaining);
+            return;
+        };
+        drift(state, time);
+        remaining -= time;
+        match contact {
+            Contact::Ball(i, j) => {
+                let (head, tail) = state.balls.split_at_mut(j);
+                let (a, b) = (&mut head[i], &mut tail[0]);
+                let speed = closing_speed(a, b);
+                collide(a, b);
+                let (a, b) = (a.id, b.id);
+                state
+                    .events
+                    .push(Event::ball_contact(state.time, a, b, speed));
+            }
+            Contact::Cushion(i, k) => {
+                let ball = &mut state.balls[i];
+                let speed = cushion::resolve(ball, &cushions[k], table);
+                let id = ball.id;
+                state.events.push(Event::cushion_contact(
+                    state.time,
+                    id,
+                    cushions[k].rail,
+                    speed,
+                ));
+            }
+            Contact::Pocket(i, pocket) => {
+                let ball = state.balls.remove(i);
+                state
+                    .events
+                    .push(Event::pocketed(state.time, ball.id, pocket));
+                state
+                    .pocketed
+                    .push(PocketedBall::new(ball, pocket, state.time));
+                record_all_stopped(state);
+            }
+        }
+    }
+
+    drift(state, remaining);
+    resolve_ball_collisions(&mut state.balls);
+    for ball in &mut state.balls {
+        for cushion in cushions {
+            if cushion.overlaps(ball) {
+                cushion::resolve(ball, cushion, table);
+            }
+        }
+    }
+}
+
+/// Records that the table has come to rest, if no ball is moving any more.
+fn record_all_stopped(state: &mut GameState) {
+    if !state.balls.iter().any(Ball::is_moving) {
+        state.events.push(Event::all_stopped(state.time));
+    }
+}
+
+/// Moves every ball along its current velocity for `dt` seconds.
+fn drift(state: &mut GameState, dt: f32) {
+    if dt <= 0.0 {
+        return;
+    }
+    for ball in &mut state.balls {
+        ball.position += ball.velocity * dt;
+    }
+    state.time += dt;
+}
+
+/// Finds the earliest contact within `horizon` seconds, if any.
+fn next_contact(
+    balls: &[Ball],
+    cushions: &[Cushion],
+    mouths: &[PocketMouth],
+    horizon: f32,
+) -> Option<(f32, Contact)> {
+    let mut earliest: Option<(f32, Contact)> = None;
+    let mut consider = |time: f32, contact: Contact| {
+        if time <= horizon && earliest.is_none_or(|(best, _)| time < best) {
+            earliest = Some((time, contact));
+        }
+    };
+
+    for (i, a) in balls.iter().enumerate() {
+        let moving = a.velocity.length_squared() > 0.0;
+        // A ball at rest cannot reach a cushion or a pocket by itself.
+        if moving {
+            // Nor anything further away than it can travel in time.
+            let travel = a.velocity.length() * horizon + REACH_MARGIN;
+            for (k, cushion) in cushions.iter().enumerate() {
+                if !cushion.is_near(a.position, a.radius + travel) {
+                    continue;
+                }
+                if let Some(time) = cushion.time_of_impact(a) {
+                    consider(time, Contact::Cushion(i, k));
+                }
+            }
+            for mouth in mouths {
+                if mouth.spec.shelf_depth - mouth.depth(a.position) > travel {
+                    continue;
+                }
+                if let Some(time) = mouth.drop_time(a) {
+                    consider(time, Contact::Pocket(i, mouth.pocket));
+                }
+            }
+        }
+        for (j, b) in balls.iter().enumerate().skip(i + 1) {
+            // Nor another ball at rest.
+            if !moving && b.velocity.length_squared() <= 0.0 {
+                continue;
+            }
+            if let Some(time) = ball_time_of_impact(a, b) {
+                consider(time, Contact::Ball(i, j));
+            }
+        }
+    }
+
+    earliest
+}
+
+/// Returns the time until `a` and `b` touch, assuming straight-line motion.
+///
+/// Balls that already overlap while approaching report an immediate contact;
+/// balls that are separating never collide.
+fn ball_time_of_impact(a: &Ball, b: &Ball) -> Option<f32> {
+    let offset = b.position - a.position;
+    let relative_velocity = b.velocity - a.velocity;
+    let approach = offset.dot(relative_velocity);
+    if approach >= 0.0 {
+        return None;
+    }
+
+    let contact_distance = a.radius + b.radius;
+    let gap = offset.length_squared() - contact_distance * contact_distance;
+    if gap <= 0.0 {
+        return Some(0.0);
+    }
+
+    let discriminant = approach * approach - relative_velocity.length_squared() * gap;
+    if discriminant < 0.0 {
+        return None;
+    }
+
+    // Smaller root of |offset + v t|^2 = d^2, in the cancellation-free form.
+    Some(gap / (-approach + discriminant.sqrt()))
+}
+
+/// Returns the speed at which `a` and `b` approach along the line of centres.
+fn closing_speed(a: &Ball, b: &Ball) -> f32 {
+    let delta = b.position - a.position;
+    let distance = delta.length();
+    if distance <= f32::EPSILON {
+        return 0.0;
+    }
+    (a.velocity - b.velocity).dot(delta) / distance
+}
+
+/// Resolves all overlapping ball pairs.
+///
+/// Pairs are relaxed repeatedly (up to [`COLLISION_ITERATIONS`] passes) so
+/// that simultaneous contacts, such as a cue ball pinned inside a tight
+/// cluster, propagate through the whole group.
+fn resolve_ball_collisions(balls: &mut [Ball]) {
+    for _ in 0..COLLISION_ITERATIONS {
+        let mut any_contact = false;
+        for j in 1..balls.len() {
+            let (head, tail) = balls.split_at_mut(j);
+            let b = &mut tail[0];
+            for a in head.iter_mut() {
+                any_contact |= resolve_ball_pair(a, b);
+            }
+        }
+        if !any_contact {
+            break;
+        }
+    }
+}
+
+/// Resolves the contact between two balls if they touch or overlap.
+///
+/// Overlap is removed by pushing the balls apart along the contact normal in
+/// proportion to their inverse masses, and an impulse scaled by
+/// [`BALL_RESTITUTION`] is applied if the balls are approaching each other.
+/// Returns `true` when the balls were in contact.
+fn resolve_ball_pair(a: &mut Ball, b: &mut Ball) -> bool {
+    let delta = b.position - a.position;
+    let min_distance = a.radius + b.radius;
+    let distance_squared = delta.length_squared();
+    if distance_squared > min_distance * min_distance || distance_squared <= f32::EPSILON {
+        return false;
+    }
+
+    let distance = distance_squared.sqrt();
+    let normal = delta / distance;
+    let inv_mass_a = 1.0 / a.mass;
+    let inv_mass_b = 1.0 / b.mass;
+    let inv_mass_sum = inv_mass_a + inv_mass_b;
+
+    // Positional correction: separate the balls so they just touch.
+    let correction = normal * ((min_distance - distance).max(0.0) / inv_mass_sum);
+    a.position -= correction * inv_mass_a;
+    b.position += correction * inv_mass_b;
+
+    apply_impulse(a, b, normal);
+    true
+}
+
+/// Resolves a contact found by its time of impact.
+///
+/// The balls are moved to the contact time, but rounding can leave them a
+/// sliver apart rather than touching. The impulse is applied regardless:
+/// skipping it would leave them approaching, and the same contact would be
+/// found again at once, over and over.
+fn collide(a: &mut Ball, b: &mut Ball) {
+    let delta = b.position - a.position;
+    let distance = delta.length();
+    if distance <= f32::EPSILON {
+        return;
+    }
+    apply_impulse(a, b, delta / distance);
+}
+
+/// Applies the impulse, scaled by [`BALL_RESTITUTION`], that stops two balls
+/// approaching along `normal`, if they are.
+fn apply_impulse(a: &mut Ball, b: &mut Ball, normal: Vector2D) {
+    let approach_speed = (b.velocity - a.velocity).dot(normal);
+    if approach_speed >= 0.0 {
+        return;
+    }
+
+    let inv_mass_a = 1.0 / a.mass;
+    let inv_mass_b = 1.0 / b.mass;
+    let impulse = -(1.0 + BALL_RESTITUTION) * approach_speed / (inv_mass_a + inv_mass_b);
+    a.velocity -= normal * (impulse * inv_mass_a);
+    b.velocity += normal * (impulse * inv_mass_b);
+    apply_contact_friction(a, b, normal, impulse);
+}
+
+/// Applies the friction impulse between the surfaces of two balls in contact.
+///
+/// Whenever the balls carry spin or meet at an angle, their contact points
+/// rub against each other. Friction, capped at [`BALL_FRICTION`] times the
+/// normal impulse, opposes that rubbing: it transfers spin from one ball to
+/// the other and throws the object ball slightly off the line of centres.
+fn apply_contact_friction(a: &mut Ball, b: &mut Ball, normal: Vector2D, normal_impulse: f32) {
+    let n = Vector3D::from_plane(normal);
+    let arm_a = n * a.radius;
+    let arm_b = n * -b.radius;
+    let point_a = Vector3D::from_plane(a.velocity) + a.angular_velocity.cross(arm_a);
+    let point_b = Vector3D::from_plane(b.velocity) + b.angular_velocity.cross(arm_b);
+    let relative = point_a - point_b;
+    let slip = relative - n * relative.dot(n);
+    let slip_speed = slip.length();
+    if slip_speed <= f32::EPSILON {
+        return;
+    }
+
+    // A solid sphere resists slip at its surface with 7/2 of its inverse
+    // mass: once through its mass and 5/2 through its moment of inertia.
+    let inv_effective_mass = 3.5 * (1.0 / a.mass + 1.0 / b.mass);
+    let magnitude = (slip_speed / inv_effective_mass).min(BALL_FRICTION * normal_impulse);
+    let impulse = slip * (magnitude / slip_speed);
+
+    a.velocity -= impulse.plane() / a.mass;
+    b.velocity += impulse.plane() / b.mass;
+    a.angular_velocity += arm_a.cross(-impulse) * (2.5 / (a.mass * a.radius * a.radius));
+    b.angular_velocity += arm_b.cross(impulse) * (2.5 / (b.mass * b.radius * b.radius));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Table;
+
+    #[test]
+    fn separating_balls_do_not_receive_impulse() {
+        let mut a = Ball::new(100.0, 200.0, -50.0, 0.0, 10.0);
+        let mut b = Ball::new(119.0, 200.0, 50.0, 0.0, 10.0);
+        assert!(resolve_ball_pair(&mut a, &mut b));
+        assert!((b.position - a.position).length() >= 20.0 - 1e-4);
+        assert!((a.velocity.x + 50.0).abs() < f32::EPSILON);
+        assert!((b.velocity.x - 50.0).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn sidespin_is_transferred_in_the_opposite_sense() {
+        let mut cue = Ball::new(0.0, 0.0, 300.0, 0.0, 10.0);
+        cue.set_spin(0.0, 0.0, 50.0);
+        let mut object = Ball::new(20.0, 0.0, 0.0, 0.0, 10.0);
+
+        assert!(resolve_ball_pair(&mut cue, &mut object));
+
+        assert!(object.sidespin() < 0.0);
+        assert!(cue.sidespin() < 50.0);
+        // Spin-induced throw pushes the object ball off the line of centres.
+        assert!(object.velocity.y.abs() > 0.1);
+        assert!(object.velocity.y.abs() <= BALL_FRICTION * object.velocity.x + 1e-3);
+    }
+
+    #[test]
+    fn spinless_full_hit_transfers_no_spin() {
+        let mut cue = Ball::new(0.0, 0.0, 300.0, 0.0, 10.0);
+        let mut object = Ball::new(20.0, 0.0, 0.0, 0.0, 10.0);
+
+        assert!(resolve_ball_pair(&mut cue, &mut object));
+
+        assert!(object.angular_velocity.length() < f32::EPSILON);
+        assert!(object.velocity.y.abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn time_of_impact_is_exact_for_head_on_approach() {
+        let a = Ball::new(0.0, 0.0, 100.0, 0.0, 10.0);
+        let b = Ball::new(50.0, 0.0, -50.0, 0.0, 10.0);
+        let time = ball_time_of_impact(&a, &b).unwrap_or(f32::NAN);
+        // Gap of 30 closed at 150 units/s.
+        assert!((time - 0.2).abs() < 1e-6);
+    }
+
+    #[test]
+    fn time_of_impact_misses_when_paths_do_not_cross() {
+        let a = Ball::new(0.0, 0.0, 100.0, 0.0, 10.0);
+        let b = Ball::new(50.0, 25.0, 0.0, 0.0, 10.0);
+        assert!(ball_time_of_impact(&a, &b).is_none());
+    }
+
+    #[test]
+    fn contacts_are_resolved_in_time_order() {
+        let table = Table::new(800.0, 400.0);
+        let balls = [
+            Ball::new(100.0, 200.0, 100.0, 0.0, 10.0),
+            Ball::new(150.0, 200.0, 0.0, 0.0, 10.0),
+            Ball::new(100.0, 300.0, 0.0, 0.0, 10.0),
+        ];
+        let cushions = cushion::cushions(table);
+        let mouths: Vec<PocketMouth> = pocket::mouths(table).collect();
+        let (time, contact) = next_contact(&balls, &cushions, &mouths, 1.0)
+            .unwrap_or((f32::NAN, Contact::Ball(0, 0)));
+        assert_eq!(contact, Contact::Ball(0, 1));
+        assert!((time - 0.3).abs() < 1e-5);
+    }
+
+    #[test]
+    fn coasting_ends_where_stepping_does() {
+        // A rolling ball sent into a ball at rest, which runs on to the rail.
+        let mut cue = Ball::new(100.0, 200.0, 300.0, 0.0, 10.0);
+        cue.set_spin(0.0, 30.0, 0.0);
+        let balls = vec![cue, Ball::new(400.0, 210.0, 0.0, 0.0, 10.0)];
+        let mut stepped = GameState::new(Table::new(800.0, 400.0), balls);
+        let mut coasted = stepped.clone();
+
+        advance(&mut stepped, 10.0);
+        advance_coasting(&mut coasted, 10.0, FRICTION_STEP);
+
+        assert!(!coasted.balls.iter().any(Ball::is_moving));
+        for (a, b) in stepped.balls.iter().zip(&coasted.balls) {
+            assert!((a.position - b.position).length() < 1.0);
+        }
+        let contacts = |state: &GameState| {
+            state
+                .events
+                .iter()
+                .filter(|event| event.kind == crate::EventKind::BallContact)
+                .count()
+        };
+        assert_eq!(contacts(&coasted), contacts(&stepped));
+    }
+}
@@ -0,0 +1,319 @@
+//! Pocket geometry and pocketed-ball records.
+//!
+//! Each pocket is described by its mouth: the line between the points of its
+//! two jaws. The cushions are cut away along the mouth, so a ball can roll
+//! through it, and the ball drops once its centre has travelled past the
+//! shelf behind the mouth and is over the hole.
+//!
+//! Corner pockets cut diagonally across the corners of the table; side
+//! pockets sit in the middle of the two long rails, which are assumed to run
+//! along the table's width.
+
+use std::f32::consts::FRAC_1_SQRT_2;
+
+use wasm_bindgen::prelude::*;
+
+use crate::snapshot::{Decode, Encode, Reader, SnapshotError};
+use crate::{Ball, Table, Vector2D};
+
+/// Identifies one of the six pockets of a pool table.
+///
+/// "Top" is the rail at `y = 0`, "bottom" the rail at `y = height`.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Pocket {
+    /// The corner pocket at `(0, 0)`.
+    TopLeft = 0,
+    /// The side pocket in the middle of the top rail.
+    TopSide = 1,
+    /// The corner pocket at `(width, 0)`.
+    TopRight = 2,
+    /// The corner pocket at `(0, height)`.
+    BottomLeft = 3,
+    /// The side pocket in the middle of the bottom rail.
+    BottomSide = 4,
+    /// The corner pocket at `(width, height)`.
+    BottomRight = 5,
+}
+
+impl Pocket {
+    /// All six pockets, in declaration order.
+    pub const ALL: [Self; 6] = [
+        Self::TopLeft,
+        Self::TopSide,
+        Self::TopRight,
+        Self::BottomLeft,
+        Self::BottomSide,
+        Self::BottomRight,
+    ];
+
+    /// Returns `true` for the four corner pockets.
+    #[must_use]
+    pub const fn is_corner(self) -> bool {
+        !matches!(self, Self::TopSide | Self::BottomSide)
+    }
+}
+
+/// The shape of a pocket: opening, jaws and shelf.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PocketSpec {
+    /// Width of the opening, measured between the points of the two jaws.
+    /// A mouth of zero means the table has no such pockets.
+    pub mouth: f32,
+    /// Angle between each jaw face and the cushion it is cut into, in
+    /// radians. Wider angles open the pocket up towards the hole.
+    pub jaw_angle: f32,
+    /// Distance from the mouth to the edge of the hole. A ball whose centre
+    /// has not crossed the shelf can still hang in the jaws.
+    pub shelf_depth: f32,
+}
+
+#[wasm_bindgen]
+impl PocketSpec {
+    /// Creates a new `PocketSpec`.
+    #[wasm_bindgen(constructor)]
+    #[must_use]
+    pub fn new(mouth: f32, jaw_angle: f32, shelf_depth: f32) -> Self {
+        Self {
+            mouth,
+            jaw_angle,
+            shelf_depth,
+        }
+    }
+
+    /// Returns `true` if the pocket has an opening at all.
+    #[must_use]
+    pub fn is_open(&self) -> bool {
+        self.mouth > 0.0
+    }
+}
+
+impl PocketSpec {
+    /// No pocket: the cushion runs straight through.
+    pub const NONE: Self = Self {
+        mouth: 0.0,
+        jaw_angle: 0.0,
+        shelf_depth: 0.0,
+    };
+
+    /// Regulation corner pocket, in simulation units.
+    pub const CORNER: Self = Self {
+        mouth: 36.5,
+        jaw_angle: 142.0 * std::f32::consts::PI / 180.0,
+        shelf_depth: 12.0,
+    };
+
+    /// Regulation side pocket, in simulation units.
+    pub const SIDE: Self = Self {
+        mouth: 40.5,
+        jaw_angle: 104.0 * std::f32::consts::PI / 180.0,
+        shelf_depth: 3.0,
+    };
+
+    /// Snooker corner pocket, in simulation units. Snooker pockets are
+    /// tighter than pool pockets and barely larger than two balls.
+    pub const SNOOKER_CORNER: Self = Self {
+        mouth: 27.5,
+        jaw_angle: 140.0 * std::f32::consts::PI / 180.0,
+        shelf_depth: 4.0,
+    };
+
+    /// Snooker side pocket, in simulation units.
+    pub const SNOOKER_SIDE: Self = Self {
+        mouth: 33.0,
+        jaw_angle: 105.0 * std::f32::consts::PI / 180.0,
+        shelf_depth: 2.0,
+    };
+
+    /// Returns `true` if every dimension of the pocket is finite.
+    #[must_use]
+    pub const fn is_finite(&self) -> bool {
+        self.mouth.is_finite() && self.jaw_angle.is_finite() && self.shelf_depth.is_finite()
+    }
+}
+
+/// A ball that has dropped into a pocket.
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PocketedBall {
+    /// The ball as it was when it dropped.
+    ball: Ball,
+    /// The pocket it dropped into.
+    pub pocket: Pocket,
+    /// Simulation time at which it dropped, in seconds.
+    pub time: f32,
+}
+
+#[wasm_bindgen]
+impl PocketedBall {
+    /// Returns the ball as it was when it dropped.
+    #[must_use]
+    pub fn ball(&self) -> Ball {
+        self.ball.clone()
+    }
+
+    /// Returns the id of the ball that dropped.
+    #[must_use]
+    pub fn ball_id(&self) -> u32 {
+        self.ball.id
+    }
+}
+
+impl PocketedBall {
+    /// Records `ball` dropping into `pocket` at `time`.
+    #[must_use]
+    pub const fn new(ball: Ball, pocket: Pocket, time: f32) -> Self {
+        Self { ball, pocket, time }
+    }
+}
+
+/// A pocket mouth laid out on a particular table.
+#[derive(Clone, Copy, Debug)]
+pub struct PocketMouth {
+    /// The pocket this mouth opens into.
+    pub pocket: Pocket,
+    /// The point of the first jaw.
+    pub start: Vector2D,
+    /// The point of the second jaw.
+    pub end: Vector2D,
+    /// Unit normal of the mouth, pointing into the pocket.
+    pub normal: Vector2D,
+    /// The shape of the pocket.
+    pub spec: PocketSpec,
+}
+
+impl PocketMouth {
+    /// Returns how far `point` lies past the mouth, towards the hole.
+    #[must_use]
+    pub fn depth(&self, point: Vector2D) -> f32 {
+        (point - self.start).dot(self.normal)
+    }
+
+    /// Returns where `point` projects onto the mouth, from `0` at the first
+    /// jaw to `1` at the second.
+    #[must_use]
+    pub fn lateral(&self, point: Vector2D) -> f32 {
+        let span = self.end - self.start;
+        (point - self.start).dot(span) / span.length_squared()
+    }
+
+    /// Returns the time until `ball` drops through this mouth, if its
+    /// current heading takes it over the hole.
+    #[must_use]
+    pub fn drop_time(&self, ball: &Ball) -> Option<f32> {
+        let speed = ball.velocity.dot(self.normal);
+        if speed <= 0.0 {
+            return None;
+        }
+        let time = ((self.spec.shelf_depth - self.depth(ball.position)) / speed).max(0.0);
+
+        // Allow the centre to wander up to a ball radius past either jaw
+        // while falling; anything further out was not heading for the hole.
+        let slack = ball.radius / (self.end - self.start).length();
+        let lateral = self.lateral(ball.position + ball.velocity * time);
+        (-slack..=1.0 + slack).contains(&lateral).then_some(time)
+    }
+}
+
+/// Returns the mouths of all open pockets on `table`.
+pub fn mouths(table: Table) -> impl Iterator<Item = PocketMouth> {
+    Pocket::ALL
+        .into_iter()
+        .filter_map(move |pocket| mouth(table, pocket))
+}
+
+/// Lays out the mouth of `pocket` on `table`, if that pocket is open.
+fn mouth(table: Table, pocket: Pocket) -> Option<PocketMouth> {
+    let spec = if pocket.is_corner() {
+        table.corner_pockets
+    } else {
+        table.side_pockets
+    };
+    if !spec.is_open() {
+        return None;
+    }
+
+    let (w, h) = (table.width, table.height);
+    let leg = spec.mouth * FRAC_1_SQRT_2;
+    let half = spec.mouth * 0.5;
+    let diagonal = FRAC_1_SQRT_2;
+    let (start, end, normal) = match pocket {
+        Pocket::TopLeft => ((leg, 0.0), (0.0, leg), (-diagonal, -diagonal)),
+        Pocket::TopSide => ((w * 0.5 - half, 0.0), (w * 0.5 + half, 0.0), (0.0, -1.0)),
+        Pocket::TopRight => ((w - leg, 0.0), (w, leg), (diagonal, -diagonal)),
+        Pocket::BottomLeft => ((0.0, h - leg), (leg, h), (-diagonal, diagonal)),
+        Pocket::BottomSide => ((w * 0.5 - half, h), (w * 0.5 + half, h), (0.0, 1.0)),
+        Pocket::BottomRight => ((w - leg, h), (w, h - leg), (diagonal, diagonal)),
+    };
+
+    Some(PocketMouth {
+        pocket,
+        start: Vector2D::new(start.0, start.1),
+        end: Vector2D::new(end.0, end.1),
+        normal: Vector2D::new(normal.0, normal.1),
+        spec,
+    })
+}
+
+impl Encode for PocketedBall {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.ball.encode(out);
+        self.pocket.encode(out);
+        self.time.encode(out);
+    }
+}
+
+impl Decode for PocketedBall {
+    fn decode(input: &mut Reader<'_>) -> Result<Self, SnapshotError> {
+        Ok(Self {
+            ball: input.read()?,
+            pocket: input.read()?,
+            time: input.read()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pocketless_table_has_no_mouths() {
+        assert_eq!(mouths(Table::new(800.0, 400.0)).count(), 0);
+        assert_eq!(mouths(Table::pool(800.0, 400.0)).count(), 6);
+    }
+
+    #[test]
+    fn corner_mouth_has_regulation_width() {
+        let table = Table::pool(800.0, 400.0);
+        for mouth in mouths(table) {
+            let width = (mouth.end - mouth.start).length();
+            assert!((width - mouth.spec.mouth).abs() < 1e-3);
+            // The normal points away from the middle of the table.
+            let centre = Vector2D::new(400.0, 200.0);
+            assert!(mouth.depth(centre) < 0.0);
+        }
+    }
+
+    #[test]
+    fn ball_heading_into_mouth_drops_after_shelf() {
+        let table = Table::pool(800.0, 400.0);
+        let ball = Ball::new(400.0, 30.0, 0.0, -100.0, 10.0);
+        let time = mouths(table)
+            .find(|m| m.pocket == Pocket::TopSide)
+            .and_then(|m| m.drop_time(&ball))
+            .unwrap_or(f32::NAN);
+        // 30 units to the mouth plus the shelf, at 100 units per second.
+        assert!((time - (30.0 + PocketSpec::SIDE.shelf_depth) / 100.0).abs() < 1e-5);
+
+        let wide = Ball::new(300.0, 30.0, 0.0, -100.0, 10.0);
+        assert!(mouths(table)
+            .find(|m| m.pocket == Pocket::TopSide)
+            .and_then(|m| m.drop_time(&wide))
+            .is_none());
+    }
+}
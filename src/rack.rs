@@ -0,0 +1,329 @@
+//! Regulation racks for the standard games.
+//!
+//! Pool racks sit with their apex ball on the foot spot, a quarter of the
+//! table length from the foot rail, and the cue ball starts on the head spot
+//! in the kitchen. Snooker places the reds in a triangle behind the pink and
+//! the colours on their spots, with the cue ball in the D. Carom starts
+//! from the fixed three-ball opening position.
+//!
+//! Racks are returned as balls ordered by number, cue ball first, so that
+//! [`GameState::new`](crate::GameState::new) gives every ball its number as
+//! its id. Positions that the rules leave open are filled by a seeded
+//! [`Rng`], and a non-zero `gap` leaves each ball up to that far from its
+//! neighbours, as a real rack rarely freezes every ball.
+
+use std::f32::consts::TAU;
+
+use crate::math;
+use crate::rng::Rng;
+use crate::{
+    Ball, BallKind, Table, Vector2D, POOL_BALL_RADIUS, SNOOKER_BALL_RADIUS, UNITS_PER_METER,
+};
+
+/// Distance of the black spot from the top cushion, as a fraction of the
+/// table length.
+const BLACK_SPOT: f32 = 324.0 / 3569.0;
+
+/// Distance of the breaker's ball from the head spot in the carom opening
+/// position (15.2 cm).
+const CAROM_BREAK_OFFSET: f32 = 0.152 * UNITS_PER_METER;
+
+/// Ball layout of a triangle, row by row from the apex.
+const TRIANGLE: [u8; 5] = [1, 2, 3, 4, 5];
+
+/// Ball layout of a nine-ball diamond, row by row from the apex.
+const DIAMOND: [u8; 5] = [1, 2, 3, 2, 1];
+
+/// Racks the fifteen balls of eight-ball.
+///
+/// The 8 sits in the middle of the third row with a solid and a stripe in
+/// the two back corners; every other position is random.
+#[must_use]
+pub fn eight_ball(table: Table, rng: &mut Rng, gap: f32) -> Vec<Ball> {
+    let mut solids: Vec<u32> = (1..8).collect();
+    let mut stripes: Vec<u32> = (9..16).collect();
+    rng.shuffle(&mut solids);
+    rng.shuffle(&mut stripes);
+    let mut corners = [solids[0], stripes[0]];
+    rng.shuffle(&mut corners);
+    let mut rest: Vec<u32> = solids[1..].iter().chain(&stripes[1..]).copied().collect();
+    rng.shuffle(&mut rest);
+
+    let mut rest = rest.into_iter();
+    let numbers = (0..15).map(|slot| match slot {
+        4 => 8,
+        10 => corners[0],
+        14 => corners[1],
+        _ => rest.next().unwrap_or_default(),
+    });
+    pool_rack(table, rng, gap, &TRIANGLE, numbers)
+}
+
+/// Racks the nine balls of nine-ball in a diamond, with the 1 at the apex and
+/// the 9 in the middle.
+#[must_use]
+pub fn nine_ball(table: Table, rng: &mut Rng, gap: f32) -> Vec<Ball> {
+    let numbers = apex_and_centre(rng, 9);
+    pool_rack(table, rng, gap, &DIAMOND, numbers)
+}
+
+/// Racks the ten balls of ten-ball in a triangle, with the 1 at the apex and
+/// the 10 in the middle of the third row.
+#[must_use]
+pub fn ten_ball(table: Table, rng: &mut Rng, gap: f32) -> Vec<Ball> {
+    let numbers = apex_and_centre(rng, 10);
+    pool_rack(table, rng, gap, &TRIANGLE[..4], numbers)
+}
+
+/// Racks the fifteen balls of straight pool in a triangle, in random order.
+#[must_use]
+pub fn straight_pool(table: Table, rng: &mut Rng, gap: f32) -> Vec<Ball> {
+    let mut numbers: Vec<u32> = (1..16).collect();
+    rng.shuffle(&mut numbers);
+    pool_rack(table, rng, gap, &TRIANGLE, numbers.into_iter())
+}
+
+/// Returns the fifteen positions of a pool triangle on the foot spot, apex
+/// first and row by row, with neighbouring balls `gap` apart.
+#[must_use]
+pub fn triangle_spots(table: Table, gap: f32) -> Vec<Vector2D> {
+    let foot_spot = Vector2D::new(table.width * 0.75, table.height * 0.5);
+    triangle(foot_spot, POOL_BALL_RADIUS, gap, &TRIANGLE)
+}
+
+/// Places the fifteen reds, the six colours on their spots and the cue ball
+/// in the D.
+///
+/// Balls are numbered cue ball, reds `1..=15`, then yellow, green, brown,
+/// blue, pink and black as `16..=21`.
+#[must_use]
+pub fn snooker(table: Table, rng: &mut Rng, gap: f32) -> Vec<Ball> {
+    let r = SNOOKER_BALL_RADIUS;
+    let middle = table.height * 0.5;
+    let (baulk, d) = table.baulk();
+
+    let cue = Vector2D::new(baulk - d * 0.5, middle + d * 0.5);
+    let mut balls = vec![Ball::snooker(BallKind::Cue, cue.x, cue.y)];
+    let apex = Vector2D::new(table.width * 0.75 + 2.0 * r + gap, middle);
+    balls.extend(triangle(apex, r, gap, &TRIANGLE).into_iter().map(|spot| {
+        let spot = jitter(spot, rng, gap);
+        Ball::snooker(BallKind::Red, spot.x, spot.y)
+    }));
+    balls.extend(BallKind::COLOURS.into_iter().filter_map(|kind| {
+        colour_spot(table, kind).map(|spot| Ball::snooker(kind, spot.x, spot.y))
+    }));
+    balls
+}
+
+/// Returns the spot of a snooker colour, or `None` for other kinds of ball.
+///
+/// Yellow, brown and green sit on the baulk line, the brown in the middle
+/// and the others where the D meets it. The blue is on the centre spot, the pink
+/// halfway between it and the top cushion, and the black near the top
+/// cushion.
+#[must_use]
+pub fn colour_spot(table: Table, kind: BallKind) -> Option<Vector2D> {
+    let (length, middle) = (table.width, table.height * 0.5);
+    let (baulk, d) = table.baulk();
+    let spot = match kind {
+        BallKind::Yellow => Vector2D::new(baulk, middle + d),
+        BallKind::Green => Vector2D::new(baulk, middle - d),
+        BallKind::Brown => Vector2D::new(baulk, middle),
+        BallKind::Blue => Vector2D::new(length * 0.5, middle),
+        BallKind::Pink => Vector2D::new(length * 0.75, middle),
+        BallKind::Black => Vector2D::new(length * (1.0 - BLACK_SPOT), middle),
+        _ => return None,
+    };
+    Some(spot)
+}
+
+/// Sets up the three balls of carom: the red on the foot spot, the yellow
+/// on the head spot and the breaker's white on the head string beside it.
+///
+/// The balls are listed white, yellow, red, so they get ids 0, 1 and 2.
+#[must_use]
+pub fn carom(table: Table) -> Vec<Ball> {
+    let (head, foot, middle) = (table.width * 0.25, table.width * 0.75, table.height * 0.5);
+    vec![
+        Ball::carom(BallKind::Cue, head, middle + CAROM_BREAK_OFFSET),
+        Ball::carom(BallKind::SecondCue, head, middle),
+        Ball::carom(BallKind::CaromRed, foot, middle),
+    ]
+}
+
+/// Puts the 1 at the apex and `centre` in the middle of a rack of balls
+/// `1..=centre`, shuffling the others.
+fn apex_and_centre(rng: &mut Rng, centre: u32) -> impl Iterator<Item = u32> {
+    let mut rest: Vec<u32> = (2..centre).collect();
+    rng.shuffle(&mut rest);
+    let mut rest = rest.into_iter();
+    (0..centre).map(move |slot| match slot {
+        0 => 1,
+        4 => centre,
+        _ => rest.next().unwrap_or_default(),
+    })
+}
+
+/// Lays out a pool rack with its apex on the foot spot and the cue ball on
+/// the head spot. `numbers` gives the ball in each slot, row by row.
+fn pool_rack(
+    table: Table,
+    rng: &mut Rng,
+    gap: f32,
+    rows: &[u8],
+    numbers: impl Iterator<Item = u32>,
+) -> Vec<Ball> {
+    let middle = table.height * 0.5;
+    let foot_spot = Vector2D::new(table.width * 0.75, middle);
+
+    let mut slots: Vec<(u32, Vector2D)> = numbers
+        .zip(triangle(foot_spot, POOL_BALL_RADIUS, gap, rows))
+        .map(|(number, spot)| (number, jitter(spot, rng, gap)))
+        .collect();
+    slots.push((0, Vector2D::new(table.width * 0.25, middle)));
+    slots.sort_unstable_by_key(|(number, _)| *number);
+
+    slots
+        .into_iter()
+        .map(|(number, spot)| Ball::pool(number, spot.x, spot.y))
+        .collect()
+}
+
+/// Returns the centres of a rack of balls of radius `r` whose apex is at
+/// `apex` and whose rows, of the given sizes, run towards `+x`.
+///
+/// Neighbouring centres are `2r + gap` apart, leaving room for each ball to
+/// be nudged by up to half the gap without touching another.
+fn triangle(apex: Vector2D, r: f32, gap: f32, rows: &[u8]) -> Vec<Vector2D> {
+    let spacing = 2.0 * r + gap;
+    let row_step = spacing * 3.0_f32.sqrt() * 0.5;
+    let mut spots = Vec::new();
+    let mut x = apex.x;
+    for &size in rows {
+        let first = apex.y - spacing * 0.5 * f32::from(size.saturating_sub(1));
+        spots.extend((0..size).map(|j| Vector2D::new(x, first + f32::from(j) * spacing)));
+        x += row_step;
+    }
+    spots
+}
+
+/// Moves `spot` in a random direction by up to half of `gap`.
+fn jitter(spot: Vector2D, rng: &mut Rng, gap: f32) -> Vector2D {
+    if gap <= 0.0 {
+        return spot;
+    }
+    let (sin, cos) = math::sin_cos(rng.next_f32() * TAU);
+    let distance = gap * 0.5 * rng.next_f32().sqrt();
+    spot + Vector2D::new(cos, sin) * distance
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn no_overlaps(balls: &[Ball]) -> bool {
+        balls.iter().enumerate().all(|(i, a)| {
+            balls[i + 1..]
+                .iter()
+                .all(|b| (b.position - a.position).length() >= a.radius + b.radius - 1e-3)
+        })
+    }
+
+    #[test]
+    fn eight_ball_rack_follows_the_rules() {
+        let table = Table::pool(800.0, 400.0);
+        let balls = eight_ball(table, &mut Rng::new(3), 0.0);
+
+        assert_eq!(balls.len(), 16);
+        assert!(no_overlaps(&balls));
+        assert!(balls.iter().zip(0..).all(|(ball, n)| ball.number == n));
+        assert!(balls[0].is_cue() && balls[8].kind == BallKind::Eight);
+        // The 8 is in the middle of the third row.
+        let third_row = 600.0 + 2.0 * POOL_BALL_RADIUS * 3.0_f32.sqrt();
+        assert!((balls[8].position - Vector2D::new(third_row, 200.0)).length() < 1e-3);
+        // One solid and one stripe in the back corners.
+        let back = 600.0 + 4.0 * POOL_BALL_RADIUS * 3.0_f32.sqrt();
+        let corners: Vec<usize> = (1..16)
+            .filter(|&n| (balls[n].x() - back).abs() < 1e-3)
+            .filter(|&n| (balls[n].y() - 200.0).abs() > 3.0 * POOL_BALL_RADIUS)
+            .collect();
+        assert_eq!(corners.len(), 2);
+        assert!(corners.iter().any(|&n| n < 8) && corners.iter().any(|&n| n > 8));
+        // The cue ball waits in the kitchen.
+        assert!(balls[0].x() <= 200.0);
+    }
+
+    #[test]
+    fn nine_and_ten_ball_racks_put_the_money_ball_in_the_middle() {
+        let table = Table::pool(800.0, 400.0);
+        let nine = nine_ball(table, &mut Rng::new(5), 0.0);
+        let ten = ten_ball(table, &mut Rng::new(5), 0.0);
+
+        assert_eq!(nine.len(), 10);
+        assert_eq!(ten.len(), 11);
+        assert!(no_overlaps(&nine) && no_overlaps(&ten));
+        assert!((nine[1].position - Vector2D::new(600.0, 200.0)).length() < 1e-3);
+        assert!((ten[1].position - Vector2D::new(600.0, 200.0)).length() < 1e-3);
+        assert!((nine[9].y() - 200.0).abs() < 1e-3);
+        assert!((ten[10].y() - 200.0).abs() < 1e-3);
+        // Nine-ball is a diamond: the last ball sits alone on the centre line.
+        let far = nine.iter().skip(1).map(Ball::x).fold(0.0, f32::max);
+        let last = nine.iter().filter(|b| (b.x() - far).abs() < 1e-3).count();
+        assert_eq!(last, 1);
+    }
+
+    #[test]
+    fn same_seed_gives_same_rack() {
+        let table = Table::pool(800.0, 400.0);
+        let positions = |seed| -> Vec<(f32, f32)> {
+            eight_ball(table, &mut Rng::new(seed), 0.5)
+                .iter()
+                .map(|b| (b.x(), b.y()))
+                .collect()
+        };
+        assert_eq!(positions(11), positions(11));
+        assert_ne!(positions(11), positions(12));
+    }
+
+    #[test]
+    fn gaps_never_make_balls_overlap() {
+        let table = Table::pool(800.0, 400.0);
+        for seed in 0..20 {
+            let balls = eight_ball(table, &mut Rng::new(seed), 1.0);
+            assert!(no_overlaps(&balls));
+        }
+    }
+
+    #[test]
+    fn snooker_colours_sit_on_their_spots() {
+        let table = Table::new(1124.0, 562.0);
+        let balls = snooker(table, &mut Rng::new(0), 0.0);
+
+        assert_eq!(balls.len(), 22);
+        assert!(no_overlaps(&balls));
+        assert!(balls[1..16].iter().all(|ball| ball.kind == BallKind::Red));
+        assert_eq!(balls[21].kind, BallKind::Black);
+        assert!((balls[19].position - Vector2D::new(562.0, 281.0)).length() < 1e-3);
+        assert!((balls[20].x() - 843.0).abs() < 1e-3);
+        assert!(balls[21].x() > balls[20].x());
+        // The reds all lie between the pink and the black.
+        assert!(balls[1..16]
+            .iter()
+            .all(|red| red.x() > balls[20].x() && red.x() < balls[21].x()));
+        assert!(table.in_d(balls[0].position));
+    }
+
+    #[test]
+    fn carom_opening_position() {
+        let table = Table::carom();
+        let balls = carom(table);
+
+        assert_eq!(balls.len(), 3);
+        assert!(balls[0].is_cue() && balls[1].kind == BallKind::SecondCue);
+        let middle = table.height * 0.5;
+        assert!((balls[1].position - Vector2D::new(table.width * 0.25, middle)).length() < 1e-3);
+        assert!((balls[2].position - Vector2D::new(table.width * 0.75, middle)).length() < 1e-3);
+        assert!((balls[0].x() - balls[1].x()).abs() < 1e-3);
+        assert!(!table.corner_pockets.is_open() && !table.side_pockets.is_open());
+    }
+}
@@ -0,0 +1,516 @@
+//! Shot replays.
+//!
+//! A [`Replay`] records shots as they are struck: the state each was played
+//! from, which carries the balls, the table and the rules' view of the game,
+//! the [`GameState::state_hash`] of that state, and the [`CueStroke`].
+//! Playing a shot back restores the state, strikes it again and runs it
+//! through [`tick`] with the replay's fixed step until the balls stop. Nothing
+//! in the simulation is random, so a shot plays back to the same motion every
+//! time, and to the motion of the live game if that was ticked with the same
+//! step.
+//!
+//! For scrubbing, [`Replay::frames`] samples a shot at a fixed frame
+//! interval, so a frontend can seek to any frame without simulating.
+//!
+//! Replays are stored in the binary layout of
+//! [snapshots](GameState::to_bytes), under their own signature `PSRP` and
+//! [`REPLAY_VERSION`].
+
+use wasm_bindgen::prelude::*;
+
+use crate::snapshot::{seal, unseal, Decode, Encode, Reader, SnapshotError};
+use crate::{tick, Ball, CueStroke, GameState, Vector2D};
+
+/// Format version written by [`Replay::to_bytes`].
+pub const REPLAY_VERSION: u16 = 1;
+
+/// The bytes every replay file starts with.
+const SIGNATURE: [u8; 4] = *b"PSRP";
+
+/// Step used by a replay created with a step outside [`STEPS`], in seconds.
+const DEFAULT_STEP: f32 = 1.0 / 120.0;
+
+/// Steps a replay plays back in, in seconds. Shorter steps would take too
+/// many ticks to play a shot back, and longer ones would miss contacts.
+pub const STEPS: std::ops::RangeInclusive<f32> = 1e-4..=0.1;
+
+/// Longest a shot is played back for, in seconds.
+const MAX_SHOT_TIME: f32 = 120.0;
+
+/// A shot as it was struck.
+#[derive(Clone, Debug)]
+struct RecordedShot {
+    /// Hash of `state`.
+    state_hash: u64,
+    /// The state the shot was played from, without its events.
+    state: GameState,
+    /// The stroke the shot was played with.
+    stroke: CueStroke,
+}
+
+impl Encode for RecordedShot {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.state_hash.encode(out);
+        self.state.encode(out);
+        self.stroke.encode(out);
+    }
+}
+
+impl Decode for RecordedShot {
+    fn decode(input: &mut Reader<'_>) -> Result<Self, SnapshotError> {
+        let shot = Self {
+            state_hash: input.read()?,
+            state: input.read()?,
+            stroke: input.read()?,
+        };
+        if shot.state.state_hash() != shot.state_hash {
+            return Err(SnapshotError::Invalid);
+        }
+        Ok(shot)
+    }
+}
+
+/// A recording of shots that can be played back exactly.
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct Replay {
+    /// Simulation time, in seconds, per tick of playback.
+    step: f32,
+    /// The shots recorded, in order.
+    shots: Vec<RecordedShot>,
+}
+
+#[wasm_bindgen]
+impl Replay {
+    /// Creates an empty replay that plays shots back in ticks of `step`
+    /// seconds. A step outside 0.0001 to 0.1 s, or not a number, is replaced
+    /// by 1/120 s.
+    #[wasm_bindgen(constructor)]
+    #[must_use]
+    pub fn new(step: f32) -> Self {
+        Self {
+            step: if STEPS.contains(&step) {
+                step
+            } else {
+                DEFAULT_STEP
+            },
+            shots: Vec::new(),
+        }
+    }
+
+    /// Returns the simulation time, in seconds, per tick of playback.
+    #[must_use]
+    pub fn step(&self) -> f32 {
+        self.step
+    }
+
+    /// Strikes the cue ball of `state` with `stroke`, as
+    /// [`GameState::strike`] does, and records the shot if it was allowed.
+    /// Returns `false`, recording nothing, if it was not.
+    pub fn record(&mut self, state: &mut GameState, stroke: &CueStroke) -> bool {
+        let mut before = state.clone();
+        if !state.strike(stroke) {
+            return false;
+        }
+        before.events.clear();
+        self.shots.push(RecordedShot {
+            state_hash: before.state_hash(),
+            state: before,
+            stroke: *stroke,
+        });
+        true
+    }
+
+    /// Returns the number of shots recorded.
+    #[must_use]
+    pub fn shots_len(&self) -> usize {
+        self.shots.len()
+    }
+
+    /// Returns the hash of the state the shot at `index` was played from.
+    /// In JS this is a `BigInt`.
+    ///
+    /// Panics in Rust if out of bounds; when called from JS this will surface
+    /// as a trap, so callers must bounds-check first.
+    #[must_use]
+    pub fn state_hash(&self, index: usize) -> u64 {
+        self.shots[index].state_hash
+    }
+
+    /// Returns the stroke the shot at `index` was played with.
+    ///
+    /// Panics in Rust if out of bounds; when called from JS this will surface
+    /// as a trap, so callers must bounds-check first.
+    #[must_use]
+    pub fn stroke(&self, index: usize) -> CueStroke {
+        self.shots[index].stroke
+    }
+
+    /// Returns the state the shot at `index` was played from, rules
+    /// included, just before it was struck.
+    ///
+    /// Panics in Rust if out of bounds; when called from JS this will surface
+    /// as a trap, so callers must bounds-check first.
+    #[must_use]
+    pub fn initial_state(&self, index: usize) -> GameState {
+        self.shots[index].state.clone()
+    }
+
+    /// Plays the shot at `index` back and returns the state once every ball
+    /// has come to rest, with the shot judged by the rules and its events
+    /// recorded.
+    ///
+    /// Panics in Rust if out of bounds; when called from JS this will surface
+    /// as a trap, so callers must bounds-check first.
+    #[must_use]
+    pub fn play(&self, index: usize) -> GameState {
+        self.run(index, |_, _| {})
+    }
+
+    /// Plays the shot at `index` back and samples the balls every
+    /// `interval` seconds, from the moment of the strike until they have
+    /// all come to rest. Between ticks of playback the positions are
+    /// interpolated. An interval shorter than the replay's step, or not
+    /// finite, is replaced by the step.
+    ///
+    /// Panics in Rust if out of bounds; when called from JS this will surface
+    /// as a trap, so callers must bounds-check first.
+    #[must_use]
+    pub fn frames(&self, index: usize, interval: f32) -> ReplayFrames {
+        let interval = if interval.is_finite() {
+            interval.max(self.step)
+        } else {
+            self.step
+        };
+        let ball_ids = self.shots[index].state.ball_ids();
+        let mut frames = ReplayFrames {
+            interval,
+            ball_ids,
+            positions: Vec::new(),
+            on_table: Vec::new(),
+            frames_len: 0,
+        };
+        // The time and positions of the previous tick, and the number of the
+        // next frame.
+        let mut previous: Option<(f32, Vec<Option<Vector2D>>)> = None;
+        let mut frame = 0.0_f32;
+        let end = self.run(index, |state, elapsed| {
+            let current = frames.sample(state);
+            let Some((before, last)) = &previous else {
+                frames.push(&current);
+                frame += 1.0;
+                previous = Some((elapsed, current));
+                return;
+            };
+            loop {
+                let time = frame * interval;
+                if time > elapsed {
+                    break;
+                }
+                let alpha = (time - before) / (elapsed - before);
+                let between: Vec<Option<Vector2D>> = last
+                    .iter()
+                    .zip(&current)
+                    .map(|(&from, &to)| Some(from? + (to? - from?) * alpha))
+                    .collect();
+                frames.push(&between);
+                frame += 1.0;
+            }
+            previous = Some((elapsed, current));
+        });
+        // One more frame shows the balls where they came to rest.
+        let rest = frames.sample(&end);
+        frames.push(&rest);
+        frames
+    }
+
+    /// Returns this replay in its binary file format at
+    /// [`REPLAY_VERSION`]. In JS this is a `Uint8Array`.
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        seal(SIGNATURE, REPLAY_VERSION, self)
+    }
+
+    /// Reads a replay back from a file written by [`Replay::to_bytes`]. In
+    /// JS, a file that cannot be read throws an `Error` saying why.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`SnapshotError`] if the bytes are not a whole, intact
+    /// replay of a version this build reads, or if a shot's recorded hash
+    /// does not match the state it was played from.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        unseal(bytes, SIGNATURE, REPLAY_VERSION)
+    }
+}
+
+impl Replay {
+    /// Plays the shot at `index` back, calling `each` with the state and
+    /// the time since the strike just after the strike and after every
+    /// tick, and returns the state it ends in.
+    fn run(&self, index: usize, mut each: impl FnMut(&GameState, f32)) -> GameState {
+        let shot = &self.shots[index];
+        let mut state = shot.state.clone();
+        if !state.strike(&shot.stroke) {
+            return state;
+        }
+        let mut elapsed = 0.0;
+        each(&state, elapsed);
+        while state.balls.iter().any(Ball::is_moving) && elapsed < MAX_SHOT_TIME {
+            tick(&mut state, self.step);
+            elapsed += self.step;
+            each(&state, elapsed);
+        }
+        state
+    }
+}
+
+impl Encode for Replay {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.step.encode(out);
+        self.shots.encode(out);
+    }
+}
+
+impl Decode for Replay {
+    fn decode(input: &mut Reader<'_>) -> Result<Self, SnapshotError> {
+        let step: f32 = input.read()?;
+        if !STEPS.contains(&step) {
+            return Err(SnapshotError::Invalid);
+        }
+        Ok(Self {
+            step,
+            shots: input.read()?,
+        })
+    }
+}
+
+/// The balls of a played-back shot, sampled at a fixed interval.
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct ReplayFrames {
+    /// Time between frames, in seconds.
+    interval: f32,
+    /// Ids of the balls sampled, in the order they appear in each frame.
+    ball_ids: Vec<u32>,
+    /// Positions of the balls, frame by frame.
+    positions: Vec<Vector2D>,
+    /// Whether each ball was on the table, frame by frame.
+    on_table: Vec<bool>,
+    /// Number of frames.
+    frames_len: usize,
+}
+
+#[wasm_bindgen]
+impl ReplayFrames {
+    /// Returns the time between frames, in seconds.
+    #[must_use]
+    pub fn interval(&self) -> f32 {
+        self.interval
+    }
+
+    /// Returns the number of frames.
+    #[must_use]
+    pub fn frames_len(&self) -> usize {
+        self.frames_len
+    }
+
+    /// Returns the ids of the balls sampled: those on the table when the
+    /// shot was struck, in the order they appear in each frame.
+    #[must_use]
+    pub fn ball_ids(&self) -> Vec<u32> {
+        self.ball_ids.clone()
+    }
+
+    /// Returns the positions of the balls in every frame, as flat `x, y`
+    /// pairs, frame after frame. A ball that has dropped keeps the last
+    /// position it had on the table.
+    #[must_use]
+    pub fn positions(&self) -> Vec<f32> {
+        flatten(&self.positions)
+    }
+
+    /// Returns the positions of the balls in the frame at `index`, as flat
+    /// `x, y` pairs.
+    ///
+    /// Panics in Rust if out of bounds; when called from JS this will surface
+    /// as a trap, so callers must bounds-check first.
+    #[must_use]
+    pub fn frame(&self, index: usize) -> Vec<f32> {
+        let balls = self.ball_ids.len();
+        flatten(&self.positions[index * balls..(index + 1) * balls])
+    }
+
+    /// Returns, for each ball, whether it is on the table in the frame at
+    /// `index`: 1 if it is, 0 once it has dropped.
+    ///
+    /// Panics in Rust if out of bounds; when called from JS this will surface
+    /// as a trap, so callers must bounds-check first.
+    #[must_use]
+    pub fn on_table(&self, index: usize) -> Vec<u8> {
+        let balls = self.ball_ids.len();
+        self.on_table[index * balls..(index + 1) * balls]
+            .iter()
+            .map(|&on| u8::from(on))
+            .collect()
+    }
+}
+
+impl ReplayFrames {
+    /// Returns the position of each sampled ball in `state`, if it is on
+    /// the table.
+    fn sample(&self, state: &GameState) -> Vec<Option<Vector2D>> {
+        self.ball_ids
+            .iter()
+            .map(|&id| state.find_ball(id).map(|ball| ball.position))
+            .collect()
+    }
+
+    /// Adds a frame with the balls at `positions`. Balls that are off the
+    /// table keep their position from the frame before.
+    fn push(&mut self, positions: &[Option<Vector2D>]) {
+        let balls = self.ball_ids.len();
+        let previous = self.positions.len().checked_sub(balls);
+        for (index, position) in positions.iter().enumerate() {
+            let last = previous.map_or(Vector2D::ZERO, |start| self.positions[start + index]);
+            self.positions.push(position.unwrap_or(last));
+            self.on_table.push(position.is_some());
+        }
+        self.frames_len += 1;
+    }
+}
+
+/// Flattens `points` into `x, y` pairs.
+fn flatten(points: &[Vector2D]) -> Vec<f32> {
+    points.iter().flat_map(|point| [point.x, point.y]).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Table;
+
+    const STEP: f32 = 1.0 / 120.0;
+
+    /// Ticks `state` with [`STEP`] until every ball is at rest.
+    fn settle(state: &mut GameState) {
+        while state.balls().iter().any(Ball::is_moving) {
+            tick(state, STEP);
+        }
+    }
+
+    /// A replay of an eight-ball break and the shot after it.
+    fn two_shots() -> (Replay, GameState) {
+        let mut state = GameState::eight_ball(&Table::pool(800.0, 400.0), 11, 0.0);
+        let mut replay = Replay::new(STEP);
+        assert!(replay.record(&mut state, &CueStroke::new(0.02, 900.0, 0.1, -0.2, 0.0)));
+        settle(&mut state);
+        assert!(state.is_on_table(state.cue_ball_id().unwrap()));
+        assert!(replay.record(&mut state, &CueStroke::new(2.5, 500.0, 0.0, 0.3, 0.0)));
+        settle(&mut state);
+        (replay, state)
+    }
+
+    #[test]
+    fn playback_reproduces_the_live_shot() {
+        let (replay, live) = two_shots();
+
+        assert_eq!(replay.shots_len(), 2);
+        assert_eq!(replay.play(1).state_hash(), live.state_hash());
+        assert_eq!(replay.play(1).to_bytes(), replay.play(1).to_bytes());
+        // Nothing happened between the shots, so the first shot ends where
+        // the second starts.
+        assert_eq!(replay.play(0).state_hash(), replay.state_hash(1));
+        assert_eq!(replay.initial_state(1).state_hash(), replay.state_hash(1));
+    }
+
+    #[test]
+    fn replay_file_round_trips() {
+        let (replay, _) = two_shots();
+        let bytes = replay.to_bytes();
+
+        let loaded = Replay::from_bytes(&bytes).unwrap();
+
+        assert_eq!(&bytes[..4], b"PSRP");
+        assert_eq!(loaded.to_bytes(), bytes);
+        assert!((loaded.step() - STEP).abs() < f32::EPSILON);
+        assert_eq!(loaded.play(0).state_hash(), replay.play(0).state_hash());
+
+        let mut corrupted = bytes;
+        corrupted[40] ^= 0x01;
+        assert_eq!(
+            Replay::from_bytes(&corrupted).unwrap_err(),
+            SnapshotError::Checksum
+        );
+        let snapshot = replay.initial_state(0).to_bytes();
+        assert_eq!(
+            Replay::from_bytes(&snapshot).unwrap_err(),
+            SnapshotError::NotASnapshot
+        );
+
+        let mut tampered = replay.clone();
+        tampered.shots[1].state_hash ^= 1;
+        assert_eq!(
+            Replay::from_bytes(&tampered.to_bytes()).unwrap_err(),
+            SnapshotError::Invalid
+        );
+
+        let mut tiny = replay;
+        tiny.step = 1e-9;
+        assert_eq!(
+            Replay::from_bytes(&tiny.to_bytes()).unwrap_err(),
+            SnapshotError::Invalid
+        );
+    }
+
+    #[test]
+    fn steps_and_intervals_stay_in_range() {
+        for step in [0.0, -1.0, 1e-9, 5.0, f32::NAN, f32::INFINITY] {
+            assert!((Replay::new(step).step() - DEFAULT_STEP).abs() < f32::EPSILON);
+        }
+        let (replay, _) = two_shots();
+
+        for interval in [1e-9, -1.0, f32::NAN, f32::INFINITY] {
+            let frames = replay.frames(0, interval);
+            assert!((frames.interval() - STEP).abs() < f32::EPSILON);
+        }
+    }
+
+    #[test]
+    fn frames_run_from_the_strike_to_rest() {
+        let mut state = GameState::new(
+            Table::pool(800.0, 400.0),
+            vec![
+                Ball::new(400.0, 300.0, 0.0, 0.0, 10.0),
+                Ball::new(400.0, 150.0, 0.0, 0.0, 10.0),
+            ],
+        );
+        let mut replay = Replay::new(STEP);
+        let up = -std::f32::consts::FRAC_PI_2;
+        assert!(replay.record(&mut state, &CueStroke::new(up, 500.0, 0.0, 0.0, 0.0)));
+
+        let frames = replay.frames(0, 1.0 / 30.0);
+        let end = replay.play(0);
+
+        assert_eq!(frames.ball_ids(), vec![0, 1]);
+        assert_eq!(frames.frame(0), vec![400.0, 300.0, 400.0, 150.0]);
+        let last = frames.frames_len() - 1;
+        let cue = end.find_ball(0).unwrap().position;
+        assert_eq!(&frames.frame(last)[..2], &[cue.x, cue.y]);
+        // The object ball drops part way through and stays down.
+        assert_eq!(frames.on_table(0), vec![1, 1]);
+        assert_eq!(frames.on_table(last), vec![1, 0]);
+        assert_eq!(frames.positions().len(), frames.frames_len() * 4);
+        // Consecutive frames move smoothly: no ball ever moves further in a
+        // frame than the cue ball does in the first.
+        let moved = |index: usize, ball: usize| {
+            let (a, b) = (frames.frame(index - 1), frames.frame(index));
+            Vector2D::new(b[2 * ball] - a[2 * ball], b[2 * ball + 1] - a[2 * ball + 1]).length()
+        };
+        let first = moved(1, 0);
+        assert!(first > 0.0);
+        for index in 2..frames.frames_len() {
+            assert!(moved(index, 0) <= first + 0.5 && moved(index, 1) <= first + 0.5);
+        }
+    }
+}
@@ -0,0 +1,119 @@
+//! A small seeded random number generator.
+//!
+//! Racks (and anything else that needs randomness) draw from this generator
+//! rather than from the platform, so the same seed reproduces the same table
+//! on every machine and in every browser.
+
+use crate::math;
+
+/// A `SplitMix64` generator.
+///
+/// Statistically sound for shuffling balls and jittering positions, and
+/// cheap enough to create one per rack.
+#[derive(Clone, Debug)]
+pub struct Rng {
+    /// Internal state; advanced by a fixed increment on every draw.
+    state: u64,
+}
+
+impl Rng {
+    /// Creates a generator that will produce the sequence for `seed`.
+    #[must_use]
+    pub const fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Returns the next 64 random bits.
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number uniformly distributed in `[0, 1)`.
+    pub fn next_f32(&mut self) -> f32 {
+        // The top 23 bits fill an `f32` mantissa exactly, giving a number in
+        // `[1, 2)`.
+        let bits = u32::try_from(self.next_u64() >> 41).unwrap_or(0);
+        f32::from_bits(0x3F80_0000 | bits) - 1.0
+    }
+
+    /// Returns a number from the standard normal distribution.
+    pub fn next_normal(&mut self) -> f32 {
+        // Box-Muller; taking `1 - u` keeps the logarithm finite.
+        let u = 1.0 - self.next_f32();
+        let v = self.next_f32();
+        (-2.0 * math::ln(u)).sqrt() * math::cos(std::f32::consts::TAU * v)
+    }
+
+    /// Returns an index uniformly distributed in `0..len`.
+    ///
+    /// `len` must be non-zero.
+    pub fn below(&mut self, len: usize) -> usize {
+        let len = len as u64;
+        // Rejection sampling keeps every index equally likely.
+        let zone = u64::MAX - u64::MAX % len;
+        loop {
+            let value = self.next_u64();
+            if value < zone {
+                return usize::try_from(value % len).unwrap_or(0);
+            }
+        }
+    }
+
+    /// Shuffles `items` in place.
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i + 1));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_gives_same_sequence() {
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+        let mut c = Rng::new(43);
+        let first: Vec<u64> = (0..8).map(|_| a.next_u64()).collect();
+        let second: Vec<u64> = (0..8).map(|_| b.next_u64()).collect();
+        let other: Vec<u64> = (0..8).map(|_| c.next_u64()).collect();
+        assert_eq!(first, second);
+        assert_ne!(first, other);
+    }
+
+    #[test]
+    fn draws_stay_in_range() {
+        let mut rng = Rng::new(7);
+        for _ in 0..1000 {
+            let value = rng.next_f32();
+            assert!((0.0..1.0).contains(&value));
+            assert!(rng.below(3) < 3);
+        }
+    }
+
+    #[test]
+    fn shuffle_keeps_every_item() {
+        let mut rng = Rng::new(1);
+        let mut items: Vec<u32> = (0..15).collect();
+        rng.shuffle(&mut items);
+        assert_ne!(items, (0..15).collect::<Vec<_>>());
+        items.sort_unstable();
+        assert_eq!(items, (0..15).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn normal_draws_are_centred_with_unit_spread() {
+        let mut rng = Rng::new(3);
+        let draws: Vec<f32> = (0..4000).map(|_| rng.next_normal()).collect();
+        let mean = draws.iter().sum::<f32>() / 4000.0;
+        let variance = draws.iter().map(|x| (x - mean).powi(2)).sum::<f32>() / 4000.0;
+        assert!(mean.abs() < 0.1);
+        assert!((variance - 1.0).abs() < 0.1);
+    }
+}
@@ -0,0 +1,502 @@
+//! Carom billiards: straight rail, balkline and three-cushion.
+//!
+//! Each player has their own cue ball, white or yellow, and scores a point
+//! (a count) by making it hit both the other balls on one shot. A count
+//! keeps the player at the table; a miss passes it to the opponent. On the
+//! opening shot the red must be hit first.
+//!
+//! Three-cushion also requires the cue ball to touch cushions at least
+//! three times before it reaches the second object ball. Balkline divides
+//! the table with lines 47 cm from each cushion; while both object balls
+//! lie in the same area along the cushions, only two counts may be made
+//! before one of them is driven out.
+
+use wasm_bindgen::prelude::*;
+
+use super::{exists, is_player, BallInHand, Foul};
+use crate::snapshot::{Decode, Encode, Reader, SnapshotError};
+use crate::{Event, EventKind, GameState, Vector2D};
+
+/// Cushions the cue ball must touch before the second object ball in
+/// three-cushion.
+const THREE_CUSHIONS: u32 = 3;
+
+/// Distance of the balk lines from the cushions, as a fraction of the table
+/// length (47 cm on a 2.84 m table).
+const BALK_LINE: f32 = 0.47 / 2.84;
+
+/// Counts allowed in a row while both object balls share a balk area.
+const BALK_LIMIT: u32 = 2;
+
+/// One of the carom games.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CaromGame {
+    /// Straight rail: any shot that hits both object balls counts.
+    StraightRail = 0,
+    /// 47.2 balkline: as straight rail, but limited to two counts while
+    /// both object balls sit in one balk area.
+    Balkline = 1,
+    /// Three-cushion: the cue ball must touch three cushions before the
+    /// second object ball.
+    ThreeCushion = 2,
+}
+
+/// What happened to the cue ball during one shot.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct CaromShot {
+    /// Object balls the cue ball hit, in order, each listed once.
+    hit: Vec<u32>,
+    /// Cushions the cue ball touched before it hit the second object ball.
+    cushions: u32,
+    /// The balk area both object balls shared when the shot began.
+    balk_area: Option<(u8, u8)>,
+}
+
+/// The state of a game of carom between two players.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Carom {
+    /// Which carom game is being played.
+    game: CaromGame,
+    /// Ids of the white and yellow cue balls, for players 0 and 1.
+    cues: [u32; 2],
+    /// Id of the red.
+    red: u32,
+    /// The player to shoot, 0 or 1.
+    player: u32,
+    /// Points scored by each player.
+    scores: [u32; 2],
+    /// Points that win the game.
+    target: u32,
+    /// `true` until the opening shot has been played.
+    opening: bool,
+    /// Counts made in a row with both object balls in one balk area.
+    balk_counts: u32,
+    /// The winner, once the game is over.
+    winner: Option<u32>,
+    /// The shot being played, if any.
+    shot: Option<CaromShot>,
+}
+
+impl Carom {
+    /// Starts a game of `game` to `target` points, played with the balls
+    /// [`GameState::carom`] racks: white, yellow and red as ids 0, 1 and 2.
+    /// Player 0 breaks with the white.
+    #[must_use]
+    pub const fn new(game: CaromGame, target: u32) -> Self {
+        Self {
+            game,
+            cues: [0, 1],
+            red: 2,
+            player: 0,
+            scores: [0, 0],
+            target,
+            opening: true,
+            balk_counts: 0,
+            winner: None,
+            shot: None,
+        }
+    }
+
+    /// Returns the player to shoot.
+    #[must_use]
+    pub const fn current_player(&self) -> u32 {
+        self.player
+    }
+
+    /// Returns the winner, once the game is over.
+    #[must_use]
+    pub const fn winner(&self) -> Option<u32> {
+        self.winner
+    }
+
+    /// Carom has no fouls; missing simply ends the turn.
+    #[must_use]
+    pub const fn last_foul(&self) -> Option<Foul> {
+        None
+    }
+
+    /// The cue ball is always played from where it lies.
+    #[must_use]
+    pub const fn ball_in_hand(&self) -> BallInHand {
+        BallInHand::No
+    }
+
+    /// Returns the ids of the object balls of the player to shoot.
+    #[must_use]
+    pub fn targets(&self, state: &GameState) -> Vec<u32> {
+        let cue = self.cue_ball();
+        state
+            .balls
+            .iter()
+            .filter(|ball| ball.id != cue)
+            .map(|ball| ball.id)
+            .collect()
+    }
+
+    /// Returns the id of the cue ball of the player to shoot.
+    #[must_use]
+    pub const fn cue_ball(&self) -> u32 {
+        self.cues[(self.player & 1) as usize]
+    }
+
+    /// Returns the points scored by `player`.
+    #[must_use]
+    pub fn score(&self, player: u32) -> u32 {
+        self.scores.get(player as usize).copied().unwrap_or(0)
+    }
+
+    /// Returns which carom game is being played.
+    #[must_use]
+    pub const fn game(&self) -> CaromGame {
+        self.game
+    }
+
+    /// Starts a shot. Returns `false` once the game is over or while a shot
+    /// is still running.
+    pub fn begin_shot(&mut self, state: &GameState) -> bool {
+        if self.winner.is_some() || self.shot.is_some() {
+            return false;
+        }
+        let balk_area = if self.game == CaromGame::Balkline {
+            self.shared_balk_area(state)
+        } else {
+            None
+        };
+        if balk_area.is_none() {
+            self.balk_counts = 0;
+        }
+        self.shot = Some(CaromShot {
+            balk_area,
+            ..CaromShot::default()
+        });
+        true
+    }
+
+    /// Adds an event of the running shot.
+    pub fn observe(&mut self, event: &Event) {
+        let cue = self.cue_ball();
+        let Some(shot) = &mut self.shot else {
+            return;
+        };
+        if shot.hit.len() >= 2 {
+            return;
+        }
+        match event.kind {
+            EventKind::BallContact => {
+                let other = if event.ball() == Some(cue) {
+                    event.other_ball()
+                } else if event.other_ball() == Some(cue) {
+                    event.ball()
+                } else {
+                    None
+                };
+                if let Some(other) = other.filter(|other| !shot.hit.contains(other)) {
+                    shot.hit.push(other);
+                }
+            }
+            EventKind::CushionContact if event.ball() == Some(cue) => shot.cushions += 1,
+            _ => {}
+        }
+    }
+
+    /// Returns `true` if the players this game records are 0 or 1 and every
+    /// ball it refers to is in `state`, on the table or in a pocket.
+    /// No score may be past the target, nor the balkline count past its
+    /// limit.
+    #[must_use]
+    pub fn is_consistent(&self, state: &GameState) -> bool {
+        is_player(self.player)
+            && self.winner.is_none_or(is_player)
+            && self.scores.iter().all(|&score| score <= self.target)
+            && self.balk_counts <= BALK_LIMIT
+            && self
+                .cues
+                .iter()
+                .chain([&self.red])
+                .all(|&id| exists(state, id))
+            && self
+                .shot
+                .as_ref()
+                .is_none_or(|shot| shot.hit.iter().all(|&id| exists(state, id)))
+    }
+
+    /// Judges the shot that has just come to rest.
+    pub fn end_shot(&mut self, state: &mut GameState) {
+        let Some(shot) = self.shot.take() else {
+            return;
+        };
+        let opening = std::mem::take(&mut self.opening);
+
+        let mut count = shot.hit.len() == 2
+            && !(opening && shot.hit[0] != self.red)
+            && (self.game != CaromGame::ThreeCushion || shot.cushions >= THREE_CUSHIONS);
+        if count && shot.balk_area.is_some() {
+            if self.shared_balk_area(state) != shot.balk_area {
+                // The count took both object balls out of the area, so it
+                // is the first in whichever area they are in now.
+                self.balk_counts = 1;
+            } else if self.balk_counts >= BALK_LIMIT {
+                count = false;
+            } else {
+                self.balk_counts += 1;
+            }
+        }
+
+        if !count {
+            self.balk_counts = 0;
+            self.player = 1 - self.player;
+            return;
+        }
+        let shooter = self.player as usize;
+        self.scores[shooter] = self.scores[shooter].saturating_add(1);
+        if self.scores[shooter] >= self.target {
+            self.winner = Some(self.player);
+        }
+    }
+
+    /// Returns the balk area along the cushions that holds both object
+    /// balls of the player to shoot, if there is one.
+    fn shared_balk_area(&self, state: &GameState) -> Option<(u8, u8)> {
+        let cue = self.cue_ball();
+        let mut areas = state
+            .balls
+            .iter()
+            .filter(|ball| ball.id != cue)
+            .map(|ball| balk_area(state, ball.position));
+        let first = areas.next()?;
+        // The middle area is not restricted.
+        (first != (1, 1) && areas.all(|area| area == first)).then_some(first)
+    }
+}
+
+/// Returns the column and row, each 0 to 2, of the balk area holding
+/// `position`.
+fn balk_area(state: &GameState, position: Vector2D) -> (u8, u8) {
+    let table = state.table;
+    let line = table.width * BALK_LINE;
+    let band = |at: f32, size: f32| {
+        if at < line {
+            0
+        } else if at > size - line {
+            2
+        } else {
+            1
+        }
+    };
+    (
+        band(position.x, table.width),
+        band(position.y, table.height),
+    )
+}
+
+impl Encode for CaromShot {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.hit.encode(out);
+        self.cushions.encode(out);
+        self.balk_area.encode(out);
+    }
+}
+
+impl Decode for CaromShot {
+    fn decode(input: &mut Reader<'_>) -> Result<Self, SnapshotError> {
+        Ok(Self {
+            hit: input.read()?,
+            cushions: input.read()?,
+            balk_area: input.read()?,
+        })
+    }
+}
+
+impl Encode for Carom {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.game.encode(out);
+        self.cues.encode(out);
+        self.red.encode(out);
+        self.player.encode(out);
+        self.scores.encode(out);
+        self.target.encode(out);
+        self.opening.encode(out);
+        self.balk_counts.encode(out);
+        self.winner.encode(out);
+        self.shot.encode(out);
+    }
+}
+
+impl Decode for Carom {
+    fn decode(input: &mut Reader<'_>) -> Result<Self, SnapshotError> {
+        Ok(Self {
+            game: input.read()?,
+            cues: input.read()?,
+            red: input.read()?,
+            player: input.read()?,
+            scores: input.read()?,
+            target: input.read()?,
+            opening: input.read()?,
+            balk_counts: input.read()?,
+            winner: input.read()?,
+            shot: input.read()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::testing::shoot;
+    use crate::{Ball, BallKind, Rules, Table};
+
+    /// A carom game past the opening shot with the balls at `white`,
+    /// `yellow` and `red`.
+    fn game(game: CaromGame, balls: [(f32, f32); 3]) -> GameState {
+        let mut rules = Carom::new(game, 10);
+        rules.opening = false;
+        let kinds = [BallKind::Cue, BallKind::SecondCue, BallKind::CaromRed];
+        let balls = kinds
+            .into_iter()
+            .zip(balls)
+            .map(|(kind, (x, y))| Ball::carom(kind, x, y))
+            .collect();
+        GameState::new(Table::carom(), balls).with_rules(Rules::Carom(rules))
+    }
+
+    /// Plays `events` as one shot by player 0 and judges it.
+    fn judge(state: &mut GameState, events: &[Event]) {
+        assert!(crate::rules::begin_shot(state));
+        let from = state.events.len();
+        state.events.extend_from_slice(events);
+        state.events.push(Event::all_stopped(1.0));
+        crate::rules::observe(state, from);
+    }
+
+    #[test]
+    fn hitting_both_balls_counts_in_straight_rail() {
+        // The white grazes the yellow on its way to the red.
+        let mut state = game(
+            CaromGame::StraightRail,
+            [(200.0, 223.0), (400.0, 242.0), (480.0, 223.0)],
+        );
+
+        assert!(shoot(&mut state, 0.0, 300.0));
+
+        assert_eq!(state.score(0), 1);
+        assert_eq!(state.current_player(), Some(0));
+        assert_eq!(state.cue_ball_id(), Some(0));
+    }
+
+    #[test]
+    fn a_miss_hands_over_the_other_cue_ball() {
+        let mut state = game(
+            CaromGame::StraightRail,
+            [(200.0, 223.0), (400.0, 223.0), (600.0, 100.0)],
+        );
+
+        judge(&mut state, &[Event::ball_contact(0.1, 0, 1, 100.0)]);
+
+        assert_eq!(state.score(0), 0);
+        assert_eq!(state.current_player(), Some(1));
+        assert_eq!(state.cue_ball_id(), Some(1));
+    }
+
+    #[test]
+    fn three_cushion_needs_three_cushions_before_the_second_ball() {
+        let cushions = [
+            Event::ball_contact(0.1, 0, 2, 100.0),
+            Event::cushion_contact(0.2, 0, crate::Rail::Top, 90.0),
+            Event::cushion_contact(0.3, 0, crate::Rail::Right, 80.0),
+        ];
+        let mut short = game(
+            CaromGame::ThreeCushion,
+            [(200.0, 223.0), (400.0, 223.0), (600.0, 223.0)],
+        );
+        let mut events = cushions.to_vec();
+        events.push(Event::ball_contact(0.4, 1, 0, 70.0));
+        judge(&mut short, &events);
+        assert_eq!(short.score(0), 0);
+        assert_eq!(short.current_player(), Some(1));
+
+        let mut full = game(
+            CaromGame::ThreeCushion,
+            [(200.0, 223.0), (400.0, 223.0), (600.0, 223.0)],
+        );
+        let mut events = cushions.to_vec();
+        events.push(Event::cushion_contact(0.4, 0, crate::Rail::Bottom, 75.0));
+        events.push(Event::ball_contact(0.5, 1, 0, 70.0));
+        judge(&mut full, &events);
+        assert_eq!(full.score(0), 1);
+        assert_eq!(full.current_player(), Some(0));
+    }
+
+    #[test]
+    fn balkline_limits_counts_in_one_area() {
+        // Both object balls sit in the top left balk area.
+        let mut state = game(
+            CaromGame::Balkline,
+            [(300.0, 223.0), (40.0, 40.0), (80.0, 40.0)],
+        );
+        let carom = [
+            Event::ball_contact(0.1, 0, 1, 100.0),
+            Event::ball_contact(0.2, 0, 2, 80.0),
+        ];
+        for _ in 0..BALK_LIMIT {
+            judge(&mut state, &carom);
+        }
+        assert_eq!(state.score(0), 2);
+
+        judge(&mut state, &carom);
+        assert_eq!(state.score(0), 2);
+        assert_eq!(state.current_player(), Some(1));
+    }
+
+    #[test]
+    fn balkline_limit_starts_again_in_another_area() {
+        // Both object balls sit in the top left balk area.
+        let mut state = game(
+            CaromGame::Balkline,
+            [(300.0, 223.0), (40.0, 40.0), (80.0, 40.0)],
+        );
+        let carom = [
+            Event::ball_contact(0.1, 0, 1, 100.0),
+            Event::ball_contact(0.2, 0, 2, 80.0),
+        ];
+        for _ in 0..BALK_LIMIT {
+            judge(&mut state, &carom);
+        }
+
+        // The next count drives both of them into the top right area.
+        assert!(crate::rules::begin_shot(&mut state));
+        let right = state.table.width;
+        for ball in state.balls.iter_mut().filter(|ball| ball.id != 0) {
+            ball.position.x = right - ball.position.x;
+        }
+        let from = state.events.len();
+        state.events.extend_from_slice(&carom);
+        state.events.push(Event::all_stopped(1.0));
+        crate::rules::observe(&mut state, from);
+        assert_eq!(state.score(0), 3);
+
+        // That count was the first there, so one more is allowed.
+        judge(&mut state, &carom);
+        assert_eq!(state.score(0), 4);
+        judge(&mut state, &carom);
+        assert_eq!(state.score(0), 4);
+        assert_eq!(state.current_player(), Some(1));
+    }
+
+    #[test]
+    fn opening_shot_must_hit_the_red_first() {
+        let mut state = GameState::carom(&Table::carom(), CaromGame::StraightRail, 10);
+        judge(
+            &mut state,
+            &[
+                Event::ball_contact(0.1, 0, 1, 100.0),
+                Event::ball_contact(0.2, 0, 2, 80.0),
+            ],
+        );
+        assert_eq!(state.score(0), 0);
+        assert_eq!(state.current_player(), Some(1));
+    }
+}
@@ -0,0 +1,463 @@
+//! Eight-ball, following the WPA world standardized rules.
+//!
+//! The table is open after the break. The first player to legally pocket a
+//! ball takes that ball's group, solids or stripes, and must clear it before
+//! playing the 8. Every shot must hit a ball of the shooter's group first
+//! (any ball while the table is open) and then pocket a ball or drive a ball
+//! to a cushion. A foul hands the opponent the cue ball anywhere on the
+//! table; a scratch on the break restricts it to the kitchen.
+//!
+//! The 8 must be called. Pocketing it legally into the called pocket after
+//! clearing the group wins; pocketing it any other way loses, except on the
+//! break, where it is spotted.
+
+use wasm_bindgen::prelude::*;
+
+use super::{exists, is_player, kind_of, spot_ball, BallInHand, Foul, ShotRecord};
+use crate::snapshot::{Decode, Encode, Reader, SnapshotError};
+use crate::{BallKind, Event, GameState, Pocket, Vector2D, CUE_BALL_ID};
+
+/// Object balls that must reach a cushion on a break that pockets nothing.
+const BREAK_RAIL_BALLS: usize = 4;
+
+/// One of the two groups of object balls.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Group {
+    /// The balls numbered 1 to 7.
+    Solids = 0,
+    /// The balls numbered 9 to 15.
+    Stripes = 1,
+}
+
+impl Group {
+    /// Returns the group a ball of `kind` belongs to, if any.
+    #[must_use]
+    pub const fn of(kind: BallKind) -> Option<Self> {
+        match kind {
+            BallKind::Solid => Some(Self::Solids),
+            BallKind::Stripe => Some(Self::Stripes),
+            _ => None,
+        }
+    }
+
+    /// Returns the other group.
+    #[must_use]
+    pub const fn other(self) -> Self {
+        match self {
+            Self::Solids => Self::Stripes,
+            Self::Stripes => Self::Solids,
+        }
+    }
+}
+
+/// The state of a game of eight-ball between two players.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EightBall {
+    /// Id of the cue ball.
+    cue: u32,
+    /// The player to shoot, 0 or 1.
+    player: u32,
+    /// Each player's group, once the table is no longer open.
+    groups: [Option<Group>; 2],
+    /// `true` until the break has been played.
+    breaking: bool,
+    /// The pocket called for the 8 on the coming shot.
+    called: Option<Pocket>,
+    /// Where the cue ball may be placed before the coming shot.
+    ball_in_hand: BallInHand,
+    /// The foul committed on the last shot.
+    last_foul: Option<Foul>,
+    /// The winner, once the game is over.
+    winner: Option<u32>,
+    /// The shot being played, if any.
+    shot: Option<ShotRecord>,
+}
+
+impl Default for EightBall {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EightBall {
+    /// Starts a game with player 0 to break from the kitchen.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            cue: CUE_BALL_ID,
+            player: 0,
+            groups: [None, None],
+            breaking: true,
+            called: None,
+            ball_in_hand: BallInHand::Kitchen,
+            last_foul: None,
+            winner: None,
+            shot: None,
+        }
+    }
+
+    /// Returns the player to shoot.
+    #[must_use]
+    pub const fn current_player(&self) -> u32 {
+        self.player
+    }
+
+    /// Returns the winner, once the game is over.
+    #[must_use]
+    pub const fn winner(&self) -> Option<u32> {
+        self.winner
+    }
+
+    /// Returns the foul committed on the last shot, if any.
+    #[must_use]
+    pub const fn last_foul(&self) -> Option<Foul> {
+        self.last_foul
+    }
+
+    /// Returns where the cue ball may be placed before the next shot.
+    #[must_use]
+    pub const fn ball_in_hand(&self) -> BallInHand {
+        self.ball_in_hand
+    }
+
+    /// Returns the ids of the balls the player to shoot may hit first.
+    #[must_use]
+    pub fn targets(&self, state: &GameState) -> Vec<u32> {
+        let group = self.group(self.player);
+        let on_eight = group.is_some_and(|group| {
+            !state
+                .balls
+                .iter()
+                .any(|ball| Group::of(ball.kind) == Some(group))
+        });
+        state
+            .balls
+            .iter()
+            .filter(|ball| ball.id != self.cue)
+            .filter(|ball| self.breaking || Self::legal_first(Some(ball.kind), group, on_eight))
+            .map(|ball| ball.id)
+            .collect()
+    }
+
+    /// Returns the group of `player`, or `None` while the table is open.
+    #[must_use]
+    pub fn group(&self, player: u32) -> Option<Group> {
+        self.groups.get(player as usize).copied().flatten()
+    }
+
+    /// Calls `pocket` for the 8 on the coming shot.
+    pub const fn call_pocket(&mut self, pocket: Pocket) {
+        self.called = Some(pocket);
+    }
+
+    /// Starts a shot. Returns `false` once the game is over or while a shot
+    /// is still running.
+    pub fn begin_shot(&mut self) -> bool {
+        if self.winner.is_some() || self.shot.is_some() {
+            return false;
+        }
+        self.ball_in_hand = BallInHand::No;
+        self.shot = Some(ShotRecord::default());
+        true
+    }
+
+    /// Adds an event of the running shot.
+    pub fn observe(&mut self, event: &Event) {
+        if let Some(shot) = &mut self.shot {
+            shot.observe(event, self.cue);
+        }
+    }
+
+    /// Returns `true` if the players this game records are 0 or 1 and every
+    /// ball it refers to is in `state`, on the table or in a pocket.
+    #[must_use]
+    pub fn is_consistent(&self, state: &GameState) -> bool {
+        is_player(self.player)
+            && self.winner.is_none_or(is_player)
+            && exists(state, self.cue)
+            && self
+                .shot
+                .as_ref()
+                .is_none_or(|shot| shot.is_consistent(state))
+    }
+
+    /// Judges the shot that has just come to rest.
+    pub fn end_shot(&mut self, state: &mut GameState) {
+        let Some(shot) = self.shot.take() else {
+            return;
+        };
+        let called = self.called.take();
+        let kind = |id| kind_of(state, id);
+        let eight = shot
+            .pocketed
+            .iter()
+            .find(|(id, _)| kind(*id) == Some(BallKind::Eight))
+            .copied();
+        let object_pocketed = shot
+            .pocketed
+            .iter()
+            .any(|(id, _)| kind(*id).and_then(Group::of).is_some());
+
+        if self.breaking {
+            self.breaking = false;
+            let foul = if shot.scratched {
+                Some(Foul::Scratch)
+            } else if shot.first_contact.is_none() {
+                Some(Foul::NoContact)
+            } else if shot.pocketed.is_empty() && shot.object_balls_to_rail.len() < BREAK_RAIL_BALLS
+            {
+                Some(Foul::IllegalBreak)
+            } else {
+                None
+            };
+            if let Some((id, _)) = eight {
+                let foot_spot = Vector2D::new(state.table.width * 0.75, state.table.height * 0.5);
+                spot_ball(state, id, foot_spot);
+            }
+            let in_hand = if foul == Some(Foul::Scratch) {
+                BallInHand::Kitchen
+            } else {
+                BallInHand::Anywhere
+            };
+            self.finish_turn(foul, object_pocketed, in_hand);
+            return;
+        }
+
+        let group = self.group(self.player);
+        let on_eight = group.is_some_and(|group| {
+            !state
+                .balls
+                .iter()
+                .any(|ball| Group::of(ball.kind) == Some(group))
+                && !shot
+                    .pocketed
+                    .iter()
+                    .any(|(id, _)| kind(*id).and_then(Group::of) == Some(group))
+        });
+        let first = shot.first_contact.and_then(kind);
+        let foul = if shot.scratched {
+            Some(Foul::Scratch)
+        } else if first.is_none() {
+            Some(Foul::NoContact)
+        } else if !Self::legal_first(first, group, on_eight) {
+            Some(Foul::WrongBallFirst)
+        } else if shot.pocketed.is_empty() && !shot.rail_after_contact {
+            Some(Foul::NoRail)
+        } else {
+            None
+        };
+
+        if let Some((_, pocket)) = eight {
+            let won = foul.is_none() && on_eight && called == Some(pocket);
+            self.last_foul = foul;
+            self.winner = Some(if won { self.player } else { 1 - self.player });
+            return;
+        }
+
+        // An open table is decided by the first ball of a group to drop on a
+        // legal shot.
+        if group.is_none() && foul.is_none() {
+            let claimed = shot
+                .pocketed
+                .iter()
+                .find_map(|(id, _)| kind(*id).and_then(Group::of));
+            if let Some(claimed) = claimed {
+                self.groups[self.player as usize] = Some(claimed);
+                self.groups[1 - self.player as usize] = Some(claimed.other());
+            }
+        }
+        let own = self.group(self.player);
+        let scored = shot
+            .pocketed
+            .iter()
+            .any(|(id, _)| own.is_some() && kind(*id).and_then(Group::of) == own);
+        self.finish_turn(foul, scored, BallInHand::Anywhere);
+    }
+
+    /// Returns `true` if hitting a ball of kind `first` first is legal for a
+    /// player with `group` who may or may not be on the 8.
+    fn legal_first(first: Option<BallKind>, group: Option<Group>, on_eight: bool) -> bool {
+        match (first, group) {
+            (Some(BallKind::Eight), _) => on_eight || group.is_none(),
+            (Some(kind), Some(group)) => !on_eight && Group::of(kind) == Some(group),
+            (Some(kind), None) => Group::of(kind).is_some(),
+            (None, _) => false,
+        }
+    }
+
+    /// Ends the turn: the shooter stays at the table after a legal scoring
+    /// shot, and otherwise the opponent comes in, with the cue ball in hand
+    /// (placed as `in_hand` allows) after a foul.
+    fn finish_turn(&mut self, foul: Option<Foul>, scored: bool, in_hand: BallInHand) {
+        self.last_foul = foul;
+        if foul.is_some() {
+            self.ball_in_hand = in_hand;
+            self.player = 1 - self.player;
+        } else if !scored {
+            self.player = 1 - self.player;
+        }
+    }
+}
+
+impl Encode for EightBall {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.cue.encode(out);
+        self.player.encode(out);
+        self.groups.encode(out);
+        self.breaking.encode(out);
+        self.called.encode(out);
+        self.ball_in_hand.encode(out);
+        self.last_foul.encode(out);
+        self.winner.encode(out);
+        self.shot.encode(out);
+    }
+}
+
+impl Decode for EightBall {
+    fn decode(input: &mut Reader<'_>) -> Result<Self, SnapshotError> {
+        Ok(Self {
+            cue: input.read()?,
+            player: input.read()?,
+            groups: input.read()?,
+            breaking: input.read()?,
+            called: input.read()?,
+            ball_in_hand: input.read()?,
+            last_foul: input.read()?,
+            winner: input.read()?,
+            shot: input.read()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::testing::{pool_game, pot_in_top_side, shoot_up};
+    use crate::{Ball, CueStroke, Rules, Table};
+
+    /// A game past the break, with `groups` already decided.
+    fn game(balls: Vec<Ball>, groups: [Option<Group>; 2]) -> GameState {
+        let mut rules = EightBall::new();
+        rules.breaking = false;
+        rules.ball_in_hand = BallInHand::No;
+        rules.groups = groups;
+        pool_game(balls, Rules::EightBall(rules))
+    }
+
+    const SOLIDS: [Option<Group>; 2] = [Some(Group::Solids), Some(Group::Stripes)];
+
+    #[test]
+    fn first_legal_pot_on_open_table_decides_groups() {
+        let mut state = game(pot_in_top_side(11, &[]), [None, None]);
+
+        assert!(shoot_up(&mut state, 200.0));
+
+        assert!(!state.is_on_table(1));
+        assert_eq!(state.last_foul(), None);
+        assert_eq!(state.current_player(), Some(0));
+        assert_eq!(state.group(0), Some(Group::Stripes));
+        assert_eq!(state.group(1), Some(Group::Solids));
+    }
+
+    #[test]
+    fn hitting_the_other_group_first_is_a_foul() {
+        let mut state = game(pot_in_top_side(11, &[]), SOLIDS);
+
+        assert!(shoot_up(&mut state, 200.0));
+
+        assert_eq!(state.last_foul(), Some(Foul::WrongBallFirst));
+        assert_eq!(state.current_player(), Some(1));
+        assert_eq!(state.ball_in_hand(), BallInHand::Anywhere);
+    }
+
+    #[test]
+    fn scratch_gives_ball_in_hand_anywhere() {
+        let mut state = game(
+            vec![Ball::pool(0, 400.0, 100.0), Ball::pool(3, 200.0, 300.0)],
+            SOLIDS,
+        );
+
+        assert!(shoot_up(&mut state, 200.0));
+
+        assert_eq!(state.last_foul(), Some(Foul::Scratch));
+        assert!(state.cue_ball_id().is_some_and(|id| !state.is_on_table(id)));
+        assert!(!state.place_cue_ball(200.0, 300.0));
+        assert!(state.place_cue_ball(700.0, 300.0));
+        assert!(state.is_on_table(0));
+        assert!(state.pocketed_by_id(0).is_none());
+    }
+
+    #[test]
+    fn soft_touch_without_a_rail_is_a_foul() {
+        let mut state = game(
+            vec![Ball::pool(0, 400.0, 219.0), Ball::pool(2, 400.0, 200.0)],
+            SOLIDS,
+        );
+
+        assert!(shoot_up(&mut state, 40.0));
+
+        assert_eq!(state.last_foul(), Some(Foul::NoRail));
+        assert_eq!(state.current_player(), Some(1));
+    }
+
+    #[test]
+    fn eight_in_called_pocket_after_clearing_wins() {
+        let mut state = game(pot_in_top_side(8, &[]), SOLIDS);
+        assert!(state.call_pocket(Pocket::TopSide));
+
+        assert!(shoot_up(&mut state, 200.0));
+
+        assert_eq!(state.winner(), Some(0));
+        assert!(!state.strike(&CueStroke::new(0.0, 100.0, 0.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn eight_in_uncalled_pocket_loses() {
+        let mut state = game(pot_in_top_side(8, &[]), SOLIDS);
+        assert!(state.call_pocket(Pocket::BottomSide));
+
+        assert!(shoot_up(&mut state, 200.0));
+
+        assert_eq!(state.winner(), Some(1));
+    }
+
+    #[test]
+    fn eight_before_clearing_the_group_loses() {
+        let mut balls = pot_in_top_side(8, &[]);
+        balls.push(Ball::pool(5, 100.0, 300.0));
+        let mut state = game(balls, SOLIDS);
+        assert!(state.call_pocket(Pocket::TopSide));
+
+        assert!(shoot_up(&mut state, 200.0));
+
+        assert_eq!(state.winner(), Some(1));
+    }
+
+    #[test]
+    fn weak_break_is_illegal() {
+        let mut state = game(
+            vec![Ball::pool(0, 400.0, 219.0), Ball::pool(2, 400.0, 200.0)],
+            [None, None],
+        );
+        if let Some(Rules::EightBall(rules)) = &mut state.rules {
+            rules.breaking = true;
+        }
+
+        assert!(shoot_up(&mut state, 40.0));
+
+        assert_eq!(state.last_foul(), Some(Foul::IllegalBreak));
+        assert_eq!(state.ball_in_hand(), BallInHand::Anywhere);
+    }
+
+    #[test]
+    fn racked_game_breaks_from_the_kitchen() {
+        let table = Table::pool(800.0, 400.0);
+        let mut state = GameState::eight_ball(&table, 4, 0.0);
+        assert_eq!(state.ball_in_hand(), BallInHand::Kitchen);
+        assert!(!state.place_cue_ball(300.0, 200.0));
+        assert!(state.place_cue_ball(150.0, 150.0));
+    }
+}
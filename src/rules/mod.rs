@@ -0,0 +1,570 @@
+//! Game rules on top of the simulation.
+//!
+//! A ruleset follows a game shot by shot. [`GameState::strike`] opens a
+//! shot, every event the physics records during it is fed to the ruleset,
+//! and once the table comes to rest the shot is judged: fouls are called,
+//! balls are spotted, the turn passes or stays, and the game may end.
+//!
+//! Rules only ever look at the event stream and the positions it leaves
+//! behind, so they apply equally to live play and to replays.
+
+mod carom;
+mod eight_ball;
+mod rotation;
+mod snooker;
+mod straight_pool;
+
+use wasm_bindgen::prelude::*;
+
+pub use carom::{Carom, CaromGame};
+pub use eight_ball::{EightBall, Group};
+pub use rotation::Rotation;
+pub use snooker::Snooker;
+pub use straight_pool::StraightPool;
+
+use crate::snapshot::{Decode, Encode, Reader, SnapshotError};
+use crate::{Ball, BallKind, Event, EventKind, GameState, Pocket, PocketedBall, Vector2D};
+
+/// A foul committed on a shot.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Foul {
+    /// The cue ball did not touch an object ball.
+    NoContact = 0,
+    /// The cue ball touched a ball it was not allowed to hit first.
+    WrongBallFirst = 1,
+    /// No ball was pocketed and no ball touched a cushion after the cue ball
+    /// hit the first object ball.
+    NoRail = 2,
+    /// The cue ball dropped into a pocket.
+    Scratch = 3,
+    /// The break did not drive enough balls to the cushions.
+    IllegalBreak = 4,
+    /// A ball other than the ball on dropped into a pocket.
+    WrongBallPotted = 5,
+}
+
+/// Where the incoming player may place the cue ball before their shot.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BallInHand {
+    /// The cue ball must be played from where it lies.
+    No = 0,
+    /// Anywhere on the table.
+    Anywhere = 1,
+    /// Anywhere behind the head string, in the kitchen.
+    Kitchen = 2,
+    /// Anywhere in the D.
+    InD = 3,
+}
+
+/// What happened during one shot, gathered from its events.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ShotRecord {
+    /// The first ball the cue ball touched.
+    pub first_contact: Option<u32>,
+    /// Balls that dropped, with their pockets, in the order they fell.
+    pub pocketed: Vec<(u32, Pocket)>,
+    /// Whether any ball touched a cushion after the first contact.
+    pub rail_after_contact: bool,
+    /// Object balls that touched a cushion, each listed once.
+    pub object_balls_to_rail: Vec<u32>,
+    /// Whether the cue ball dropped.
+    pub scratched: bool,
+}
+
+impl ShotRecord {
+    /// Adds `event` to the record of a shot played with cue ball `cue`.
+    pub fn observe(&mut self, event: &Event, cue: u32) {
+        let Some(ball) = event.ball() else {
+            return;
+        };
+        match event.kind {
+            EventKind::BallContact if self.first_contact.is_none() => {
+                if ball == cue {
+                    self.first_contact = event.other_ball();
+                } else if event.other_ball() == Some(cue) {
+                    self.first_contact = Some(ball);
+                }
+            }
+            EventKind::CushionContact => {
+                if self.first_contact.is_some() {
+                    self.rail_after_contact = true;
+                }
+                if ball != cue && !self.object_balls_to_rail.contains(&ball) {
+                    self.object_balls_to_rail.push(ball);
+                }
+            }
+            EventKind::Pocketed => {
+                if ball == cue {
+                    self.scratched = true;
+                } else if let Some(pocket) = event.pocket() {
+                    self.pocketed.push((ball, pocket));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns `true` if every ball the record names is in `state`, on the
+    /// table or in a pocket.
+    #[must_use]
+    pub fn is_consistent(&self, state: &GameState) -> bool {
+        self.first_contact
+            .iter()
+            .chain(self.pocketed.iter().map(|(id, _)| id))
+            .chain(&self.object_balls_to_rail)
+            .all(|&id| exists(state, id))
+    }
+}
+
+/// The ruleset a [`GameState`] is played under.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Rules {
+    /// Eight-ball.
+    EightBall(EightBall),
+    /// Nine-ball or ten-ball.
+    Rotation(Rotation),
+    /// Snooker.
+    Snooker(Snooker),
+    /// Straight rail, balkline or three-cushion.
+    Carom(Carom),
+    /// Straight pool.
+    StraightPool(StraightPool),
+}
+
+impl Rules {
+    /// Returns the player whose turn it is.
+    #[must_use]
+    pub const fn current_player(&self) -> u32 {
+        match self {
+            Self::EightBall(game) => game.current_player(),
+            Self::Rotation(game) => game.current_player(),
+            Self::Snooker(game) => game.current_player(),
+            Self::Carom(game) => game.current_player(),
+            Self::StraightPool(game) => game.current_player(),
+        }
+    }
+
+    /// Returns the winner, once the game is over.
+    #[must_use]
+    pub const fn winner(&self) -> Option<u32> {
+        match self {
+            Self::EightBall(game) => game.winner(),
+            Self::Rotation(game) => game.winner(),
+            Self::Snooker(game) => game.winner(),
+            Self::Carom(game) => game.winner(),
+            Self::StraightPool(game) => game.winner(),
+        }
+    }
+
+    /// Returns the foul committed on the last shot, if any.
+    #[must_use]
+    pub const fn last_foul(&self) -> Option<Foul> {
+        match self {
+            Self::EightBall(game) => game.last_foul(),
+            Self::Rotation(game) => game.last_foul(),
+            Self::Snooker(game) => game.last_foul(),
+            Self::Carom(game) => game.last_foul(),
+            Self::StraightPool(game) => game.last_foul(),
+        }
+    }
+
+    /// Returns where the cue ball may be placed before the next shot.
+    #[must_use]
+    pub const fn ball_in_hand(&self) -> BallInHand {
+        match self {
+            Self::EightBall(game) => game.ball_in_hand(),
+            Self::Rotation(game) => game.ball_in_hand(),
+            Self::Snooker(game) => game.ball_in_hand(),
+            Self::Carom(game) => game.ball_in_hand(),
+            Self::StraightPool(game) => game.ball_in_hand(),
+        }
+    }
+
+    /// Returns the ids of the balls the player to shoot may hit first.
+    #[must_use]
+    pub fn targets(&self, state: &GameState) -> Vec<u32> {
+        match self {
+            Self::EightBall(game) => game.targets(state),
+            Self::Rotation(game) => game.targets(state),
+            Self::Snooker(game) => game.targets(state),
+            Self::Carom(game) => game.targets(state),
+            Self::StraightPool(game) => game.targets(state),
+        }
+    }
+
+    /// Returns the cue ball of the player to shoot, in games where each
+    /// player has their own.
+    #[must_use]
+    pub const fn cue_ball(&self) -> Option<u32> {
+        match self {
+            Self::Carom(game) => Some(game.cue_ball()),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the players the ruleset records are 0 or 1 and every
+    /// ball it refers to is in `state`.
+    #[must_use]
+    pub fn is_consistent(&self, state: &GameState) -> bool {
+        match self {
+            Self::EightBall(game) => game.is_consistent(state),
+            Self::Rotation(game) => game.is_consistent(state),
+            Self::Snooker(game) => game.is_consistent(state),
+            Self::Carom(game) => game.is_consistent(state),
+            Self::StraightPool(game) => game.is_consistent(state),
+        }
+    }
+
+    /// Starts a new shot. Returns `false` if no shot may be played.
+    fn begin_shot(&mut self, state: &GameState) -> bool {
+        match self {
+            Self::EightBall(game) => game.begin_shot(),
+            Self::Rotation(game) => game.begin_shot(),
+            Self::Snooker(game) => game.begin_shot(state),
+            Self::Carom(game) => game.begin_shot(state),
+            Self::StraightPool(game) => game.begin_shot(),
+        }
+    }
+
+    /// Feeds an event of the current shot to the ruleset.
+    fn observe(&mut self, event: &Event) {
+        match self {
+            Self::EightBall(game) => game.observe(event),
+            Self::Rotation(game) => game.observe(event),
+            Self::Snooker(game) => game.observe(event),
+            Self::Carom(game) => game.observe(event),
+            Self::StraightPool(game) => game.observe(event),
+        }
+    }
+
+    /// Judges the shot that has just come to rest.
+    fn end_shot(&mut self, state: &mut GameState) {
+        match self {
+            Self::EightBall(game) => game.end_shot(state),
+            Self::Rotation(game) => game.end_shot(state),
+            Self::Snooker(game) => game.end_shot(state),
+            Self::Carom(game) => game.end_shot(state),
+            Self::StraightPool(game) => game.end_shot(state),
+        }
+    }
+}
+
+/// Opens a shot under the rules of `state`, if it has any. Returns `false`
+/// if the rules do not allow a shot now.
+pub fn begin_shot(state: &mut GameState) -> bool {
+    let Some(mut rules) = state.rules.take() else {
+        return true;
+    };
+    let allowed = rules.begin_shot(state);
+    state.rules = Some(rules);
+    allowed
+}
+
+/// Feeds the events recorded from index `from` onwards to the rules of
+/// `state`, judging the shot if the table has come to rest.
+///
+/// The table is at rest once no ball is moving, whether or not a stop was
+/// recorded: a shot that set nothing in motion never records one, and would
+/// otherwise stay open for good.
+pub fn observe(state: &mut GameState, from: usize) {
+    let Some(mut rules) = state.rules.take() else {
+        return;
+    };
+    for event in &state.events[from..] {
+        rules.observe(event);
+    }
+    if !state.balls.iter().any(Ball::is_moving) {
+        rules.end_shot(state);
+    }
+    state.rules = Some(rules);
+}
+
+/// Returns the ball with id `id`, on the table or in a pocket.
+pub fn any_ball(state: &GameState, id: u32) -> Option<Ball> {
+    state.find_ball(id).cloned().or_else(|| {
+        state
+            .pocketed
+            .iter()
+            .find(|record| record.ball_id() == id)
+            .map(PocketedBall::ball)
+    })
+}
+
+/// Returns `true` if the ball with id `id` is in `state`, on the table or in
+/// a pocket.
+fn exists(state: &GameState, id: u32) -> bool {
+    state.is_on_table(id) || state.pocketed.iter().any(|record| record.ball_id() == id)
+}
+
+/// Returns `true` if `player` is one of the two players, 0 or 1.
+const fn is_player(player: u32) -> bool {
+    player < 2
+}
+
+/// Returns the kind of the ball with id `id`, on the table or in a pocket.
+fn kind_of(state: &GameState, id: u32) -> Option<BallKind> {
+    any_ball(state, id).map(|ball| ball.kind)
+}
+
+/// Returns the number of the ball with id `id`, on the table or in a pocket.
+fn number_of(state: &GameState, id: u32) -> Option<u32> {
+    any_ball(state, id).map(|ball| ball.number)
+}
+
+/// Returns `true` if a ball of `radius` at `position` lies on the cloth and
+/// clear of every ball other than `id`.
+pub fn is_clear(state: &GameState, id: u32, position: Vector2D, radius: f32) -> bool {
+    let table = state.table;
+    (radius..=table.width - radius).contains(&position.x)
+        && (radius..=table.height - radius).contains(&position.y)
+        && state
+            .balls
+            .iter()
+            .filter(|ball| ball.id != id)
+            .all(|ball| (ball.position - position).length() >= ball.radius + radius)
+}
+
+/// Puts ball `id` at rest at `position`, taking it out of its pocket if it
+/// has dropped. Returns `false` if there is no such ball or the spot is not
+/// clear.
+pub fn place_ball(state: &mut GameState, id: u32, position: Vector2D) -> bool {
+    let radius = match state.find_ball(id) {
+        Some(ball) => ball.radius,
+        None => match state.pocketed.iter().find(|record| record.ball_id() == id) {
+            Some(record) => record.ball().radius,
+            None => return false,
+        },
+    };
+    if !is_clear(state, id, position, radius) {
+        return false;
+    }
+
+    if let Some(index) = state
+        .pocketed
+        .iter()
+        .position(|record| record.ball_id() == id)
+    {
+        let mut ball = state.pocketed.remove(index).ball();
+        ball.position = position;
+        state.balls.push(ball);
+        state.balls.sort_unstable_by_key(|ball| ball.id);
+    }
+    if let Some(ball) = state.balls.iter_mut().find(|ball| ball.id == id) {
+        ball.position = position;
+        ball.velocity = Vector2D::ZERO;
+        ball.angular_velocity = crate::Vector3D::ZERO;
+    }
+    true
+}
+
+/// Returns ball `id` to the table on `spot`, or as close behind it (towards
+/// `+x`) as the other balls allow.
+pub fn spot_ball(state: &mut GameState, id: u32, spot: Vector2D) {
+    let width = state.table.width;
+    let behind = (0_u16..)
+        .map(f32::from)
+        .take_while(|offset| spot.x + offset < width);
+    for offset in behind {
+        if place_ball(state, id, spot + Vector2D::new(offset, 0.0)) {
+            return;
+        }
+    }
+    // The line behind the spot is full; work back towards the head instead.
+    let ahead = (1_u16..)
+        .map(f32::from)
+        .take_while(|offset| spot.x - offset > 0.0);
+    for offset in ahead {
+        if place_ball(state, id, spot - Vector2D::new(offset, 0.0)) {
+            return;
+        }
+    }
+}
+
+impl Encode for ShotRecord {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.first_contact.encode(out);
+        self.pocketed.encode(out);
+        self.rail_after_contact.encode(out);
+        self.object_balls_to_rail.encode(out);
+        self.scratched.encode(out);
+    }
+}
+
+impl Decode for ShotRecord {
+    fn decode(input: &mut Reader<'_>) -> Result<Self, SnapshotError> {
+        Ok(Self {
+            first_contact: input.read()?,
+            pocketed: input.read()?,
+            rail_after_contact: input.read()?,
+            object_balls_to_rail: input.read()?,
+            scratched: input.read()?,
+        })
+    }
+}
+
+impl Encode for Rules {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Self::EightBall(game) => {
+                0_u8.encode(out);
+                game.encode(out);
+            }
+            Self::Rotation(game) => {
+                1_u8.encode(out);
+                game.encode(out);
+            }
+            Self::Snooker(game) => {
+                2_u8.encode(out);
+                game.encode(out);
+            }
+            Self::Carom(game) => {
+                3_u8.encode(out);
+                game.encode(out);
+            }
+            Self::StraightPool(game) => {
+                4_u8.encode(out);
+                game.encode(out);
+            }
+        }
+    }
+}
+
+impl Decode for Rules {
+    fn decode(input: &mut Reader<'_>) -> Result<Self, SnapshotError> {
+        match input.read::<u8>()? {
+            0 => input.read().map(Self::EightBall),
+            1 => input.read().map(Self::Rotation),
+            2 => input.read().map(Self::Snooker),
+            3 => input.read().map(Self::Carom),
+            4 => input.read().map(Self::StraightPool),
+            _ => Err(SnapshotError::Invalid),
+        }
+    }
+}
+
+/// Fixtures shared by the tests of each ruleset and of matches.
+#[cfg(test)]
+pub mod testing {
+    use std::f32::consts::FRAC_PI_2;
+
+    use super::Rules;
+    use crate::{tick, Ball, CueStroke, GameState, Table};
+
+    /// A game under `rules` on an 800 by 400 pool table.
+    pub fn pool_game(balls: Vec<Ball>, rules: Rules) -> GameState {
+        GameState::new(Table::pool(800.0, 400.0), balls).with_rules(rules)
+    }
+
+    /// Strikes the cue ball at `angle` and `speed` and lets the shot play
+    /// out. Returns `false` if the strike was refused.
+    pub fn shoot(state: &mut GameState, angle: f32, speed: f32) -> bool {
+        let struck = state.strike(&CueStroke::new(angle, speed, 0.0, 0.0, 0.0));
+        for _ in 0..600 {
+            tick(state, 1.0 / 60.0);
+        }
+        struck
+    }
+
+    /// Strikes the cue ball straight up the table at `speed` and lets the
+    /// shot play out. Returns `false` if the strike was refused.
+    pub fn shoot_up(state: &mut GameState, speed: f32) -> bool {
+        shoot(state, -FRAC_PI_2, speed)
+    }
+
+    /// The cue ball lined up to send pool ball `number` into the top side
+    /// pocket, with `others` along the bottom rail.
+    pub fn pot_in_top_side(number: u32, others: &[u32]) -> Vec<Ball> {
+        let mut balls = vec![
+            Ball::pool(0, 400.0, 140.0),
+            Ball::pool(number, 400.0, 100.0),
+        ];
+        let spare = (0_u16..).map(|i| 100.0 + 30.0 * f32::from(i));
+        balls.extend(
+            others
+                .iter()
+                .zip(spare)
+                .map(|(&n, x)| Ball::pool(n, x, 350.0)),
+        );
+        balls
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event_stream() -> Vec<Event> {
+        vec![
+            Event::cushion_contact(0.1, 0, crate::Rail::Top, 100.0),
+            Event::ball_contact(0.2, 3, 0, 200.0),
+            Event::ball_contact(0.3, 3, 5, 150.0),
+            Event::cushion_contact(0.4, 5, crate::Rail::Right, 80.0),
+            Event::cushion_contact(0.5, 5, crate::Rail::Top, 40.0),
+            Event::pocketed(0.6, 3, Pocket::TopRight),
+            Event::pocketed(0.7, 0, Pocket::BottomLeft),
+        ]
+    }
+
+    #[test]
+    fn shot_record_follows_the_cue_ball() {
+        let mut record = ShotRecord::default();
+        for event in event_stream() {
+            record.observe(&event, 0);
+        }
+
+        assert_eq!(record.first_contact, Some(3));
+        assert!(record.rail_after_contact);
+        assert_eq!(record.object_balls_to_rail, [5]);
+        assert_eq!(record.pocketed, [(3, Pocket::TopRight)]);
+        assert!(record.scratched);
+    }
+
+    #[test]
+    fn cushion_before_contact_does_not_count_as_a_rail() {
+        let mut record = ShotRecord::default();
+        for event in &event_stream()[..2] {
+            record.observe(event, 0);
+        }
+        assert!(!record.rail_after_contact);
+    }
+
+    #[test]
+    fn spotted_ball_moves_behind_an_occupied_spot() {
+        let table = crate::Table::pool(800.0, 400.0);
+        let mut state = GameState::new(
+            table,
+            vec![Ball::pool(0, 600.0, 200.0), Ball::pool(8, 400.0, 300.0)],
+        );
+        state.balls[1].velocity = Vector2D::new(0.0, 500.0);
+        for _ in 0..30 {
+            crate::tick(&mut state, 1.0 / 60.0);
+        }
+        assert!(!state.is_on_table(1));
+
+        spot_ball(&mut state, 1, Vector2D::new(600.0, 200.0));
+
+        let eight = state.find_ball(1).map_or(0.0, |ball| ball.position.x);
+        assert!(eight >= 600.0 + 2.0 * crate::POOL_BALL_RADIUS);
+        assert!(state.pocketed_by_id(1).is_none());
+    }
+
+    #[test]
+    fn shot_that_moves_nothing_is_still_judged() {
+        let mut state = GameState::eight_ball(&crate::Table::pool(800.0, 400.0), 1, 0.0);
+        assert!(begin_shot(&mut state));
+        assert!(!begin_shot(&mut state));
+
+        crate::tick(&mut state, 1.0 / 60.0);
+
+        assert!(state.events().is_empty());
+        assert_eq!(state.last_foul(), Some(Foul::NoContact));
+        assert_eq!(state.current_player(), Some(1));
+        assert!(state.strike(&crate::CueStroke::new(0.0, 900.0, 0.0, 0.0, 0.0)));
+    }
+}
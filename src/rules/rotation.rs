@@ -0,0 +1,448 @@
+//! Rotation games: nine-ball and ten-ball, following the WPA rules.
+//!
+//! The cue ball must always hit the lowest-numbered ball on the table
+//! first, and then a ball must drop or touch a cushion. Any ball pocketed on
+//! a legal shot keeps the shooter at the table, and pocketing the money ball
+//! (the 9 or the 10) legally wins, whether directly or in a combination.
+//! After a foul the opponent has the cue ball in hand anywhere, and a player
+//! who fouls three times in a row loses.
+//!
+//! The player who takes the first shot after the break may declare a
+//! push-out: that shot need not hit anything, and the opponent then chooses
+//! whether to play on or pass the table back.
+//!
+//! Ten-ball is call-shot. A ball pocketed other than as called stays down
+//! but ends the turn, and the 10 only wins when it is called; it is spotted
+//! if it drops any other way, including on the break.
+
+use super::{exists, is_player, number_of, spot_ball, BallInHand, Foul, ShotRecord};
+use crate::snapshot::{tagged, Decode, Encode, Reader, SnapshotError};
+use crate::{Event, GameState, Pocket, Vector2D, CUE_BALL_ID};
+
+/// Object balls that must reach a cushion on a break that pockets nothing.
+const BREAK_RAIL_BALLS: usize = 4;
+
+/// Consecutive fouls that lose the game.
+const FOUL_LIMIT: u32 = 3;
+
+/// Where the game stands with respect to the push-out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+enum PushOut {
+    /// The coming shot cannot be a push-out.
+    Unavailable,
+    /// The coming shot may be declared a push-out.
+    Available,
+    /// The coming shot has been declared a push-out.
+    Declared,
+    /// The last shot was a push-out; the player to shoot may pass.
+    Played,
+}
+
+/// The state of a game of nine-ball or ten-ball between two players.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rotation {
+    /// Id of the cue ball.
+    cue: u32,
+    /// Number of the ball that wins the game.
+    money: u32,
+    /// Whether every shot must be called.
+    call_shot: bool,
+    /// The player to shoot, 0 or 1.
+    player: u32,
+    /// Consecutive fouls of each player.
+    fouls: [u32; 2],
+    /// `true` until the break has been played.
+    breaking: bool,
+    /// Push-out state around the coming shot.
+    push_out: PushOut,
+    /// The ball and pocket called for the coming shot.
+    called: Option<(u32, Pocket)>,
+    /// Where the cue ball may be placed before the coming shot.
+    ball_in_hand: BallInHand,
+    /// The foul committed on the last shot.
+    last_foul: Option<Foul>,
+    /// The winner, once the game is over.
+    winner: Option<u32>,
+    /// The shot being played, if any.
+    shot: Option<ShotRecord>,
+}
+
+impl Rotation {
+    /// Starts a game of nine-ball with player 0 to break.
+    #[must_use]
+    pub const fn nine_ball() -> Self {
+        Self::new(9, false)
+    }
+
+    /// Starts a game of ten-ball with player 0 to break.
+    #[must_use]
+    pub const fn ten_ball() -> Self {
+        Self::new(10, true)
+    }
+
+    /// Starts a game won by the ball numbered `money`.
+    const fn new(money: u32, call_shot: bool) -> Self {
+        Self {
+            cue: CUE_BALL_ID,
+            money,
+            call_shot,
+            player: 0,
+            fouls: [0, 0],
+            breaking: true,
+            push_out: PushOut::Unavailable,
+            called: None,
+            ball_in_hand: BallInHand::Kitchen,
+            last_foul: None,
+            winner: None,
+            shot: None,
+        }
+    }
+
+    /// Returns the player to shoot.
+    #[must_use]
+    pub const fn current_player(&self) -> u32 {
+        self.player
+    }
+
+    /// Returns the winner, once the game is over.
+    #[must_use]
+    pub const fn winner(&self) -> Option<u32> {
+        self.winner
+    }
+
+    /// Returns the foul committed on the last shot, if any.
+    #[must_use]
+    pub const fn last_foul(&self) -> Option<Foul> {
+        self.last_foul
+    }
+
+    /// Returns where the cue ball may be placed before the next shot.
+    #[must_use]
+    pub const fn ball_in_hand(&self) -> BallInHand {
+        self.ball_in_hand
+    }
+
+    /// Returns the ids of the balls the player to shoot may hit first: the
+    /// lowest-numbered ball, or any ball on a push-out.
+    #[must_use]
+    pub fn targets(&self, state: &GameState) -> Vec<u32> {
+        let objects = state.balls.iter().filter(|ball| ball.id != self.cue);
+        if self.push_out == PushOut::Declared {
+            return objects.map(|ball| ball.id).collect();
+        }
+        objects
+            .min_by_key(|ball| ball.number)
+            .map(|ball| ball.id)
+            .into_iter()
+            .collect()
+    }
+
+    /// Returns how many fouls in a row `player` has committed.
+    #[must_use]
+    pub fn consecutive_fouls(&self, player: u32) -> u32 {
+        self.fouls.get(player as usize).copied().unwrap_or(0)
+    }
+
+    /// Returns the number of the ball that wins the game.
+    #[must_use]
+    pub const fn money_ball(&self) -> u32 {
+        self.money
+    }
+
+    /// Calls `ball` into `pocket` for the coming shot.
+    pub const fn call_shot(&mut self, ball: u32, pocket: Pocket) {
+        self.called = Some((ball, pocket));
+    }
+
+    /// Declares the coming shot a push-out. Returns `false` unless it is the
+    /// first shot after the break.
+    pub fn push_out(&mut self) -> bool {
+        if self.push_out == PushOut::Available && self.shot.is_none() {
+            self.push_out = PushOut::Declared;
+        }
+        self.push_out == PushOut::Declared
+    }
+
+    /// Hands the table back to the player who pushed out. Returns `false`
+    /// unless the last shot was a push-out.
+    pub fn pass(&mut self) -> bool {
+        if self.push_out != PushOut::Played || self.shot.is_some() {
+            return false;
+        }
+        self.push_out = PushOut::Unavailable;
+        self.player = 1 - self.player;
+        true
+    }
+
+    /// Starts a shot. Returns `false` once the game is over or while a shot
+    /// is still running.
+    pub fn begin_shot(&mut self) -> bool {
+        if self.winner.is_some() || self.shot.is_some() {
+            return false;
+        }
+        self.ball_in_hand = BallInHand::No;
+        if self.push_out != PushOut::Declared {
+            self.push_out = PushOut::Unavailable;
+        }
+        self.shot = Some(ShotRecord::default());
+        true
+    }
+
+    /// Adds an event of the running shot.
+    pub fn observe(&mut self, event: &Event) {
+        if let Some(shot) = &mut self.shot {
+            shot.observe(event, self.cue);
+        }
+    }
+
+    /// Returns `true` if the players this game records are 0 or 1 and every
+    /// ball it refers to is in `state`, on the table or in a pocket.
+    /// Neither player may have more fouls in a row than lose the game.
+    #[must_use]
+    pub fn is_consistent(&self, state: &GameState) -> bool {
+        is_player(self.player)
+            && self.winner.is_none_or(is_player)
+            && exists(state, self.cue)
+            && self.called.is_none_or(|(id, _)| exists(state, id))
+            && self.fouls.iter().all(|&fouls| fouls <= FOUL_LIMIT)
+            && self
+                .shot
+                .as_ref()
+                .is_none_or(|shot| shot.is_consistent(state))
+    }
+
+    /// Judges the shot that has just come to rest.
+    pub fn end_shot(&mut self, state: &mut GameState) {
+        let Some(shot) = self.shot.take() else {
+            return;
+        };
+        let breaking = std::mem::take(&mut self.breaking);
+        let pushing = self.push_out == PushOut::Declared;
+        let called = self.called.take();
+
+        let number = |id| number_of(state, id);
+        let lowest = state
+            .balls
+            .iter()
+            .map(|ball| ball.number)
+            .chain(shot.pocketed.iter().filter_map(|(id, _)| number(*id)))
+            .filter(|&n| n > 0)
+            .min();
+        let money = shot
+            .pocketed
+            .iter()
+            .find(|(id, _)| number(*id) == Some(self.money))
+            .copied();
+        let others_down = shot
+            .pocketed
+            .iter()
+            .any(|(id, _)| number(*id) != Some(self.money));
+        let call_made =
+            !self.call_shot || breaking || called.is_some_and(|call| shot.pocketed.contains(&call));
+
+        let foul = if shot.scratched {
+            Some(Foul::Scratch)
+        } else if pushing {
+            None
+        } else if shot.first_contact.is_none() {
+            Some(Foul::NoContact)
+        } else if shot.first_contact.and_then(number) != lowest {
+            Some(Foul::WrongBallFirst)
+        } else if breaking
+            && shot.pocketed.is_empty()
+            && shot.object_balls_to_rail.len() < BREAK_RAIL_BALLS
+        {
+            Some(Foul::IllegalBreak)
+        } else if shot.pocketed.is_empty() && !shot.rail_after_contact {
+            Some(Foul::NoRail)
+        } else {
+            None
+        };
+        self.last_foul = foul;
+
+        if let Some((id, pocket)) = money {
+            let money_called = !self.call_shot || called == Some((id, pocket));
+            if foul.is_none() && !pushing && money_called && !(breaking && self.call_shot) {
+                self.winner = Some(self.player);
+                return;
+            }
+            let foot_spot = Vector2D::new(state.table.width * 0.75, state.table.height * 0.5);
+            spot_ball(state, id, foot_spot);
+        }
+
+        let shooter = self.player as usize;
+        if foul.is_some() {
+            self.fouls[shooter] += 1;
+            if self.fouls[shooter] >= FOUL_LIMIT {
+                self.winner = Some(1 - self.player);
+                return;
+            }
+            self.ball_in_hand = BallInHand::Anywhere;
+            self.player = 1 - self.player;
+        } else {
+            self.fouls[shooter] = 0;
+            if pushing || !(others_down && call_made) {
+                self.player = 1 - self.player;
+            }
+        }
+        self.push_out = if pushing && foul.is_none() {
+            PushOut::Played
+        } else if breaking {
+            PushOut::Available
+        } else {
+            PushOut::Unavailable
+        };
+    }
+}
+
+tagged! {
+    PushOut { Unavailable, Available, Declared, Played }
+}
+
+impl Encode for Rotation {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.cue.encode(out);
+        self.money.encode(out);
+        self.call_shot.encode(out);
+        self.player.encode(out);
+        self.fouls.encode(out);
+        self.breaking.encode(out);
+        self.push_out.encode(out);
+        self.called.encode(out);
+        self.ball_in_hand.encode(out);
+        self.last_foul.encode(out);
+        self.winner.encode(out);
+        self.shot.encode(out);
+    }
+}
+
+impl Decode for Rotation {
+    fn decode(input: &mut Reader<'_>) -> Result<Self, SnapshotError> {
+        Ok(Self {
+            cue: input.read()?,
+            money: input.read()?,
+            call_shot: input.read()?,
+            player: input.read()?,
+            fouls: input.read()?,
+            breaking: input.read()?,
+            push_out: input.read()?,
+            called: input.read()?,
+            ball_in_hand: input.read()?,
+            last_foul: input.read()?,
+            winner: input.read()?,
+            shot: input.read()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::testing::{pool_game, pot_in_top_side, shoot_up};
+    use crate::{Ball, Rules};
+
+    /// A nine-ball or ten-ball game past the break.
+    fn game(rules: Rotation, balls: Vec<Ball>) -> GameState {
+        let mut rules = rules;
+        rules.breaking = false;
+        rules.ball_in_hand = BallInHand::No;
+        pool_game(balls, Rules::Rotation(rules))
+    }
+
+    #[test]
+    fn lowest_ball_must_be_hit_first() {
+        let mut state = game(Rotation::nine_ball(), pot_in_top_side(4, &[2, 9]));
+
+        assert!(shoot_up(&mut state, 200.0));
+
+        assert_eq!(state.last_foul(), Some(Foul::WrongBallFirst));
+        assert_eq!(state.current_player(), Some(1));
+        assert_eq!(state.ball_in_hand(), BallInHand::Anywhere);
+    }
+
+    #[test]
+    fn legal_pot_keeps_the_shooter_at_the_table() {
+        let mut state = game(Rotation::nine_ball(), pot_in_top_side(2, &[4, 9]));
+
+        assert!(shoot_up(&mut state, 200.0));
+
+        assert_eq!(state.last_foul(), None);
+        assert_eq!(state.current_player(), Some(0));
+        assert_eq!(state.winner(), None);
+    }
+
+    #[test]
+    fn nine_on_a_legal_shot_wins() {
+        let mut state = game(Rotation::nine_ball(), pot_in_top_side(9, &[]));
+
+        assert!(shoot_up(&mut state, 200.0));
+
+        assert_eq!(state.winner(), Some(0));
+    }
+
+    #[test]
+    fn nine_on_a_foul_is_spotted() {
+        let mut state = game(Rotation::nine_ball(), pot_in_top_side(9, &[3]));
+
+        assert!(shoot_up(&mut state, 200.0));
+
+        assert_eq!(state.winner(), None);
+        assert_eq!(state.last_foul(), Some(Foul::WrongBallFirst));
+        assert!(state.is_on_table(1));
+    }
+
+    #[test]
+    fn three_fouls_in_a_row_lose() {
+        let mut rules = Rotation::nine_ball();
+        rules.fouls = [2, 0];
+        let mut state = game(rules, pot_in_top_side(4, &[2, 9]));
+
+        assert!(shoot_up(&mut state, 200.0));
+
+        assert_eq!(state.winner(), Some(1));
+    }
+
+    #[test]
+    fn push_out_may_be_passed_back() {
+        let mut rules = Rotation::nine_ball();
+        rules.push_out = PushOut::Available;
+        // The 2 is nowhere near the line of the shot.
+        let mut state = game(
+            rules,
+            vec![Ball::pool(0, 400.0, 300.0), Ball::pool(2, 100.0, 100.0)],
+        );
+        assert!(state.push_out());
+
+        assert!(shoot_up(&mut state, 60.0));
+
+        assert_eq!(state.last_foul(), None);
+        assert_eq!(state.current_player(), Some(1));
+        assert!(state.pass());
+        assert_eq!(state.current_player(), Some(0));
+        assert!(!state.pass() && !state.push_out());
+    }
+
+    #[test]
+    fn ten_ball_needs_the_call() {
+        let mut state = game(Rotation::ten_ball(), pot_in_top_side(10, &[]));
+
+        assert!(shoot_up(&mut state, 200.0));
+
+        // Uncalled: the 10 comes back up and the turn passes.
+        assert_eq!(state.winner(), None);
+        assert!(state.is_on_table(1));
+        assert_eq!(state.current_player(), Some(1));
+    }
+
+    #[test]
+    fn ten_ball_called_into_its_pocket_wins() {
+        let mut state = game(Rotation::ten_ball(), pot_in_top_side(10, &[]));
+        assert!(state.call_shot(1, Pocket::TopSide));
+
+        assert!(shoot_up(&mut state, 200.0));
+
+        assert_eq!(state.winner(), Some(0));
+    }
+}
@@ -0,0 +1,734 @@
+//! Snooker, following the WPBSA rules.
+//!
+//! Reds and colours alternate while reds remain: a player on a red may pot
+//! any number of reds, and then one colour, which is re-spotted. Once the
+//! reds are gone the colours are taken in order, yellow to black, and stay
+//! down. A frame ends when the black drops for the last time; if the scores
+//! are then level the black is re-spotted and played for.
+//!
+//! A foul gives the opponent the value of the ball on or of any ball
+//! wrongly hit or pocketed, and never less than four points. The opponent
+//! has a free ball if the foul leaves them snookered, and after a foul that
+//! failed to hit the ball on although it was in sight they may have the
+//! balls put back and make the offender play again.
+
+use super::{exists, is_player, kind_of, place_ball, spot_ball, BallInHand, Foul, ShotRecord};
+use crate::snapshot::{Decode, Encode, Reader, SnapshotError};
+use crate::{rack, Ball, BallKind, Event, GameState, PocketedBall, Vector2D, CUE_BALL_ID};
+
+/// The least a foul costs.
+const MIN_PENALTY: u32 = 4;
+
+/// The ball a player must hit first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+enum On {
+    /// Any red.
+    Red,
+    /// Any one colour, after a red has been potted.
+    AnyColour,
+    /// This colour, once the reds are gone.
+    Colour(BallKind),
+}
+
+impl On {
+    /// Returns what is on for the incoming player in the position `state`
+    /// shows, or `None` once every object ball is down.
+    fn next(state: &GameState) -> Option<Self> {
+        let remains = |kind| state.balls.iter().any(|ball| ball.kind == kind);
+        if remains(BallKind::Red) {
+            return Some(Self::Red);
+        }
+        BallKind::COLOURS
+            .into_iter()
+            .find(|&kind| remains(kind))
+            .map(Self::Colour)
+    }
+
+    /// Returns `true` if a ball of `kind` may be played.
+    fn includes(self, kind: BallKind) -> bool {
+        match self {
+            Self::Red => kind == BallKind::Red,
+            Self::AnyColour => kind.is_colour(),
+            Self::Colour(colour) => kind == colour,
+        }
+    }
+}
+
+/// The table and turn as they were before a shot, kept so that a miss can
+/// be replayed.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Layout {
+    /// Balls on the table.
+    balls: Vec<Ball>,
+    /// Balls in the pockets.
+    pocketed: Vec<PocketedBall>,
+    /// What was on.
+    on: On,
+    /// Whether the shot was a free ball.
+    free_ball: bool,
+    /// Whether some part of a ball on was in sight of the cue ball.
+    in_sight: bool,
+}
+
+/// The state of a frame of snooker between two players.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Snooker {
+    /// Id of the cue ball.
+    cue: u32,
+    /// The player to shoot, 0 or 1.
+    player: u32,
+    /// Points scored by each player.
+    scores: [u32; 2],
+    /// What the player to shoot is on.
+    on: On,
+    /// The ball nominated for the coming shot.
+    nominated: Option<u32>,
+    /// Whether the coming shot is a free ball.
+    free_ball: bool,
+    /// Where the cue ball may be placed before the coming shot.
+    ball_in_hand: BallInHand,
+    /// The foul committed on the last shot.
+    last_foul: Option<Foul>,
+    /// Points awarded for the last foul.
+    penalty: u32,
+    /// Whether the last foul was also a miss.
+    miss: bool,
+    /// The winner, once the frame is over.
+    winner: Option<u32>,
+    /// The position before the last shot.
+    before: Option<Layout>,
+    /// The shot being played, if any.
+    shot: Option<ShotRecord>,
+}
+
+impl Default for Snooker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Snooker {
+    /// Starts a frame with player 0 to break from the D.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            cue: CUE_BALL_ID,
+            player: 0,
+            scores: [0, 0],
+            on: On::Red,
+            nominated: None,
+            free_ball: false,
+            ball_in_hand: BallInHand::InD,
+            last_foul: None,
+            penalty: 0,
+            miss: false,
+            winner: None,
+            before: None,
+            shot: None,
+        }
+    }
+
+    /// Returns the player to shoot.
+    #[must_use]
+    pub const fn current_player(&self) -> u32 {
+        self.player
+    }
+
+    /// Returns the winner, once the frame is over.
+    #[must_use]
+    pub const fn winner(&self) -> Option<u32> {
+        self.winner
+    }
+
+    /// Returns the foul committed on the last shot, if any.
+    #[must_use]
+    pub const fn last_foul(&self) -> Option<Foul> {
+        self.last_foul
+    }
+
+    /// Returns where the cue ball may be placed before the next shot.
+    #[must_use]
+    pub const fn ball_in_hand(&self) -> BallInHand {
+        self.ball_in_hand
+    }
+
+    /// Returns the ids of the balls the player to shoot may hit first: the
+    /// balls on, or any ball on a free ball.
+    #[must_use]
+    pub fn targets(&self, state: &GameState) -> Vec<u32> {
+        state
+            .balls
+            .iter()
+            .filter(|ball| ball.id != self.cue && (self.free_ball || self.on.includes(ball.kind)))
+            .map(|ball| ball.id)
+            .collect()
+    }
+
+    /// Returns the points scored by `player`.
+    #[must_use]
+    pub fn score(&self, player: u32) -> u32 {
+        self.scores.get(player as usize).copied().unwrap_or(0)
+    }
+
+    /// Returns the points the last foul gave away, or zero.
+    #[must_use]
+    pub const fn penalty(&self) -> u32 {
+        self.penalty
+    }
+
+    /// Returns the kind of ball that is on: [`BallKind::Red`], a colour once
+    /// the reds are gone, or `None` while any colour may be played.
+    #[must_use]
+    pub const fn ball_on(&self) -> Option<BallKind> {
+        match self.on {
+            On::Red => Some(BallKind::Red),
+            On::AnyColour => None,
+            On::Colour(colour) => Some(colour),
+        }
+    }
+
+    /// Returns `true` if the coming shot is a free ball.
+    #[must_use]
+    pub const fn free_ball(&self) -> bool {
+        self.free_ball
+    }
+
+    /// Returns `true` if the last shot was called a foul and a miss.
+    #[must_use]
+    pub const fn missed(&self) -> bool {
+        self.miss
+    }
+
+    /// Nominates ball `id` for the coming shot: the colour to be potted
+    /// after a red, or the ball to play as a free ball.
+    ///
+    /// Without a nomination, the first ball the cue ball hits is taken as
+    /// nominated.
+    pub const fn nominate(&mut self, id: u32) {
+        self.nominated = Some(id);
+    }
+
+    /// After a miss, puts the balls back as they were and has the offender
+    /// play again. Returns `false` if the last shot was not a miss.
+    pub fn replay(&mut self, state: &mut GameState) -> bool {
+        if !self.miss || self.shot.is_some() {
+            return false;
+        }
+        let Some(before) = self.before.take() else {
+            return false;
+        };
+        state.balls = before.balls;
+        state.pocketed = before.pocketed;
+        self.on = before.on;
+        self.free_ball = before.free_ball;
+        self.ball_in_hand = BallInHand::No;
+        self.player = 1 - self.player;
+        self.miss = false;
+        true
+    }
+
+    /// Starts a shot. Returns `false` once the frame is over or while a shot
+    /// is still running.
+    pub fn begin_shot(&mut self, state: &GameState) -> bool {
+        if self.winner.is_some() || self.shot.is_some() {
+            return false;
+        }
+        let in_sight = self.free_ball
+            || state.find_ball(self.cue).is_some_and(|cue| {
+                state
+                    .balls
+                    .iter()
+                    .filter(|ball| self.on.includes(ball.kind))
+                    .any(|ball| edges_in_sight(state, cue, ball) > 0)
+            });
+        self.before = Some(Layout {
+            balls: state.balls.clone(),
+            pocketed: state.pocketed.clone(),
+            on: self.on,
+            free_ball: self.free_ball,
+            in_sight,
+        });
+        self.ball_in_hand = BallInHand::No;
+        self.miss = false;
+        self.shot = Some(ShotRecord::default());
+        true
+    }
+
+    /// Adds an event of the running shot.
+    pub fn observe(&mut self, event: &Event) {
+        if let Some(shot) = &mut self.shot {
+            shot.observe(event, self.cue);
+        }
+    }
+
+    /// Returns `true` if the players this game records are 0 or 1 and every
+    /// ball it refers to is in `state`, on the table or in a pocket.
+    /// The position kept from before the last shot must be one balls could
+    /// be in.
+    #[must_use]
+    pub fn is_consistent(&self, state: &GameState) -> bool {
+        is_player(self.player)
+            && self.winner.is_none_or(is_player)
+            && exists(state, self.cue)
+            && self.nominated.is_none_or(|id| exists(state, id))
+            && self.before.as_ref().is_none_or(|before| {
+                before.balls.iter().all(Ball::is_finite)
+                    && before
+                        .pocketed
+                        .iter()
+                        .all(|record| record.ball().is_finite())
+            })
+            && self
+                .shot
+                .as_ref()
+                .is_none_or(|shot| shot.is_consistent(state))
+    }
+
+    /// Judges the shot that has just come to rest.
+    pub fn end_shot(&mut self, state: &mut GameState) {
+        let Some(shot) = self.shot.take() else {
+            return;
+        };
+        let nominated = self.nominated.take();
+        let free_ball = std::mem::take(&mut self.free_ball);
+        let on = self.on;
+
+        let kind = |id| kind_of(state, id).unwrap_or(BallKind::Plain);
+        let first = shot.first_contact;
+        // A free ball stands in for the ball on; after a red, the colour
+        // played is the one nominated.
+        let stand_in = nominated.or(first).filter(|_| free_ball);
+        let colour = nominated
+            .or(first)
+            .filter(|&id| on == On::AnyColour && kind(id).is_colour());
+        let is_on = |id: u32| {
+            Some(id) == stand_in
+                || match on {
+                    On::AnyColour => Some(id) == colour,
+                    _ => on.includes(kind(id)),
+                }
+        };
+        let on_value = match on {
+            On::Red => 1,
+            On::AnyColour => colour.map_or(0, |id| kind(id).value()),
+            On::Colour(colour) => colour.value(),
+        };
+        let potted: Vec<u32> = shot.pocketed.iter().map(|(id, _)| *id).collect();
+
+        let foul = if shot.scratched {
+            Some(Foul::Scratch)
+        } else if first.is_none() {
+            Some(Foul::NoContact)
+        } else if !first.is_some_and(is_on) {
+            Some(Foul::WrongBallFirst)
+        } else if !potted.iter().all(|&id| is_on(id)) {
+            Some(Foul::WrongBallPotted)
+        } else {
+            None
+        };
+        self.last_foul = foul;
+        let penalty = first
+            .iter()
+            .chain(&potted)
+            .map(|&id| kind(id).value())
+            .chain([MIN_PENALTY, on_value])
+            .max()
+            .unwrap_or(MIN_PENALTY);
+
+        // Colours come back up unless they were potted in sequence.
+        let mut respot: Vec<(u32, BallKind)> = potted
+            .iter()
+            .map(|&id| (id, kind(id)))
+            .filter(|&(id, colour)| {
+                colour.is_colour()
+                    && (foul.is_some() || Some(id) == stand_in || on == On::AnyColour)
+            })
+            .collect();
+        respot.sort_unstable_by_key(|&(_, colour)| std::cmp::Reverse(colour.value()));
+        for (id, colour) in respot {
+            respot_colour(state, id, colour);
+        }
+
+        let shooter = self.player as usize;
+        if let Some(foul) = foul {
+            self.penalty = penalty;
+            self.scores[1 - shooter] = self.scores[1 - shooter].saturating_add(self.penalty);
+            let in_sight = self.before.as_ref().is_some_and(|before| before.in_sight);
+            self.miss = matches!(foul, Foul::NoContact | Foul::WrongBallFirst) && in_sight;
+            if shot.scratched {
+                self.ball_in_hand = BallInHand::InD;
+            }
+            self.player = 1 - self.player;
+            match On::next(state) {
+                Some(next) if on != On::Colour(BallKind::Black) => {
+                    self.on = next;
+                    self.free_ball = !shot.scratched && self.snookered(state);
+                }
+                _ => self.end_frame(state, shooter),
+            }
+            return;
+        }
+
+        self.penalty = 0;
+        let points = if on == On::Red {
+            u32::try_from(potted.len()).unwrap_or(u32::MAX)
+        } else if potted.is_empty() {
+            0
+        } else {
+            on_value
+        };
+        self.scores[shooter] = self.scores[shooter].saturating_add(points);
+        let next = On::next(state);
+        if points == 0 {
+            self.player = 1 - self.player;
+        }
+        match next {
+            Some(_) if points > 0 && on == On::Red => self.on = On::AnyColour,
+            Some(next) => self.on = next,
+            None => self.end_frame(state, shooter),
+        }
+    }
+
+    /// Ends the frame on the scores. If they are level the black is
+    /// re-spotted, and the player who did not take the last shot plays it
+    /// from the D.
+    fn end_frame(&mut self, state: &mut GameState, shooter: usize) {
+        self.winner = match self.scores[0].cmp(&self.scores[1]) {
+            std::cmp::Ordering::Greater => Some(0),
+            std::cmp::Ordering::Less => Some(1),
+            std::cmp::Ordering::Equal => None,
+        };
+        if self.winner.is_some() {
+            return;
+        }
+        let black = state
+            .pocketed
+            .iter()
+            .map(PocketedBall::ball)
+            .chain(state.balls.iter().cloned())
+            .find(|ball| ball.kind == BallKind::Black);
+        if let Some(black) = black {
+            respot_colour(state, black.id, BallKind::Black);
+        }
+        self.on = On::Colour(BallKind::Black);
+        self.ball_in_hand = BallInHand::InD;
+        self.player = u32::from(shooter == 0);
+    }
+
+    /// Returns `true` if the player to shoot cannot hit both sides of any
+    /// ball on in a straight line.
+    fn snookered(&self, state: &GameState) -> bool {
+        let Some(cue) = state.find_ball(self.cue) else {
+            return false;
+        };
+        !state
+            .balls
+            .iter()
+            .filter(|ball| self.on.includes(ball.kind))
+            .any(|ball| edges_in_sight(state, cue, ball) == 2)
+    }
+}
+
+/// Returns how many of the two extreme edges of `target` the cue ball can
+/// reach in a straight line without touching another ball first.
+fn edges_in_sight(state: &GameState, cue: &Ball, target: &Ball) -> usize {
+    let offset = target.position - cue.position;
+    let distance = offset.length();
+    if distance <= 0.0 {
+        return 2;
+    }
+    let across = Vector2D::new(-offset.y, offset.x) * ((cue.radius + target.radius) / distance);
+    [across, -across]
+        .into_iter()
+        .filter(|&side| {
+            let (start, end) = (cue.position, target.position + side);
+            let span = end - start;
+            state
+                .balls
+                .iter()
+                .filter(|ball| ball.id != cue.id && ball.id != target.id)
+                .all(|ball| {
+                    let along =
+                        ((ball.position - start).dot(span) / span.length_squared()).clamp(0.0, 1.0);
+                    (ball.position - (start + span * along)).length() >= ball.radius + cue.radius
+                })
+        })
+        .count()
+}
+
+/// Puts a colour back on its own spot or, if that is taken, on the highest
+/// valued spot that is free. With every spot taken it goes as close to its
+/// own spot as it can, towards the top cushion.
+fn respot_colour(state: &mut GameState, id: u32, colour: BallKind) {
+    let table = state.table;
+    let Some(own) = rack::colour_spot(table, colour) else {
+        return;
+    };
+    let spots = BallKind::COLOURS
+        .into_iter()
+        .rev()
+        .filter_map(|kind| rack::colour_spot(table, kind));
+    for spot in std::iter::once(own).chain(spots) {
+        if place_ball(state, id, spot) {
+            return;
+        }
+    }
+    spot_ball(state, id, own);
+}
+
+impl Encode for On {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Self::Red => 0_u8.encode(out),
+            Self::AnyColour => 1_u8.encode(out),
+            Self::Colour(kind) => {
+                2_u8.encode(out);
+                kind.encode(out);
+            }
+        }
+    }
+}
+
+impl Decode for On {
+    fn decode(input: &mut Reader<'_>) -> Result<Self, SnapshotError> {
+        match input.read::<u8>()? {
+            0 => Ok(Self::Red),
+            1 => Ok(Self::AnyColour),
+            2 => input.read().map(Self::Colour),
+            _ => Err(SnapshotError::Invalid),
+        }
+    }
+}
+
+impl Encode for Layout {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.balls.encode(out);
+        self.pocketed.encode(out);
+        self.on.encode(out);
+        self.free_ball.encode(out);
+        self.in_sight.encode(out);
+    }
+}
+
+impl Decode for Layout {
+    fn decode(input: &mut Reader<'_>) -> Result<Self, SnapshotError> {
+        Ok(Self {
+            balls: input.read()?,
+            pocketed: input.read()?,
+            on: input.read()?,
+            free_ball: input.read()?,
+            in_sight: input.read()?,
+        })
+    }
+}
+
+impl Encode for Snooker {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.cue.encode(out);
+        self.player.encode(out);
+        self.scores.encode(out);
+        self.on.encode(out);
+        self.nominated.encode(out);
+        self.free_ball.encode(out);
+        self.ball_in_hand.encode(out);
+        self.last_foul.encode(out);
+        self.penalty.encode(out);
+        self.miss.encode(out);
+        self.winner.encode(out);
+        self.before.encode(out);
+        self.shot.encode(out);
+    }
+}
+
+impl Decode for Snooker {
+    fn decode(input: &mut Reader<'_>) -> Result<Self, SnapshotError> {
+        Ok(Self {
+            cue: input.read()?,
+            player: input.read()?,
+            scores: input.read()?,
+            on: input.read()?,
+            nominated: input.read()?,
+            free_ball: input.read()?,
+            ball_in_hand: input.read()?,
+            last_foul: input.read()?,
+            penalty: input.read()?,
+            miss: input.read()?,
+            winner: input.read()?,
+            before: input.read()?,
+            shot: input.read()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use super::*;
+    use crate::rules::testing::shoot;
+    use crate::{Rules, Table};
+
+    /// A frame of snooker on a full-size table with `on` to play and the
+    /// cue ball already placed.
+    fn frame(on: On, scores: [u32; 2], balls: Vec<Ball>) -> GameState {
+        let mut rules = Snooker::new();
+        rules.on = on;
+        rules.scores = scores;
+        rules.ball_in_hand = BallInHand::No;
+        GameState::new(Table::snooker(), balls).with_rules(Rules::Snooker(rules))
+    }
+
+    /// The cue ball lined up to send a ball of `kind` into the top side
+    /// pocket, with `others` placed as given.
+    fn pot_in_top_side(kind: BallKind, others: &[(BallKind, Vector2D)]) -> Vec<Ball> {
+        let middle = Table::snooker().width * 0.5;
+        let mut balls = vec![
+            Ball::snooker(BallKind::Cue, middle, 140.0),
+            Ball::snooker(kind, middle, 100.0),
+        ];
+        balls.extend(
+            others
+                .iter()
+                .map(|&(kind, at)| Ball::snooker(kind, at.x, at.y)),
+        );
+        balls
+    }
+
+    fn spot(kind: BallKind) -> Vector2D {
+        rack::colour_spot(Table::snooker(), kind).unwrap()
+    }
+
+    #[test]
+    fn red_then_any_colour() {
+        let mut state = frame(
+            On::Red,
+            [0, 0],
+            pot_in_top_side(BallKind::Red, &[(BallKind::Blue, spot(BallKind::Blue))]),
+        );
+
+        assert!(shoot(&mut state, -FRAC_PI_2, 200.0));
+
+        assert_eq!(state.last_foul(), None);
+        assert_eq!(state.score(0), 1);
+        assert_eq!(state.current_player(), Some(0));
+        assert_eq!(state.ball_on(), None);
+    }
+
+    #[test]
+    fn wrong_ball_first_gives_away_its_value() {
+        let mut state = frame(
+            On::Red,
+            [0, 0],
+            pot_in_top_side(
+                BallKind::Blue,
+                &[(BallKind::Red, Vector2D::new(950.0, 450.0))],
+            ),
+        );
+
+        assert!(shoot(&mut state, -FRAC_PI_2, 200.0));
+
+        assert_eq!(state.last_foul(), Some(Foul::WrongBallFirst));
+        assert_eq!(state.penalty(), 5);
+        assert_eq!(state.score(1), 5);
+        assert_eq!(state.current_player(), Some(1));
+        // The blue comes back up on its spot.
+        let blue = state.find_ball(1).unwrap();
+        assert!((blue.position - spot(BallKind::Blue)).length() < 1e-3);
+    }
+
+    #[test]
+    fn colour_goes_to_the_highest_free_spot_when_its_own_is_taken() {
+        let mut state = frame(
+            On::AnyColour,
+            [1, 0],
+            pot_in_top_side(
+                BallKind::Pink,
+                &[
+                    (BallKind::Red, spot(BallKind::Pink)),
+                    (BallKind::Black, spot(BallKind::Black)),
+                ],
+            ),
+        );
+
+        assert!(shoot(&mut state, -FRAC_PI_2, 200.0));
+
+        assert_eq!(state.last_foul(), None);
+        assert_eq!(state.score(0), 7);
+        assert_eq!(state.ball_on(), Some(BallKind::Red));
+        let pink = state.find_ball(1).unwrap();
+        assert!((pink.position - spot(BallKind::Blue)).length() < 1e-3);
+    }
+
+    #[test]
+    fn final_black_ends_the_frame_or_is_respotted_on_a_tie() {
+        let mut state = frame(
+            On::Colour(BallKind::Black),
+            [10, 0],
+            pot_in_top_side(BallKind::Black, &[]),
+        );
+        assert!(shoot(&mut state, -FRAC_PI_2, 200.0));
+        assert_eq!(state.winner(), Some(0));
+
+        let mut state = frame(
+            On::Colour(BallKind::Black),
+            [0, 7],
+            pot_in_top_side(BallKind::Black, &[]),
+        );
+        assert!(shoot(&mut state, -FRAC_PI_2, 200.0));
+        assert_eq!(state.winner(), None);
+        assert_eq!(state.current_player(), Some(1));
+        assert_eq!(state.ball_in_hand(), BallInHand::InD);
+        let black = state.find_ball(1).unwrap();
+        assert!((black.position - spot(BallKind::Black)).length() < 1e-3);
+    }
+
+    #[test]
+    fn foul_that_leaves_a_snooker_gives_a_free_ball() {
+        // The blue hides the only red from the cue ball.
+        let mut state = frame(
+            On::Red,
+            [0, 0],
+            vec![
+                Ball::snooker(BallKind::Cue, 200.0, 280.0),
+                Ball::snooker(BallKind::Blue, 300.0, 280.0),
+                Ball::snooker(BallKind::Red, 900.0, 280.0),
+            ],
+        );
+
+        assert!(shoot(&mut state, -FRAC_PI_2, 5.0));
+
+        assert_eq!(state.last_foul(), Some(Foul::NoContact));
+        assert_eq!(state.score(1), 4);
+        assert!(state.free_ball());
+        // The red was never in sight, so this was no miss.
+        assert!(!state.missed() && !state.replay_miss());
+    }
+
+    #[test]
+    fn miss_may_be_replayed() {
+        let mut state = frame(
+            On::Red,
+            [0, 0],
+            vec![
+                Ball::snooker(BallKind::Cue, 200.0, 280.0),
+                Ball::snooker(BallKind::Blue, 200.0, 120.0),
+                Ball::snooker(BallKind::Red, 900.0, 280.0),
+            ],
+        );
+
+        assert!(shoot(&mut state, -FRAC_PI_2, 150.0));
+
+        assert_eq!(state.last_foul(), Some(Foul::WrongBallFirst));
+        assert!(state.missed());
+        assert_eq!(state.current_player(), Some(1));
+        assert!(state.replay_miss());
+        assert_eq!(state.current_player(), Some(0));
+        assert_eq!(state.score(1), 5);
+        let cue = state.find_ball(0).unwrap();
+        assert!((cue.position - Vector2D::new(200.0, 280.0)).length() < 1e-3);
+        assert!(!state.free_ball());
+    }
+}
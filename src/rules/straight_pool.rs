@@ -0,0 +1,496 @@
+//! Straight pool (14.1 continuous), following the WPA rules.
+//!
+//! Every shot is called, any ball may be played, and each ball pocketed on
+//! a shot that makes its call scores a point. A shot that misses the call
+//! ends the turn, and balls it pocketed come back up on the foot spot. A
+//! foul costs a point and leaves the incoming player the table as it lies,
+//! or the cue ball in the kitchen after a scratch. A third foul in a row
+//! costs a further fifteen points. The opening break must make its call or
+//! drive two object balls to a cushion; failing costs two points.
+//!
+//! When only one object ball is left, the fourteen others are racked with
+//! the apex empty and the shooter plays on. The last ball and the cue ball
+//! stay where they are unless they are in the way of the rack.
+
+use super::{exists, is_player, place_ball, spot_ball, BallInHand, Foul, ShotRecord};
+use crate::snapshot::{Decode, Encode, Reader, SnapshotError};
+use crate::{
+    rack, Event, GameState, Pocket, PocketedBall, Vector2D, CUE_BALL_ID, POOL_BALL_RADIUS,
+};
+
+/// Object balls that must reach a cushion on an opening break that does not
+/// make its call.
+const BREAK_RAIL_BALLS: usize = 2;
+
+/// Points lost for a foul on the opening break.
+const BREAK_PENALTY: i32 = 2;
+
+/// Consecutive fouls that cost the extra penalty.
+const FOUL_LIMIT: u32 = 3;
+
+/// Extra points lost for the third foul in a row.
+const FOUL_LIMIT_PENALTY: i32 = 15;
+
+/// Space left between re-racked balls, so that placing one never reports an
+/// overlap with its neighbour.
+const RACK_GAP: f32 = 0.01;
+
+/// The state of a game of straight pool between two players.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StraightPool {
+    /// Id of the cue ball.
+    cue: u32,
+    /// Points that win the game.
+    target: i32,
+    /// The player to shoot, 0 or 1.
+    player: u32,
+    /// Points scored by each player, less penalties.
+    scores: [i32; 2],
+    /// Consecutive fouls of each player.
+    fouls: [u32; 2],
+    /// Points scored in the current inning.
+    run: u32,
+    /// Longest run of each player.
+    high_runs: [u32; 2],
+    /// `true` until the opening break has been played.
+    breaking: bool,
+    /// The ball and pocket called for the coming shot.
+    called: Option<(u32, Pocket)>,
+    /// Where the cue ball may be placed before the coming shot.
+    ball_in_hand: BallInHand,
+    /// The foul committed on the last shot.
+    last_foul: Option<Foul>,
+    /// The winner, once the game is over.
+    winner: Option<u32>,
+    /// The shot being played, if any.
+    shot: Option<ShotRecord>,
+}
+
+impl StraightPool {
+    /// Starts a game to `target` points with player 0 to break.
+    #[must_use]
+    pub fn new(target: u32) -> Self {
+        Self {
+            cue: CUE_BALL_ID,
+            target: i32::try_from(target).unwrap_or(i32::MAX),
+            player: 0,
+            scores: [0, 0],
+            fouls: [0, 0],
+            run: 0,
+            high_runs: [0, 0],
+            breaking: true,
+            called: None,
+            ball_in_hand: BallInHand::Kitchen,
+            last_foul: None,
+            winner: None,
+            shot: None,
+        }
+    }
+
+    /// Returns the player to shoot.
+    #[must_use]
+    pub const fn current_player(&self) -> u32 {
+        self.player
+    }
+
+    /// Returns the winner, once the game is over.
+    #[must_use]
+    pub const fn winner(&self) -> Option<u32> {
+        self.winner
+    }
+
+    /// Returns the foul committed on the last shot, if any.
+    #[must_use]
+    pub const fn last_foul(&self) -> Option<Foul> {
+        self.last_foul
+    }
+
+    /// Returns where the cue ball may be placed before the next shot.
+    #[must_use]
+    pub const fn ball_in_hand(&self) -> BallInHand {
+        self.ball_in_hand
+    }
+
+    /// Returns the ids of the balls the player to shoot may hit first: any
+    /// object ball.
+    #[must_use]
+    pub fn targets(&self, state: &GameState) -> Vec<u32> {
+        state
+            .balls
+            .iter()
+            .filter(|ball| ball.id != self.cue)
+            .map(|ball| ball.id)
+            .collect()
+    }
+
+    /// Returns the score of `player`, which penalties may take below zero.
+    #[must_use]
+    pub fn score(&self, player: u32) -> i32 {
+        self.scores.get(player as usize).copied().unwrap_or(0)
+    }
+
+    /// Returns the longest run of points `player` has made in one inning.
+    #[must_use]
+    pub fn high_run(&self, player: u32) -> u32 {
+        self.high_runs.get(player as usize).copied().unwrap_or(0)
+    }
+
+    /// Returns how many fouls in a row `player` has committed.
+    #[must_use]
+    pub fn consecutive_fouls(&self, player: u32) -> u32 {
+        self.fouls.get(player as usize).copied().unwrap_or(0)
+    }
+
+    /// Calls `ball` into `pocket` for the coming shot.
+    pub const fn call_shot(&mut self, ball: u32, pocket: Pocket) {
+        self.called = Some((ball, pocket));
+    }
+
+    /// Starts a shot. Returns `false` once the game is over or while a shot
+    /// is still running.
+    pub fn begin_shot(&mut self) -> bool {
+        if self.winner.is_some() || self.shot.is_some() {
+            return false;
+        }
+        self.ball_in_hand = BallInHand::No;
+        self.shot = Some(ShotRecord::default());
+        true
+    }
+
+    /// Adds an event of the running shot.
+    pub fn observe(&mut self, event: &Event) {
+        if let Some(shot) = &mut self.shot {
+            shot.observe(event, self.cue);
+        }
+    }
+
+    /// Returns `true` if the players this game records are 0 or 1 and every
+    /// ball it refers to is in `state`, on the table or in a pocket.
+    /// Neither player may have as many fouls in a row as cost the extra
+    /// penalty, since reaching that count clears it.
+    #[must_use]
+    pub fn is_consistent(&self, state: &GameState) -> bool {
+        is_player(self.player)
+            && self.winner.is_none_or(is_player)
+            && exists(state, self.cue)
+            && self.called.is_none_or(|(id, _)| exists(state, id))
+            && self.fouls.iter().all(|&fouls| fouls < FOUL_LIMIT)
+            && self
+                .shot
+                .as_ref()
+                .is_none_or(|shot| shot.is_consistent(state))
+    }
+
+    /// Judges the shot that has just come to rest.
+    pub fn end_shot(&mut self, state: &mut GameState) {
+        let Some(shot) = self.shot.take() else {
+            return;
+        };
+        let breaking = std::mem::take(&mut self.breaking);
+        let made = self
+            .called
+            .take()
+            .is_some_and(|call| shot.pocketed.contains(&call));
+
+        let foul = if shot.scratched {
+            Some(Foul::Scratch)
+        } else if shot.first_contact.is_none() {
+            Some(Foul::NoContact)
+        } else if breaking && !made && shot.object_balls_to_rail.len() < BREAK_RAIL_BALLS {
+            Some(Foul::IllegalBreak)
+        } else if shot.pocketed.is_empty() && !shot.rail_after_contact {
+            Some(Foul::NoRail)
+        } else {
+            None
+        };
+        self.last_foul = foul;
+
+        // Balls only stay down when the call was made on a legal shot.
+        if foul.is_some() || !made {
+            let foot_spot = Vector2D::new(state.table.width * 0.75, state.table.height * 0.5);
+            for &(id, _) in &shot.pocketed {
+                spot_ball(state, id, foot_spot);
+            }
+        }
+
+        let shooter = self.player as usize;
+        if let Some(foul) = foul {
+            self.fouls[shooter] += 1;
+            let penalty = if foul == Foul::IllegalBreak {
+                BREAK_PENALTY
+            } else {
+                1
+            };
+            self.scores[shooter] = self.scores[shooter].saturating_sub(penalty);
+            if self.fouls[shooter] >= FOUL_LIMIT {
+                self.scores[shooter] = self.scores[shooter].saturating_sub(FOUL_LIMIT_PENALTY);
+                self.fouls[shooter] = 0;
+            }
+            if shot.scratched {
+                self.ball_in_hand = BallInHand::Kitchen;
+            }
+            self.end_inning();
+        } else {
+            self.fouls[shooter] = 0;
+            if made {
+                let points = u32::try_from(shot.pocketed.len()).unwrap_or(u32::MAX);
+                let gained = i32::try_from(points).unwrap_or(i32::MAX);
+                self.scores[shooter] = self.scores[shooter].saturating_add(gained);
+                self.run = self.run.saturating_add(points);
+                self.high_runs[shooter] = self.high_runs[shooter].max(self.run);
+                if self.scores[shooter] >= self.target {
+                    self.winner = Some(self.player);
+                    return;
+                }
+            } else {
+                self.end_inning();
+            }
+        }
+
+        let left = state
+            .balls
+            .iter()
+            .filter(|ball| ball.id != self.cue)
+            .count();
+        if left <= 1 {
+            self.rerack(state);
+        }
+    }
+
+    /// Passes the table to the other player.
+    const fn end_inning(&mut self) {
+        self.run = 0;
+        self.player = 1 - self.player;
+    }
+
+    /// Racks the fourteen pocketed balls behind an empty apex, moving the
+    /// last object ball and the cue ball first if they are in the way.
+    ///
+    /// If both are in the rack area, the last ball goes on the foot spot and
+    /// the cue ball into the kitchen. A last ball in the rack area otherwise
+    /// goes to the centre spot if the cue ball is in the kitchen and to the
+    /// head spot if not. If the last ball has dropped as well, all fifteen
+    /// are racked.
+    fn rerack(&mut self, state: &mut GameState) {
+        let table = state.table;
+        let spots = rack::triangle_spots(table, RACK_GAP);
+        let middle = table.height * 0.5;
+        let foot_spot = Vector2D::new(table.width * 0.75, middle);
+        let head_spot = Vector2D::new(table.width * 0.25, middle);
+        let in_rack = |position: Vector2D| {
+            spots
+                .iter()
+                .any(|&spot| (spot - position).length() < 2.0 * POOL_BALL_RADIUS)
+        };
+
+        let cue = state.find_ball(self.cue).map(|ball| ball.position);
+        let cue_in_rack = cue.is_some_and(in_rack);
+        let cue_in_kitchen = cue.is_some_and(|position| position.x <= head_spot.x);
+        let last = state
+            .balls
+            .iter()
+            .find(|ball| ball.id != self.cue)
+            .map(|ball| (ball.id, in_rack(ball.position)));
+
+        if cue_in_rack {
+            spot_ball(state, self.cue, head_spot);
+            self.ball_in_hand = BallInHand::Kitchen;
+        }
+        if let Some((id, true)) = last {
+            let spot = if cue_in_rack {
+                foot_spot
+            } else if cue_in_kitchen {
+                Vector2D::new(table.width * 0.5, middle)
+            } else {
+                head_spot
+            };
+            spot_ball(state, id, spot);
+        }
+
+        let mut racked: Vec<u32> = state
+            .pocketed
+            .iter()
+            .map(PocketedBall::ball_id)
+            .filter(|&id| id != self.cue)
+            .collect();
+        racked.sort_unstable();
+        let mut free = spots.into_iter().skip(usize::from(last.is_some()));
+        for id in racked {
+            if !free.any(|spot| place_ball(state, id, spot)) {
+                spot_ball(state, id, foot_spot);
+            }
+        }
+    }
+}
+
+impl Encode for StraightPool {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.cue.encode(out);
+        self.target.encode(out);
+        self.player.encode(out);
+        self.scores.encode(out);
+        self.fouls.encode(out);
+        self.run.encode(out);
+        self.high_runs.encode(out);
+        self.breaking.encode(out);
+        self.called.encode(out);
+        self.ball_in_hand.encode(out);
+        self.last_foul.encode(out);
+        self.winner.encode(out);
+        self.shot.encode(out);
+    }
+}
+
+impl Decode for StraightPool {
+    fn decode(input: &mut Reader<'_>) -> Result<Self, SnapshotError> {
+        Ok(Self {
+            cue: input.read()?,
+            target: input.read()?,
+            player: input.read()?,
+            scores: input.read()?,
+            fouls: input.read()?,
+            run: input.read()?,
+            high_runs: input.read()?,
+            breaking: input.read()?,
+            called: input.read()?,
+            ball_in_hand: input.read()?,
+            last_foul: input.read()?,
+            winner: input.read()?,
+            shot: input.read()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::testing::{pool_game, shoot_up};
+    use crate::{Ball, Rules};
+
+    /// A game of straight pool past the opening break. Balls numbered
+    /// `down` start in the pockets.
+    fn game(rules: StraightPool, balls: Vec<Ball>, down: &[u32]) -> GameState {
+        let mut rules = rules;
+        rules.breaking = false;
+        rules.ball_in_hand = BallInHand::No;
+        let mut state = pool_game(balls, Rules::StraightPool(rules));
+        let (pocketed, balls) = state
+            .balls
+            .drain(..)
+            .partition(|ball| down.contains(&ball.number));
+        state.balls = balls;
+        state.pocketed = pocketed
+            .into_iter()
+            .map(|ball| PocketedBall::new(ball, Pocket::TopLeft, 0.0))
+            .collect();
+        state
+    }
+
+    /// The cue ball lined up to send the 1 into the top side pocket, with
+    /// the 2 at `two` and the 3 to 15 laid out along the bottom rail.
+    fn rack_of_fifteen(two: Vector2D) -> Vec<Ball> {
+        let mut balls = vec![
+            Ball::pool(0, 400.0, 140.0),
+            Ball::pool(1, 400.0, 100.0),
+            Ball::pool(2, two.x, two.y),
+        ];
+        let spare = (0_u16..).map(|i| 40.0 + 25.0 * f32::from(i));
+        balls.extend((3..16).zip(spare).map(|(n, x)| Ball::pool(n, x, 370.0)));
+        balls
+    }
+
+    #[test]
+    fn called_ball_scores_and_extends_the_run() {
+        let mut state = game(
+            StraightPool::new(100),
+            rack_of_fifteen(Vector2D::new(100.0, 300.0)),
+            &[],
+        );
+        assert!(state.call_shot(1, Pocket::TopSide));
+
+        assert!(shoot_up(&mut state, 200.0));
+
+        assert_eq!(state.last_foul(), None);
+        assert_eq!(state.score(0), 1);
+        assert_eq!(state.high_run(0), 1);
+        assert_eq!(state.current_player(), Some(0));
+    }
+
+    #[test]
+    fn missed_call_spots_the_ball_and_ends_the_inning() {
+        let mut state = game(
+            StraightPool::new(100),
+            rack_of_fifteen(Vector2D::new(100.0, 300.0)),
+            &[],
+        );
+        assert!(state.call_shot(1, Pocket::TopLeft));
+
+        assert!(shoot_up(&mut state, 200.0));
+
+        assert_eq!(state.score(0), 0);
+        assert!(state.is_on_table(1));
+        assert_eq!(state.current_player(), Some(1));
+    }
+
+    #[test]
+    fn third_foul_in_a_row_costs_fifteen_more() {
+        let mut rules = StraightPool::new(100);
+        rules.fouls = [2, 0];
+        // Nothing lies on the line of the shot.
+        let mut state = game(
+            rules,
+            vec![Ball::pool(0, 400.0, 300.0), Ball::pool(1, 100.0, 100.0)],
+            &[],
+        );
+
+        assert!(shoot_up(&mut state, 60.0));
+
+        assert_eq!(state.last_foul(), Some(Foul::NoContact));
+        assert_eq!(state.score(0), -16);
+        assert_eq!(state.consecutive_fouls(0), 0);
+        assert_eq!(state.current_player(), Some(1));
+    }
+
+    #[test]
+    fn fourteen_balls_are_reracked_around_the_last_one() {
+        let down: Vec<u32> = (3..16).collect();
+        let mut state = game(
+            StraightPool::new(100),
+            rack_of_fifteen(Vector2D::new(100.0, 300.0)),
+            &down,
+        );
+        assert!(state.call_shot(1, Pocket::TopSide));
+
+        assert!(shoot_up(&mut state, 200.0));
+
+        assert_eq!(state.score(0), 1);
+        assert_eq!(state.balls().len(), 16);
+        assert!(state.pocketed().is_empty());
+        let two = state.find_ball(2).unwrap();
+        assert!((two.position - Vector2D::new(100.0, 300.0)).length() < 1e-3);
+        // The apex stays empty for the break ball.
+        let apex = Vector2D::new(600.0, 200.0);
+        assert!(state
+            .balls()
+            .iter()
+            .all(|ball| (ball.position - apex).length() > POOL_BALL_RADIUS));
+    }
+
+    #[test]
+    fn last_ball_in_the_rack_area_goes_to_the_head_spot() {
+        let down: Vec<u32> = (3..16).collect();
+        let mut state = game(
+            StraightPool::new(100),
+            rack_of_fifteen(Vector2D::new(640.0, 210.0)),
+            &down,
+        );
+        assert!(state.call_shot(1, Pocket::TopSide));
+
+        assert!(shoot_up(&mut state, 200.0));
+
+        let two = state.find_ball(2).unwrap();
+        assert!((two.position - Vector2D::new(200.0, 200.0)).length() < 1e-3);
+        assert_eq!(state.balls().len(), 16);
+    }
+}
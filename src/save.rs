@@ -0,0 +1,356 @@
+//! Saving and loading game states and matches as JSON.
+//!
+//! A save wraps the state in an envelope that records the schema version it
+//! was written with, `{"version": 1, "state": {...}}`, and a match likewise
+//! as `{"version": 1, "match": {...}}`. Loading upgrades an older save one
+//! version at a time before reading it, so positions saved by earlier builds
+//! still load; a save from a newer build is refused rather than misread.
+//! Saves can be edited by hand, so a loaded state or match is also checked
+//! for anything play could not have produced.
+//!
+//! Version 0 is a bare state or match with no envelope, as the `serde`
+//! derives write it on their own.
+
+use std::fmt;
+
+use serde::Serialize;
+use serde_json::Value;
+use wasm_bindgen::prelude::*;
+
+use crate::{GameState, Match};
+
+/// Schema version written by [`GameState::to_json`].
+pub const SAVE_VERSION: u32 = 1;
+
+/// Rewrites a saved state from one schema version to the next.
+type Upgrade = fn(Value) -> Value;
+
+/// Upgrades from each schema version to the next, indexed by the version
+/// upgraded from.
+const UPGRADES: [Upgrade; SAVE_VERSION as usize] = [
+    // Version 1 only wrapped the state in an envelope.
+    std::convert::identity,
+];
+
+/// Why a save could not be loaded.
+#[derive(Debug)]
+pub enum SaveError {
+    /// The text is not JSON, or not a game state of the version it claims.
+    Invalid(serde_json::Error),
+    /// The save was written with a newer schema than this build reads.
+    Newer(u64),
+    /// The save reads, but describes something play could not reach, such
+    /// as a third player or a rule naming a ball that does not exist.
+    Inconsistent,
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Invalid(error) => write!(f, "invalid save: {error}"),
+            Self::Newer(version) => write!(
+                f,
+                "save has schema version {version}, newer than the supported {SAVE_VERSION}"
+            ),
+            Self::Inconsistent => write!(f, "save describes a game play could not reach"),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Invalid(error) => Some(error),
+            Self::Newer(_) | Self::Inconsistent => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for SaveError {
+    fn from(error: serde_json::Error) -> Self {
+        Self::Invalid(error)
+    }
+}
+
+impl From<SaveError> for JsValue {
+    fn from(error: SaveError) -> Self {
+        JsError::from(error).into()
+    }
+}
+
+/// The envelope a state is saved in.
+#[derive(Serialize)]
+struct Save<'a> {
+    version: u32,
+    state: &'a GameState,
+}
+
+/// The envelope a match is saved in.
+#[derive(Serialize)]
+struct MatchSave<'a> {
+    version: u32,
+    r#match: &'a Match,
+}
+
+/// Parses a save, takes what is under `key` out of its envelope and returns
+/// it with the schema version it was written at. A save without an envelope
+/// is version 0.
+fn open(json: &str, key: &str) -> Result<(u64, Value), SaveError> {
+    let save: Value = serde_json::from_str(json)?;
+    match save {
+        Value::Object(mut envelope) if envelope.contains_key(key) => {
+            let version = envelope.get("version").cloned().unwrap_or(Value::Null);
+            let version: u64 = serde_json::from_value(version)?;
+            Ok((version, envelope.remove(key).unwrap_or(Value::Null)))
+        }
+        bare => Ok((0, bare)),
+    }
+}
+
+/// Returns the upgrades that bring a state saved at `version` to
+/// [`SAVE_VERSION`], in the order to apply them.
+fn upgrades(version: u64) -> Result<&'static [Upgrade], SaveError> {
+    usize::try_from(version)
+        .ok()
+        .and_then(|first| UPGRADES.get(first..))
+        .ok_or(SaveError::Newer(version))
+}
+
+#[wasm_bindgen]
+impl GameState {
+    /// Returns this state as a JSON save at [`SAVE_VERSION`], including its
+    /// rules and any undrained events.
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        let save = Save {
+            version: SAVE_VERSION,
+            state: self,
+        };
+        // Every part of a state has a JSON form, so this cannot fail.
+        serde_json::to_string(&save).unwrap_or_default()
+    }
+
+    /// Loads a state from a JSON save written by [`GameState::to_json`] in
+    /// this build or an earlier one. In JS, a save that cannot be loaded
+    /// throws an `Error` saying why.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SaveError::Newer`] if the save has a newer schema version
+    /// than [`SAVE_VERSION`], [`SaveError::Invalid`] if it is not a valid
+    /// save, and [`SaveError::Inconsistent`] if it describes a state play
+    /// could not reach.
+    pub fn from_json(json: &str) -> Result<Self, SaveError> {
+        let (version, mut state) = open(json, "state")?;
+        for upgrade in upgrades(version)? {
+            state = upgrade(state);
+        }
+        let state: Self = serde_json::from_value(state)?;
+        if !state.is_consistent() {
+            return Err(SaveError::Inconsistent);
+        }
+        Ok(state)
+    }
+}
+
+#[wasm_bindgen]
+impl Match {
+    /// Returns this match as a JSON save at [`SAVE_VERSION`], including the
+    /// frame in play.
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        let save = MatchSave {
+            version: SAVE_VERSION,
+            r#match: self,
+        };
+        // Every part of a match has a JSON form, so this cannot fail.
+        serde_json::to_string(&save).unwrap_or_default()
+    }
+
+    /// Loads a match from a JSON save written by [`Match::to_json`] in this
+    /// build or an earlier one. In JS, a save that cannot be loaded throws an
+    /// `Error` saying why.
+    ///
+    /// # Errors
+    ///
+    /// As for [`GameState::from_json`].
+    pub fn from_json(json: &str) -> Result<Self, SaveError> {
+        let (version, mut game) = open(json, "match")?;
+        let upgrades = upgrades(version)?;
+        if let Some(frame) = game.get_mut("frame").filter(|frame| !frame.is_null()) {
+            for upgrade in upgrades {
+                *frame = upgrade(frame.take());
+            }
+        }
+        let game: Self = serde_json::from_value(game)?;
+        if !game.is_consistent() {
+            return Err(SaveError::Inconsistent);
+        }
+        Ok(game)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    use crate::rules::testing::shoot;
+    use crate::{tick, CaromGame, CueStroke, Discipline, Foul, MatchFormat, Rules, Table};
+
+    /// An eight-ball game just after the break has settled.
+    fn broken() -> GameState {
+        let mut state = GameState::eight_ball(&Table::pool(800.0, 400.0), 3, 0.0);
+        assert!(state.strike(&CueStroke::new(0.0, 900.0, 0.0, 0.0, 0.0)));
+        for _ in 0..900 {
+            tick(&mut state, 1.0 / 60.0);
+        }
+        state
+    }
+
+    #[test]
+    fn saved_state_loads_back() {
+        let state = broken();
+
+        let loaded = GameState::from_json(&state.to_json()).unwrap();
+
+        assert_eq!(loaded.ball_ids(), state.ball_ids());
+        for (ball, was) in loaded.balls().iter().zip(state.balls()) {
+            assert!((ball.position - was.position).length() < f32::EPSILON);
+            assert_eq!(ball.kind, was.kind);
+        }
+        assert_eq!(loaded.pocketed.len(), state.pocketed.len());
+        assert_eq!(loaded.current_player(), state.current_player());
+        assert_eq!(loaded.events().len(), state.events().len());
+        assert_eq!(loaded.to_json(), state.to_json());
+    }
+
+    #[test]
+    fn bare_state_loads_as_version_zero() {
+        let state = broken();
+        let bare = serde_json::to_string(&state).unwrap();
+
+        let loaded = GameState::from_json(&bare).unwrap();
+
+        assert_eq!(loaded.to_json(), state.to_json());
+    }
+
+    #[test]
+    fn newer_and_malformed_saves_are_refused() {
+        let state = broken().to_json();
+        let newer = state.replacen(
+            &format!("\"version\":{SAVE_VERSION}"),
+            &format!("\"version\":{}", SAVE_VERSION + 1),
+            1,
+        );
+
+        assert!(matches!(
+            GameState::from_json(&newer),
+            Err(SaveError::Newer(version)) if version == u64::from(SAVE_VERSION + 1)
+        ));
+        assert!(matches!(
+            GameState::from_json(&state[..state.len() / 2]),
+            Err(SaveError::Invalid(_))
+        ));
+        assert!(matches!(
+            GameState::from_json(r#"{"version": 1, "state": {"balls": 3}}"#),
+            Err(SaveError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn edited_saves_that_play_could_not_reach_are_refused() {
+        let state = broken().to_json();
+        let third_player = state.replacen("\"player\":1", "\"player\":7", 1);
+        let third_player = third_player.replacen("\"player\":0", "\"player\":7", 1);
+        let doubled = state.replacen("\"id\":1,", "\"id\":0,", 1);
+
+        assert_ne!(third_player, state);
+        assert!(matches!(
+            GameState::from_json(&third_player),
+            Err(SaveError::Inconsistent)
+        ));
+        assert!(matches!(
+            GameState::from_json(&doubled),
+            Err(SaveError::Inconsistent)
+        ));
+    }
+
+    #[test]
+    fn saved_match_loads_back() {
+        let format = MatchFormat::new(Discipline::EightBall, 3);
+        let table = Table::pool(800.0, 400.0);
+        let mut game = Match::new(&format, &table, "Ann".into(), "Bo".into());
+        assert!(game.start(1));
+        assert!(game.strike(&CueStroke::new(0.0, 900.0, 0.0, 0.0, 0.0)));
+        for _ in 0..900 {
+            game.tick(1.0 / 60.0);
+        }
+
+        let saved = game.to_json();
+        let loaded = Match::from_json(&saved).unwrap();
+
+        assert_eq!(loaded.to_json(), saved);
+        assert_eq!(loaded.current_player(), game.current_player());
+        assert!(matches!(
+            Match::from_json(&saved.replacen("\"breaker\":1", "\"breaker\":2", 1)),
+            Err(SaveError::Inconsistent)
+        ));
+    }
+
+    #[test]
+    fn snooker_scores_edited_to_the_limit_stay_there() {
+        let state = GameState::snooker(&Table::snooker(), 1, 0.0).to_json();
+        let edited = state.replacen("\"scores\":[0,0]", "\"scores\":[4294967295,4294967295]", 1);
+        assert_ne!(edited, state);
+
+        let mut state = GameState::from_json(&edited).unwrap();
+        // Away from every red, so the break is a foul.
+        assert!(shoot(&mut state, PI, 300.0));
+
+        assert_eq!(state.last_foul(), Some(Foul::NoContact));
+        let Some(Rules::Snooker(game)) = state.rules() else {
+            panic!("not snooker");
+        };
+        assert_eq!(game.score(1), u32::MAX);
+    }
+
+    #[test]
+    fn straight_pool_scores_and_fouls_are_checked() {
+        let table = Table::pool(800.0, 400.0);
+        let state = GameState::straight_pool(&table, 1, 0.0, 100).to_json();
+        let lowest = state.replacen("\"scores\":[0,0]", "\"scores\":[-2147483648,0]", 1);
+        let fouled = state.replacen("\"fouls\":[0,0]", "\"fouls\":[3,0]", 1);
+        assert!(lowest != state && fouled != state);
+
+        let mut state = GameState::from_json(&lowest).unwrap();
+        // Away from the rack, so the break is a foul.
+        assert!(shoot(&mut state, PI, 300.0));
+
+        assert_eq!(state.last_foul(), Some(Foul::NoContact));
+        assert_eq!(state.score(0), i32::MIN);
+        assert!(matches!(
+            GameState::from_json(&fouled),
+            Err(SaveError::Inconsistent)
+        ));
+    }
+
+    #[test]
+    fn carom_scores_past_the_target_are_refused() {
+        let state = GameState::carom(&Table::carom(), CaromGame::Balkline, 10).to_json();
+        let past = state.replacen("\"scores\":[0,0]", "\"scores\":[4294967295,0]", 1);
+        let balked = state.replacen("\"balk_counts\":0", "\"balk_counts\":7", 1);
+        let level = state.replacen("\"scores\":[0,0]", "\"scores\":[9,9]", 1);
+        assert!(past != state && balked != state && level != state);
+
+        assert!(matches!(
+            GameState::from_json(&past),
+            Err(SaveError::Inconsistent)
+        ));
+        assert!(matches!(
+            GameState::from_json(&balked),
+            Err(SaveError::Inconsistent)
+        ));
+        assert_eq!(GameState::from_json(&level).unwrap().score(1), 9);
+    }
+}
@@ -0,0 +1,858 @@
+//! Compact binary snapshots of a game state.
+//!
+//! A snapshot holds everything a [`GameState`] carries, down to ball spin,
+//! the table and the rules' memory of the game, in a fraction of the space
+//! of its JSON form, for undo stacks and network sync. The layout is:
+//!
+//! | bytes | contents                                             |
+//! |-------|------------------------------------------------------|
+//! | 4     | the signature `PSIM`                                 |
+//! | 2     | format version, [`SNAPSHOT_VERSION`]                 |
+//! | 4     | length of the body in bytes                          |
+//! | *n*   | the body: the state, field by field                  |
+//! | 4     | CRC-32 (IEEE) of everything before it                |
+//!
+//! Every number is little-endian. In the body, integers and `f32`s take
+//! four bytes, `bool`s and enum tags one, an `Option` is a tag byte
+//! followed by its value if there is one, and a list is its length followed
+//! by its items. Fields are written in the order they are declared.
+//!
+//! Each type writes and reads itself through [`Encode`] and [`Decode`],
+//! implemented next to the type so that private state is covered. Other
+//! binary formats, such as [`Replay`](crate::Replay) files and saved
+//! [`Match`](crate::Match)es, use the same layout under their own signature.
+//!
+//! A snapshot can be edited and resealed, so a state is checked to be
+//! [consistent](GameState::is_consistent) as it is read, and one that is
+//! not is refused as [`SnapshotError::Invalid`].
+
+use std::fmt;
+
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    Ball, BallInHand, BallKind, BreakFormat, CaromGame, CueStroke, Discipline, Event, EventKind,
+    Foul, GameState, Group, MatchFormat, Pocket, PocketSpec, PocketedBall, Rail, Rules, Table,
+    Vector2D, Vector3D,
+};
+
+/// Format version written by [`GameState::to_bytes`].
+pub const SNAPSHOT_VERSION: u16 = 1;
+
+/// The bytes every snapshot starts with.
+const SIGNATURE: [u8; 4] = *b"PSIM";
+
+/// Bytes before the body: signature, version and body length.
+const HEADER_LEN: usize = 10;
+
+/// Bytes after the body: the checksum.
+const CHECKSUM_LEN: usize = 4;
+
+/// Why a snapshot could not be read.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SnapshotError {
+    /// The bytes do not start with the expected signature.
+    NotASnapshot,
+    /// The bytes end before the snapshot does.
+    Truncated,
+    /// The checksum does not match the contents.
+    Checksum,
+    /// The snapshot was written in a format version this build cannot read.
+    UnsupportedVersion {
+        /// The version the bytes were written in.
+        found: u16,
+        /// The version this build reads for that kind of file.
+        expected: u16,
+    },
+    /// The checksum matches, but the contents are not valid.
+    Invalid,
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotASnapshot => f.write_str("bytes do not start with the expected signature"),
+            Self::Truncated => f.write_str("bytes end before the snapshot does"),
+            Self::Checksum => f.write_str("snapshot checksum does not match"),
+            Self::UnsupportedVersion { found, expected } => write!(
+                f,
+                "snapshot has format version {found}, this build reads {expected}"
+            ),
+            Self::Invalid => f.write_str("snapshot contents are not valid"),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+impl From<SnapshotError> for JsValue {
+    fn from(error: SnapshotError) -> Self {
+        JsError::from(error).into()
+    }
+}
+
+/// A value that can be written into a snapshot body.
+pub trait Encode {
+    /// Appends this value to `out`.
+    fn encode(&self, out: &mut Vec<u8>);
+}
+
+/// A value that can be read back from a snapshot body.
+pub trait Decode: Sized {
+    /// Reads a value from the front of `input`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SnapshotError::Truncated`] if `input` runs out first, and
+    /// [`SnapshotError::Invalid`] if the bytes are not a valid value.
+    fn decode(input: &mut Reader<'_>) -> Result<Self, SnapshotError>;
+}
+
+/// The unread part of a snapshot body.
+pub struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    /// Reads the next value.
+    ///
+    /// # Errors
+    ///
+    /// As [`Decode::decode`].
+    pub fn read<T: Decode>(&mut self) -> Result<T, SnapshotError> {
+        T::decode(self)
+    }
+
+    /// Takes the next `N` bytes.
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], SnapshotError> {
+        let (head, rest) = self
+            .bytes
+            .split_first_chunk()
+            .ok_or(SnapshotError::Truncated)?;
+        self.bytes = rest;
+        Ok(*head)
+    }
+
+    /// Returns the number of bytes left.
+    const fn remaining(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Returns the unread bytes.
+    const fn rest(&self) -> &'a [u8] {
+        self.bytes
+    }
+}
+
+impl Encode for u8 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(*self);
+    }
+}
+
+impl Decode for u8 {
+    fn decode(input: &mut Reader<'_>) -> Result<Self, SnapshotError> {
+        input.take().map(Self::from_le_bytes)
+    }
+}
+
+impl Encode for u16 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl Decode for u16 {
+    fn decode(input: &mut Reader<'_>) -> Result<Self, SnapshotError> {
+        input.take().map(Self::from_le_bytes)
+    }
+}
+
+impl Encode for u32 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl Decode for u32 {
+    fn decode(input: &mut Reader<'_>) -> Result<Self, SnapshotError> {
+        input.take().map(Self::from_le_bytes)
+    }
+}
+
+impl Encode for u64 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl Decode for u64 {
+    fn decode(input: &mut Reader<'_>) -> Result<Self, SnapshotError> {
+        input.take().map(Self::from_le_bytes)
+    }
+}
+
+impl Encode for i32 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl Decode for i32 {
+    fn decode(input: &mut Reader<'_>) -> Result<Self, SnapshotError> {
+        input.take().map(Self::from_le_bytes)
+    }
+}
+
+impl Encode for f32 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl Decode for f32 {
+    fn decode(input: &mut Reader<'_>) -> Result<Self, SnapshotError> {
+        input.take().map(Self::from_le_bytes)
+    }
+}
+
+impl Encode for bool {
+    fn encode(&self, out: &mut Vec<u8>) {
+        u8::from(*self).encode(out);
+    }
+}
+
+impl Decode for bool {
+    fn decode(input: &mut Reader<'_>) -> Result<Self, SnapshotError> {
+        match input.read::<u8>()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(SnapshotError::Invalid),
+        }
+    }
+}
+
+impl<T: Encode> Encode for Option<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.is_some().encode(out);
+        if let Some(value) = self {
+            value.encode(out);
+        }
+    }
+}
+
+impl<T: Decode> Decode for Option<T> {
+    fn decode(input: &mut Reader<'_>) -> Result<Self, SnapshotError> {
+        if input.read()? {
+            input.read().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl<T: Encode> Encode for [T] {
+    fn encode(&self, out: &mut Vec<u8>) {
+        // A state large enough to overflow this would not fit in memory.
+        u32::try_from(self.len()).unwrap_or(u32::MAX).encode(out);
+        for item in self {
+            item.encode(out);
+        }
+    }
+}
+
+impl<T: Encode> Encode for Vec<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.as_slice().encode(out);
+    }
+}
+
+impl<T: Decode> Decode for Vec<T> {
+    fn decode(input: &mut Reader<'_>) -> Result<Self, SnapshotError> {
+        let len = usize::try_from(input.read::<u32>()?).map_err(|_| SnapshotError::Invalid)?;
+        // Every item takes at least a byte, so a corrupt length cannot make
+        // this allocate more than the input could hold.
+        let mut items = Self::with_capacity(len.min(input.remaining()));
+        for _ in 0..len {
+            items.push(input.read()?);
+        }
+        Ok(items)
+    }
+}
+
+impl Encode for str {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.as_bytes().encode(out);
+    }
+}
+
+impl Encode for String {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.as_str().encode(out);
+    }
+}
+
+impl Decode for String {
+    fn decode(input: &mut Reader<'_>) -> Result<Self, SnapshotError> {
+        Self::from_utf8(input.read()?).map_err(|_| SnapshotError::Invalid)
+    }
+}
+
+impl<T: Encode, const N: usize> Encode for [T; N] {
+    fn encode(&self, out: &mut Vec<u8>) {
+        for item in self {
+            item.encode(out);
+        }
+    }
+}
+
+impl<T: Decode, const N: usize> Decode for [T; N] {
+    fn decode(input: &mut Reader<'_>) -> Result<Self, SnapshotError> {
+        let mut items = Vec::with_capacity(N);
+        for _ in 0..N {
+            items.push(input.read()?);
+        }
+        items.try_into().map_err(|_| SnapshotError::Invalid)
+    }
+}
+
+impl<A: Encode, B: Encode> Encode for (A, B) {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.0.encode(out);
+        self.1.encode(out);
+    }
+}
+
+impl<A: Decode, B: Decode> Decode for (A, B) {
+    fn decode(input: &mut Reader<'_>) -> Result<Self, SnapshotError> {
+        Ok((input.read()?, input.read()?))
+    }
+}
+
+/// Encodes fieldless enums as their one-byte discriminants.
+macro_rules! tagged {
+    ($($kind:ty { $($variant:ident),+ $(,)? })+) => {$(
+        impl Encode for $kind {
+            fn encode(&self, out: &mut Vec<u8>) {
+                (*self as u8).encode(out);
+            }
+        }
+
+        impl Decode for $kind {
+            fn decode(input: &mut Reader<'_>) -> Result<Self, SnapshotError> {
+                let tag: u8 = input.read()?;
+                [$(<$kind>::$variant),+]
+                    .into_iter()
+                    .find(|&variant| variant as u8 == tag)
+                    .ok_or(SnapshotError::Invalid)
+            }
+        }
+    )+};
+}
+
+pub(crate) use tagged;
+
+tagged! {
+    BallKind {
+        Plain, Cue, Solid, Eight, Stripe, Red, Yellow, Green, Brown, Blue, Pink, Black,
+        SecondCue, CaromRed,
+    }
+    Pocket { TopLeft, TopSide, TopRight, BottomLeft, BottomSide, BottomRight }
+    Rail { Top, Right, Bottom, Left }
+    EventKind { BallContact, CushionContact, Pocketed, BallStopped, AllStopped }
+    Foul { NoContact, WrongBallFirst, NoRail, Scratch, IllegalBreak, WrongBallPotted }
+    BallInHand { No, Anywhere, Kitchen, InD }
+    Group { Solids, Stripes }
+    CaromGame { StraightRail, Balkline, ThreeCushion }
+    Discipline {
+        EightBall, NineBall, TenBall, StraightPool, Snooker, StraightRail, Balkline, ThreeCushion,
+    }
+    BreakFormat { Winner, Alternate, Loser }
+}
+
+impl Encode for Vector2D {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.x.encode(out);
+        self.y.encode(out);
+    }
+}
+
+impl Decode for Vector2D {
+    fn decode(input: &mut Reader<'_>) -> Result<Self, SnapshotError> {
+        Ok(Self::new(input.read()?, input.read()?))
+    }
+}
+
+impl Encode for Vector3D {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.x.encode(out);
+        self.y.encode(out);
+        self.z.encode(out);
+    }
+}
+
+impl Decode for Vector3D {
+    fn decode(input: &mut Reader<'_>) -> Result<Self, SnapshotError> {
+        Ok(Self::new(input.read()?, input.read()?, input.read()?))
+    }
+}
+
+impl Encode for Ball {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.id.encode(out);
+        self.number.encode(out);
+        self.kind.encode(out);
+        self.color.encode(out);
+        self.position.encode(out);
+        self.velocity.encode(out);
+        self.radius.encode(out);
+        self.mass.encode(out);
+        self.angular_velocity.encode(out);
+    }
+}
+
+impl Decode for Ball {
+    fn decode(input: &mut Reader<'_>) -> Result<Self, SnapshotError> {
+        Ok(Self {
+            id: input.read()?,
+            number: input.read()?,
+            kind: input.read()?,
+            color: input.read()?,
+            position: input.read()?,
+            velocity: input.read()?,
+            radius: input.read()?,
+            mass: input.read()?,
+            angular_velocity: input.read()?,
+        })
+    }
+}
+
+impl Encode for PocketSpec {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.mouth.encode(out);
+        self.jaw_angle.encode(out);
+        self.shelf_depth.encode(out);
+    }
+}
+
+impl Decode for PocketSpec {
+    fn decode(input: &mut Reader<'_>) -> Result<Self, SnapshotError> {
+        Ok(Self::new(input.read()?, input.read()?, input.read()?))
+    }
+}
+
+impl Encode for Table {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.width.encode(out);
+        self.height.encode(out);
+        self.slide_friction.encode(out);
+        self.roll_friction.encode(out);
+        self.spin_friction.encode(out);
+        self.corner_pockets.encode(out);
+        self.side_pockets.encode(out);
+        self.cushion_height.encode(out);
+        self.cushion_restitution.encode(out);
+        self.cushion_restitution_falloff.encode(out);
+        self.cushion_friction.encode(out);
+        self.baulk_line.encode(out);
+        self.d_radius.encode(out);
+    }
+}
+
+impl Decode for Table {
+    fn decode(input: &mut Reader<'_>) -> Result<Self, SnapshotError> {
+        Ok(Self {
+            width: input.read()?,
+            height: input.read()?,
+            slide_friction: input.read()?,
+            roll_friction: input.read()?,
+            spin_friction: input.read()?,
+            corner_pockets: input.read()?,
+            side_pockets: input.read()?,
+            cushion_height: input.read()?,
+            cushion_restitution: input.read()?,
+            cushion_restitution_falloff: input.read()?,
+            cushion_friction: input.read()?,
+            baulk_line: input.read()?,
+            d_radius: input.read()?,
+        })
+    }
+}
+
+impl Encode for CueStroke {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.aim_angle.encode(out);
+        self.speed.encode(out);
+        self.tip_x.encode(out);
+        self.tip_y.encode(out);
+        self.elevation.encode(out);
+    }
+}
+
+impl Decode for CueStroke {
+    fn decode(input: &mut Reader<'_>) -> Result<Self, SnapshotError> {
+        Ok(Self::new(
+            input.read()?,
+            input.read()?,
+            input.read()?,
+            input.read()?,
+            input.read()?,
+        ))
+    }
+}
+
+impl Encode for MatchFormat {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.discipline.encode(out);
+        self.race_to.encode(out);
+        self.break_format.encode(out);
+        self.points.encode(out);
+        self.timeouts.encode(out);
+        self.seed.encode(out);
+        self.gap.encode(out);
+    }
+}
+
+impl Decode for MatchFormat {
+    fn decode(input: &mut Reader<'_>) -> Result<Self, SnapshotError> {
+        Ok(Self {
+            discipline: input.read()?,
+            race_to: input.read()?,
+            break_format: input.read()?,
+            points: input.read()?,
+            timeouts: input.read()?,
+            seed: input.read()?,
+            gap: input.read()?,
+        })
+    }
+}
+
+impl Encode for GameState {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.balls.encode(out);
+        self.table.encode(out);
+        self.pocketed.encode(out);
+        self.rules.encode(out);
+        self.events.encode(out);
+        self.time.encode(out);
+        self.friction_countdown.encode(out);
+    }
+}
+
+impl Decode for GameState {
+    fn decode(input: &mut Reader<'_>) -> Result<Self, SnapshotError> {
+        let state = Self {
+            balls: input.read()?,
+            table: input.read()?,
+            pocketed: input.read::<Vec<PocketedBall>>()?,
+            rules: input.read::<Option<Rules>>()?,
+            events: input.read::<Vec<Event>>()?,
+            time: input.read()?,
+            friction_countdown: input.read()?,
+        };
+        if !state.is_consistent() {
+            return Err(SnapshotError::Invalid);
+        }
+        Ok(state)
+    }
+}
+
+#[wasm_bindgen]
+impl GameState {
+    /// Returns a binary snapshot of this state at [`SNAPSHOT_VERSION`],
+    /// including its rules and any undrained events. In JS this is a
+    /// `Uint8Array`.
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        seal(SIGNATURE, SNAPSHOT_VERSION, self)
+    }
+
+    /// Reads a state back from a snapshot written by
+    /// [`GameState::to_bytes`]. In JS, a snapshot that cannot be read
+    /// throws an `Error` saying why.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`SnapshotError`] if the bytes are not a whole, intact
+    /// snapshot of a version this build reads.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        unseal(bytes, SIGNATURE, SNAPSHOT_VERSION)
+    }
+
+    /// Returns a 64-bit hash of this state: the balls, the table, the
+    /// pocketed balls, the rules and the clocks. Recorded events are left
+    /// out, so draining them does not change it. Two states with the same
+    /// hash play on identically. In JS this is a `BigInt`.
+    #[must_use]
+    pub fn state_hash(&self) -> u64 {
+        let mut bytes = Vec::with_capacity(64 * (self.balls.len() + 4));
+        self.balls.encode(&mut bytes);
+        self.table.encode(&mut bytes);
+        self.pocketed.encode(&mut bytes);
+        self.rules.encode(&mut bytes);
+        self.time.encode(&mut bytes);
+        self.friction_countdown.encode(&mut bytes);
+        fnv1a(&bytes)
+    }
+}
+
+/// Writes `value` in the layout described in the module docs, headed by
+/// `signature` and `version`.
+pub fn seal<T: Encode + ?Sized>(signature: [u8; 4], version: u16, value: &T) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(1024);
+    bytes.extend_from_slice(&signature);
+    version.encode(&mut bytes);
+    // The body length is filled in once the body is written.
+    0_u32.encode(&mut bytes);
+    value.encode(&mut bytes);
+    let body = u32::try_from(bytes.len() - HEADER_LEN).unwrap_or(u32::MAX);
+    bytes[HEADER_LEN - 4..HEADER_LEN].copy_from_slice(&body.to_le_bytes());
+    crc32(&bytes).encode(&mut bytes);
+    bytes
+}
+
+/// Reads back a value written by [`seal`] with `signature` and `version`.
+///
+/// # Errors
+///
+/// Returns a [`SnapshotError`] if the bytes are not a whole, intact
+/// container of that signature and version.
+pub fn unseal<T: Decode>(
+    bytes: &[u8],
+    signature: [u8; 4],
+    version: u16,
+) -> Result<T, SnapshotError> {
+    if !bytes.starts_with(&signature) {
+        return Err(if signature.starts_with(bytes) {
+            SnapshotError::Truncated
+        } else {
+            SnapshotError::NotASnapshot
+        });
+    }
+    let mut header = Reader {
+        bytes: &bytes[signature.len()..],
+    };
+    let written: u16 = header.read()?;
+    let body = usize::try_from(header.read::<u32>()?).map_err(|_| SnapshotError::Invalid)?;
+    let len = HEADER_LEN
+        .checked_add(body)
+        .ok_or(SnapshotError::Truncated)?;
+    if bytes.len() < len + CHECKSUM_LEN {
+        return Err(SnapshotError::Truncated);
+    }
+    let (contents, rest) = bytes.split_at(len);
+    let checksum: u32 = Reader { bytes: rest }.read()?;
+    if checksum != crc32(contents) {
+        return Err(SnapshotError::Checksum);
+    }
+    if written != version {
+        return Err(SnapshotError::UnsupportedVersion {
+            found: written,
+            expected: version,
+        });
+    }
+    if rest.len() > CHECKSUM_LEN {
+        return Err(SnapshotError::Invalid);
+    }
+
+    let mut input = Reader {
+        bytes: &contents[HEADER_LEN..],
+    };
+    let value = input.read()?;
+    if !input.rest().is_empty() {
+        return Err(SnapshotError::Invalid);
+    }
+    Ok(value)
+}
+
+/// Lookup table for [`crc32`], one entry per byte value.
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut byte: u32 = 0;
+    while byte < 256 {
+        let mut crc = byte;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 0 {
+                crc >> 1
+            } else {
+                (crc >> 1) ^ 0xEDB8_8320
+            };
+            bit += 1;
+        }
+        table[byte as usize] = crc;
+        byte += 1;
+    }
+    table
+};
+
+/// Returns the 64-bit FNV-1a hash of `bytes`.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xCBF2_9CE4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01B3)
+    })
+}
+
+/// Returns the CRC-32 (IEEE 802.3) of `bytes`.
+fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0, |crc, &byte| {
+        CRC_TABLE[((crc ^ u32::from(byte)) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{tick, CueStroke};
+
+    /// A snooker frame part way through, with spin still on the balls.
+    fn in_play() -> GameState {
+        let mut state = GameState::snooker(&Table::snooker(), 5, 0.0);
+        let stroke = CueStroke::new(0.1, 1200.0, 0.2, 0.3, 0.0);
+        assert!(state.strike(&stroke));
+        for _ in 0..30 {
+            tick(&mut state, 1.0 / 60.0);
+        }
+        state
+    }
+
+    /// Rewrites the checksum of edited snapshot bytes to match.
+    fn reseal(mut bytes: Vec<u8>) -> Vec<u8> {
+        let end = bytes.len() - CHECKSUM_LEN;
+        let checksum = crc32(&bytes[..end]);
+        bytes[end..].copy_from_slice(&checksum.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn crc_matches_the_standard_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn snapshot_round_trips_the_whole_state() {
+        let state = in_play();
+
+        let bytes = state.to_bytes();
+        let restored = GameState::from_bytes(&bytes).unwrap();
+
+        assert_eq!(&bytes[..4], b"PSIM");
+        assert_eq!(restored.to_bytes(), bytes);
+        let (ball, was) = (&restored.balls()[0], &state.balls()[0]);
+        assert!((ball.angular_velocity - was.angular_velocity).length() < f32::EPSILON);
+        assert!(ball.is_moving());
+        assert_eq!(restored.current_player(), state.current_player());
+        assert_eq!(restored.events(), state.events());
+
+        // The restored game plays on exactly as the original does.
+        let (mut original, mut copy) = (state, restored);
+        for _ in 0..600 {
+            tick(&mut original, 1.0 / 60.0);
+            tick(&mut copy, 1.0 / 60.0);
+        }
+        assert_eq!(copy.to_bytes(), original.to_bytes());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn snapshot_is_smaller_than_its_json() {
+        let state = in_play();
+
+        assert!(state.to_bytes().len() * 3 < state.to_json().len());
+    }
+
+    #[test]
+    fn corrupted_snapshots_are_refused() {
+        let bytes = in_play().to_bytes();
+
+        let mut flipped = bytes.clone();
+        flipped[bytes.len() / 2] ^= 0x10;
+        assert_eq!(
+            GameState::from_bytes(&flipped).unwrap_err(),
+            SnapshotError::Checksum
+        );
+
+        assert_eq!(
+            GameState::from_bytes(&bytes[..bytes.len() - 1]).unwrap_err(),
+            SnapshotError::Truncated
+        );
+        assert_eq!(
+            GameState::from_bytes(&bytes[..2]).unwrap_err(),
+            SnapshotError::Truncated
+        );
+        assert_eq!(
+            GameState::from_bytes(b"{\"version\": 1}").unwrap_err(),
+            SnapshotError::NotASnapshot
+        );
+
+        let mut trailing = bytes;
+        trailing.push(0);
+        assert_eq!(
+            GameState::from_bytes(&trailing).unwrap_err(),
+            SnapshotError::Invalid
+        );
+    }
+
+    #[test]
+    fn other_versions_and_invalid_bodies_are_refused() {
+        let bytes = in_play().to_bytes();
+
+        let mut newer = bytes.clone();
+        newer[4..6].copy_from_slice(&(SNAPSHOT_VERSION + 1).to_le_bytes());
+        assert_eq!(
+            GameState::from_bytes(&reseal(newer)).unwrap_err(),
+            SnapshotError::UnsupportedVersion {
+                found: SNAPSHOT_VERSION + 1,
+                expected: SNAPSHOT_VERSION,
+            }
+        );
+        // Replays and matches have versions of their own, and the message
+        // gives the one the reader expected.
+        let error = unseal::<GameState>(&bytes, SIGNATURE, 7).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!("snapshot has format version {SNAPSHOT_VERSION}, this build reads 7")
+        );
+
+        // The first ball's kind, with a tag no kind has.
+        let mut invalid = bytes;
+        invalid[HEADER_LEN + 4 + 8] = 0xFF;
+        assert_eq!(
+            GameState::from_bytes(&reseal(invalid)).unwrap_err(),
+            SnapshotError::Invalid
+        );
+    }
+
+    #[test]
+    fn bodies_play_could_not_reach_are_refused() {
+        let state = GameState::eight_ball(&Table::pool(800.0, 400.0), 1, 0.0);
+
+        // The player to shoot, after the rules' tags and cue ball id.
+        let mut before_rules = Vec::new();
+        state.balls.encode(&mut before_rules);
+        state.table.encode(&mut before_rules);
+        state.pocketed.encode(&mut before_rules);
+        let player = HEADER_LEN + before_rules.len() + 2 + 4;
+        let mut third_player = state.to_bytes();
+        assert_eq!(third_player[player..player + 4], 0_u32.to_le_bytes());
+        third_player[player..player + 4].copy_from_slice(&7_u32.to_le_bytes());
+        assert_eq!(
+            GameState::from_bytes(&reseal(third_player)).unwrap_err(),
+            SnapshotError::Invalid
+        );
+
+        let mut no_cue_ball = state.clone();
+        no_cue_ball.balls.remove(0);
+        assert_eq!(
+            GameState::from_bytes(&no_cue_ball.to_bytes()).unwrap_err(),
+            SnapshotError::Invalid
+        );
+
+        let mut lost = state;
+        lost.balls[3].position.x = f32::NAN;
+        assert_eq!(
+            GameState::from_bytes(&lost.to_bytes()).unwrap_err(),
+            SnapshotError::Invalid
+        );
+    }
+}
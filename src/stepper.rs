@@ -0,0 +1,311 @@
+//! Fixed-timestep stepping.
+//!
+//! The browser calls back once per display frame with however much time has
+//! passed, and that varies from frame to frame and from device to device. A
+//! [`Stepper`] banks the time in an accumulator and spends it in whole steps
+//! of a fixed length through [`tick`], so the simulation sees the same steps
+//! whatever the frame timing and plays out the same way every time.
+//!
+//! What is left in the accumulator, as a fraction of a step, is the
+//! interpolation alpha. Drawing the balls that fraction of the way from
+//! where they were before the last step to where they are after it keeps
+//! motion smooth between steps without running the simulation ahead.
+
+use wasm_bindgen::prelude::*;
+
+use crate::{replay::STEPS, tick, GameState, Vector2D};
+
+/// Step used by a stepper created with a step outside [`STEPS`], in
+/// seconds.
+const DEFAULT_STEP: f32 = 1.0 / 120.0;
+
+/// Default limit on the steps taken by one [`Stepper::advance`].
+const DEFAULT_MAX_STEPS: u32 = 30;
+
+/// Spends real elapsed time on a game in fixed steps.
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct Stepper {
+    /// Simulation time, in seconds, per step.
+    step: f32,
+    /// Most steps one advance may take.
+    max_steps: u32,
+    /// Elapsed time not yet spent on a step, in seconds.
+    accumulator: f32,
+    /// Steps taken by the last advance.
+    last_steps: u32,
+    /// Ball positions before the last step, by id.
+    previous: Vec<(u32, Vector2D)>,
+}
+
+#[wasm_bindgen]
+impl Stepper {
+    /// Creates a stepper that advances a game in steps of `step` seconds.
+    /// A step outside 0.0001 to 0.1 s, or not a number, is replaced by
+    /// 1/120 s, as for a replay.
+    #[wasm_bindgen(constructor)]
+    #[must_use]
+    pub fn new(step: f32) -> Self {
+        Self {
+            step: if STEPS.contains(&step) {
+                step
+            } else {
+                DEFAULT_STEP
+            },
+            max_steps: DEFAULT_MAX_STEPS,
+            accumulator: 0.0,
+            last_steps: 0,
+            previous: Vec::new(),
+        }
+    }
+
+    /// Returns the simulation time, in seconds, per step.
+    #[must_use]
+    pub fn step(&self) -> f32 {
+        self.step
+    }
+
+    /// Returns the most steps one [`Stepper::advance`] may take.
+    #[must_use]
+    pub fn max_steps(&self) -> u32 {
+        self.max_steps
+    }
+
+    /// Sets the most steps one [`Stepper::advance`] may take, at least one.
+    ///
+    /// Time beyond the limit is dropped, so a device that cannot keep up
+    /// runs the game in slow motion rather than falling ever further
+    /// behind.
+    pub fn set_max_steps(&mut self, max_steps: u32) {
+        self.max_steps = max_steps.max(1);
+    }
+
+    /// Adds `elapsed` seconds of real time and advances `state` by as many
+    /// whole steps as the time banked allows. Returns the interpolation
+    /// alpha: the time left over, as a fraction of a step.
+    ///
+    /// Negative or non-finite times are ignored.
+    pub fn advance(&mut self, state: &mut GameState, elapsed: f32) -> f32 {
+        if elapsed.is_finite() && elapsed > 0.0 {
+            self.accumulator += elapsed;
+        }
+        self.last_steps = 0;
+        let step = self.step;
+        loop {
+            if self.accumulator < step {
+                break;
+            }
+            if self.last_steps == self.max_steps {
+                self.accumulator %= step;
+                break;
+            }
+            self.previous.clear();
+            self.previous
+                .extend(state.balls.iter().map(|ball| (ball.id, ball.position)));
+            tick(state, self.step);
+            self.accumulator -= step;
+            self.last_steps += 1;
+        }
+        self.alpha()
+    }
+
+    /// Returns the interpolation alpha: the time banked but not yet spent,
+    /// as a fraction of a step, from 0 up to but not including 1.
+    #[must_use]
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / self.step).clamp(0.0, 1.0)
+    }
+
+    /// Returns the number of steps the last [`Stepper::advance`] took.
+    #[must_use]
+    pub fn last_steps(&self) -> u32 {
+        self.last_steps
+    }
+
+    /// Returns the balls of `state` as they should be drawn now: each
+    /// [`Stepper::alpha`] of the way from where it was before the last step
+    /// to where it is. Balls that were not on the table before the last
+    /// step are drawn where they are.
+    ///
+    /// `state` should be the game this stepper advances. A ball moved by
+    /// hand, such as a cue ball placed in hand, appears to slide there over
+    /// one step unless [`Stepper::reset`] is called after moving it.
+    #[must_use]
+    pub fn render_state(&self, state: &GameState) -> RenderState {
+        let alpha = self.alpha();
+        let (ball_ids, positions) = state
+            .balls
+            .iter()
+            .map(|ball| {
+                let before = self
+                    .previous
+                    .iter()
+                    .find(|&&(id, _)| id == ball.id)
+                    .map_or(ball.position, |&(_, position)| position);
+                (ball.id, before + (ball.position - before) * alpha)
+            })
+            .unzip();
+        RenderState {
+            alpha,
+            ball_ids,
+            positions,
+        }
+    }
+
+    /// Forgets the banked time and the positions before the last step, so
+    /// the next render shows the balls where they are.
+    pub fn reset(&mut self) {
+        self.accumulator = 0.0;
+        self.last_steps = 0;
+        self.previous.clear();
+    }
+}
+
+/// The balls of a game as they should be drawn between two steps.
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct RenderState {
+    /// The interpolation alpha the positions were taken at.
+    alpha: f32,
+    /// Ids of the balls on the table, in index order.
+    ball_ids: Vec<u32>,
+    /// Interpolated position of each ball, in the same order.
+    positions: Vec<Vector2D>,
+}
+
+#[wasm_bindgen]
+impl RenderState {
+    /// Returns the interpolation alpha the positions were taken at.
+    #[must_use]
+    pub fn alpha(&self) -> f32 {
+        self.alpha
+    }
+
+    /// Returns the ids of the balls on the table, in index order.
+    #[must_use]
+    pub fn ball_ids(&self) -> Vec<u32> {
+        self.ball_ids.clone()
+    }
+
+    /// Returns the position to draw each ball at, as flat `x, y` pairs in
+    /// the order of [`RenderState::ball_ids`].
+    #[must_use]
+    pub fn positions(&self) -> Vec<f32> {
+        self.positions
+            .iter()
+            .flat_map(|position| [position.x, position.y])
+            .collect()
+    }
+
+    /// Returns the position to draw the ball with the given id at, or
+    /// `undefined` if it is not on the table.
+    #[must_use]
+    pub fn position(&self, id: u32) -> Option<Vector2D> {
+        self.ball_ids
+            .iter()
+            .position(|&ball| ball == id)
+            .map(|index| self.positions[index])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Ball, CueStroke, Table};
+
+    const STEP: f32 = 1.0 / 120.0;
+
+    fn break_shot() -> GameState {
+        let mut state = GameState::eight_ball(&Table::pool(800.0, 400.0), 4, 0.0);
+        assert!(state.strike(&CueStroke::new(0.0, 900.0, 0.0, 0.0, 0.0)));
+        state
+    }
+
+    #[test]
+    fn frame_timing_does_not_change_the_outcome() {
+        let (mut steady, mut jittery, mut reference) = (break_shot(), break_shot(), break_shot());
+        let (mut a, mut b) = (Stepper::new(STEP), Stepper::new(STEP));
+        let (mut steps_a, mut steps_b) = (0, 0);
+        // Four seconds at a steady 60 Hz, and four seconds of uneven frames,
+        // both starting part way into a step so that rounding in the frame
+        // times cannot tip the last one over.
+        a.advance(&mut steady, STEP / 2.0);
+        b.advance(&mut jittery, STEP / 2.0);
+        for _ in 0..240 {
+            a.advance(&mut steady, 1.0 / 60.0);
+            steps_a += a.last_steps();
+        }
+        for frame in 0..320 {
+            let elapsed = if frame % 2 == 0 { 0.005 } else { 0.02 };
+            b.advance(&mut jittery, elapsed);
+            steps_b += b.last_steps();
+        }
+        for _ in 0..steps_a {
+            tick(&mut reference, STEP);
+        }
+
+        assert_eq!(steps_a, steps_b);
+        assert_eq!(steady.state_hash(), jittery.state_hash());
+        assert_eq!(steady.state_hash(), reference.state_hash());
+    }
+
+    #[test]
+    fn leftover_time_becomes_the_alpha() {
+        let mut state = break_shot();
+        let mut stepper = Stepper::new(0.01);
+
+        let alpha = stepper.advance(&mut state, 0.025);
+
+        assert_eq!(stepper.last_steps(), 2);
+        assert!((alpha - 0.5).abs() < 1e-3);
+        assert!(stepper.advance(&mut state, -1.0) > 0.49);
+        assert_eq!(stepper.last_steps(), 0);
+    }
+
+    #[test]
+    fn render_state_interpolates_between_the_last_two_steps() {
+        let mut state = GameState::new(
+            Table::pool(800.0, 400.0),
+            vec![Ball::new(200.0, 200.0, 0.0, 0.0, 10.0)],
+        );
+        assert!(state.strike(&CueStroke::new(0.0, 300.0, 0.0, 0.0, 0.0)));
+        let mut stepper = Stepper::new(STEP);
+        stepper.advance(&mut state, STEP * 10.5);
+        let after = state.ball(0).position;
+        let before = stepper.previous[0].1;
+
+        let render = stepper.render_state(&state);
+
+        assert!(before.x < after.x);
+        let drawn = render.position(0).unwrap();
+        assert!((drawn.x - (before.x + after.x) * 0.5).abs() < 1e-3);
+        assert_eq!(render.positions(), vec![drawn.x, drawn.y]);
+        assert!(render.position(1).is_none());
+
+        stepper.reset();
+        let drawn = stepper.render_state(&state).position(0).unwrap();
+        assert!((drawn - after).length() < f32::EPSILON);
+    }
+
+    #[test]
+    fn steps_outside_the_replay_range_fall_back_to_the_default() {
+        assert!((Stepper::new(0.01).step() - 0.01).abs() < f32::EPSILON);
+        for step in [0.0, -1.0, 1e-6, 0.5, f32::NAN, f32::INFINITY] {
+            assert!((Stepper::new(step).step() - DEFAULT_STEP).abs() < f32::EPSILON);
+        }
+    }
+
+    #[test]
+    fn backlog_beyond_the_step_limit_is_dropped() {
+        let mut state = break_shot();
+        let mut stepper = Stepper::new(STEP);
+        stepper.set_max_steps(4);
+
+        let alpha = stepper.advance(&mut state, 1.0);
+
+        assert_eq!(stepper.last_steps(), 4);
+        assert!(alpha < 1.0);
+        stepper.advance(&mut state, 0.0);
+        assert_eq!(stepper.last_steps(), 0);
+    }
+}
@@ -0,0 +1,291 @@
+//! Aiming guides.
+//!
+//! [`GameState::trajectory`] plays a stroke on a private copy of the table,
+//! as [`GameState::predict`] does, and follows the cue ball: the path it
+//! takes to the first ball it strikes, the ghost ball where the two touch,
+//! the line the object ball is sent along and where the cue ball goes
+//! afterwards. Because the path comes from the physics rather than from
+//! straight lines, it already bends with squirt, swerve and cushion spin.
+//!
+//! Paths cross into JS as flat `[x0, y0, x1, y1, ...]` arrays, which arrive
+//! as a `Float32Array` ready to be drawn as a polyline.
+
+use wasm_bindgen::prelude::*;
+
+use crate::{physics, CueStroke, EventKind, GameState, Vector2D};
+
+/// Simulation time, in seconds, between the points of a path.
+const SAMPLE_STEP: f32 = 1.0 / 120.0;
+
+/// Speeds, in units per second, below which a tangent line is not defined.
+const MIN_TANGENT_SPEED: f32 = 1e-3;
+
+/// The path of the cue ball for a stroke, and its first contact.
+#[wasm_bindgen]
+#[derive(Clone, Debug, Default)]
+pub struct Trajectory {
+    /// The cue ball's path up to its first ball contact, or its whole path
+    /// if it touches no ball.
+    path: Vec<Vector2D>,
+    /// The cue ball's path from its first ball contact on.
+    deflected_path: Vec<Vector2D>,
+    /// Where the cue ball's centre is when it first touches a ball.
+    ghost_ball: Option<Vector2D>,
+    /// The ball the cue ball touches first.
+    object_ball: Option<u32>,
+    /// Unit direction from the ghost ball to the object ball's centre.
+    object_direction: Option<Vector2D>,
+    /// Unit direction of the tangent line at the first contact.
+    tangent_direction: Option<Vector2D>,
+}
+
+#[wasm_bindgen]
+impl Trajectory {
+    /// Returns the cue ball's path up to its first ball contact, ending at
+    /// the ghost ball, as flat `x, y` pairs. If it touches no ball, this is
+    /// its whole path.
+    #[must_use]
+    pub fn path(&self) -> Vec<f32> {
+        flatten(&self.path)
+    }
+
+    /// Returns the cue ball's path after its first ball contact, starting
+    /// at the ghost ball, as flat `x, y` pairs. Empty if it touches no ball.
+    #[must_use]
+    pub fn deflected_path(&self) -> Vec<f32> {
+        flatten(&self.deflected_path)
+    }
+
+    /// Returns where the cue ball's centre is when it first touches another
+    /// ball, or `undefined` if it touches none.
+    #[must_use]
+    pub fn ghost_ball(&self) -> Option<Vector2D> {
+        self.ghost_ball
+    }
+
+    /// Returns the id of the ball the cue ball touches first.
+    #[must_use]
+    pub fn object_ball(&self) -> Option<u32> {
+        self.object_ball
+    }
+
+    /// Returns the unit direction the object ball is sent along, the line
+    /// from the ghost ball through the object ball's centre. Cut-induced
+    /// throw is not included.
+    #[must_use]
+    pub fn object_direction(&self) -> Option<Vector2D> {
+        self.object_direction
+    }
+
+    /// Returns the unit direction of the tangent line, square to the object
+    /// direction on the side the cue ball was travelling. A stun shot sends
+    /// the cue ball along it. Undefined for a full-ball hit, where there is
+    /// no tangent.
+    #[must_use]
+    pub fn tangent_direction(&self) -> Option<Vector2D> {
+        self.tangent_direction
+    }
+}
+
+#[wasm_bindgen]
+impl GameState {
+    /// Traces the cue ball for a stroke with `stroke`, for drawing aiming
+    /// guides.
+    ///
+    /// The stroke is played on a copy of the table, as by
+    /// [`GameState::predict`], until the cue ball stops or drops or
+    /// `max_time` seconds have passed. This state is left untouched. If
+    /// the cue ball is not on the table, or the stroke is not valid, the
+    /// trajectory is empty.
+    #[must_use]
+    pub fn trajectory(&self, stroke: &CueStroke, max_time: f32) -> Trajectory {
+        let Some(cue) = self
+            .cue_ball_id()
+            .filter(|&id| self.is_on_table(id) && stroke.is_valid())
+        else {
+            return Trajectory::default();
+        };
+        let mut trial = self.trial(stroke);
+        let mut path: Vec<Vector2D> = trial
+            .find_ball(cue)
+            .map(|ball| ball.position)
+            .into_iter()
+            .collect();
+        // The time of the first ball contact, the ball touched and how many
+        // points of the path came before it.
+        let mut contact = None;
+
+        let mut elapsed = 0.0;
+        loop {
+            let Some(ball) = trial.find_ball(cue) else {
+                if let Some(record) = trial.pocketed.iter().find(|record| record.ball_id() == cue) {
+                    path.push(record.ball().position);
+                }
+                break;
+            };
+            if !ball.is_moving() || elapsed >= max_time {
+                break;
+            }
+            let step = SAMPLE_STEP.min(max_time - elapsed);
+            let first_new = trial.events.len();
+            physics::advance(&mut trial, step);
+            elapsed += step;
+
+            if contact.is_none() {
+                contact = trial.events[first_new..]
+                    .iter()
+                    .filter(|event| event.kind == EventKind::BallContact)
+                    .find_map(|event| {
+                        let other = if event.ball() == Some(cue) {
+                            event.other_ball()
+                        } else if event.other_ball() == Some(cue) {
+                            event.ball()
+                        } else {
+                            None
+                        };
+                        other.map(|other| (event.time, other, path.len()))
+                    });
+            }
+            if let Some(ball) = trial.find_ball(cue) {
+                path.push(ball.position);
+            }
+        }
+
+        let Some((time, object, split)) = contact else {
+            return Trajectory {
+                path,
+                ..Trajectory::default()
+            };
+        };
+
+        // Replay the stroke up to the contact itself to find the ghost ball.
+        let mut replay = self.trial(stroke);
+        let until = time - replay.time;
+        physics::advance(&mut replay, until);
+        let (Some(ghost), Some(target)) = (replay.find_ball(cue), replay.find_ball(object)) else {
+            return Trajectory {
+                path,
+                ..Trajectory::default()
+            };
+        };
+        let line = target.position - ghost.position;
+        let object_direction = line / line.length();
+        let velocity = ghost.velocity;
+        let tangent = velocity - object_direction * velocity.dot(object_direction);
+        let tangent_direction =
+            (tangent.length() > MIN_TANGENT_SPEED).then(|| tangent / tangent.length());
+
+        let mut deflected_path = path.split_off(split);
+        path.push(ghost.position);
+        deflected_path.insert(0, ghost.position);
+        Trajectory {
+            path,
+            deflected_path,
+            ghost_ball: Some(ghost.position),
+            object_ball: Some(object),
+            object_direction: Some(object_direction),
+            tangent_direction,
+        }
+    }
+}
+
+/// Flattens `points` into `x, y` pairs.
+fn flatten(points: &[Vector2D]) -> Vec<f32> {
+    points.iter().flat_map(|point| [point.x, point.y]).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Ball, Table};
+
+    /// A cue ball at (200, 200) and an object ball at (400, 215).
+    fn cut_shot() -> GameState {
+        GameState::new(
+            Table::pool(800.0, 400.0),
+            vec![
+                Ball::new(200.0, 200.0, 0.0, 0.0, 10.0),
+                Ball::new(400.0, 215.0, 0.0, 0.0, 10.0),
+            ],
+        )
+    }
+
+    #[test]
+    fn ghost_ball_touches_the_object_ball() {
+        let state = cut_shot();
+
+        let trajectory = state.trajectory(&CueStroke::new(0.0, 300.0, 0.0, 0.0, 0.0), 10.0);
+
+        assert_eq!(trajectory.object_ball(), Some(1));
+        let ghost = trajectory.ghost_ball().unwrap();
+        assert!((ghost.y - 200.0).abs() < 0.1);
+        let gap = (Vector2D::new(400.0, 215.0) - ghost).length();
+        assert!((gap - 20.0).abs() < 0.1);
+
+        let path = trajectory.path();
+        assert_eq!(&path[..2], &[200.0, 200.0]);
+        assert_eq!(&path[path.len() - 2..], &[ghost.x, ghost.y]);
+        let deflected = trajectory.deflected_path();
+        assert_eq!(&deflected[..2], &[ghost.x, ghost.y]);
+        assert!(deflected.len() > 4);
+        assert!(state.events().is_empty());
+    }
+
+    #[test]
+    fn cut_shot_splits_along_the_tangent_line() {
+        let trajectory = cut_shot().trajectory(&CueStroke::new(0.0, 300.0, 0.0, 0.0, 0.0), 10.0);
+
+        let object = trajectory.object_direction().unwrap();
+        let tangent = trajectory.tangent_direction().unwrap();
+        assert!(object.dot(tangent).abs() < 1e-3);
+        // The object ball is cut downwards, so the cue ball leaves upwards.
+        assert!(object.y > 0.0 && object.x > 0.0);
+        assert!(tangent.y < 0.0 && tangent.x > 0.0);
+    }
+
+    #[test]
+    fn full_hit_has_no_tangent() {
+        let state = GameState::new(
+            Table::pool(800.0, 400.0),
+            vec![
+                Ball::new(200.0, 200.0, 0.0, 0.0, 10.0),
+                Ball::new(400.0, 200.0, 0.0, 0.0, 10.0),
+            ],
+        );
+
+        let trajectory = state.trajectory(&CueStroke::new(0.0, 300.0, 0.0, 0.0, 0.0), 10.0);
+
+        let object = trajectory.object_direction().unwrap();
+        assert!((object.x - 1.0).abs() < 1e-4);
+        assert!(trajectory.tangent_direction().is_none());
+    }
+
+    #[test]
+    fn missing_every_ball_gives_only_a_path() {
+        let state = cut_shot();
+
+        let trajectory = state.trajectory(
+            &CueStroke::new(std::f32::consts::PI, 300.0, 0.0, 0.0, 0.0),
+            10.0,
+        );
+
+        assert!(trajectory.ghost_ball().is_none());
+        assert!(trajectory.deflected_path().is_empty());
+        // The cue ball comes back off the left cushion.
+        let path = trajectory.path();
+        let xs: Vec<f32> = path.iter().step_by(2).copied().collect();
+        let turn = xs.iter().copied().fold(f32::INFINITY, f32::min);
+        assert!(turn < 20.0);
+        assert!(xs[xs.len() - 1] > turn);
+    }
+
+    #[test]
+    fn invalid_stroke_gives_no_trajectory() {
+        let trajectory = cut_shot().trajectory(&CueStroke::new(0.0, f32::NAN, 0.0, 0.0, 0.0), 10.0);
+
+        assert!(trajectory.path().is_empty());
+        assert!(trajectory.deflected_path().is_empty());
+        assert!(trajectory.ghost_ball().is_none());
+        assert!(trajectory.object_ball().is_none());
+    }
+}